@@ -0,0 +1,63 @@
+extern crate rustneat;
+
+use rustneat::dataset::DatasetEnvironment;
+use rustneat::distributed::{Coordinator, Worker};
+use rustneat::{NeatParams, NeuralNetwork, Population};
+use std::env;
+use std::process::Command;
+use std::time::Duration;
+
+/// Number of worker processes spawned by the coordinator
+const N_WORKERS: usize = 4;
+
+/// Run without arguments to start a coordinator, which spawns workers on
+/// localhost by running this example again as `worker <address>`. Workers on
+/// other machines can connect to the same address.
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+    if args.len() == 3 && args[1] == "worker" {
+        let mut worker = Worker::new();
+        worker.register("xor", DatasetEnvironment::xor());
+        worker.run(&args[2]).unwrap();
+        return;
+    }
+
+    let mut coordinator: Coordinator = Coordinator::bind("127.0.0.1:0", "xor").unwrap();
+    let address = coordinator.local_addr().to_string();
+    let mut workers = (0..N_WORKERS)
+        .map(|_| {
+            Command::new(env::current_exe().unwrap())
+                .args(["worker", &address])
+                .spawn()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert!(coordinator.wait_for_workers(N_WORKERS, Duration::from_secs(10)));
+    println!("{} workers connected to {}", N_WORKERS, address);
+
+    let p = NeatParams::optimized_for_xor3(2, 1);
+    let mut start_genome = NeuralNetwork::with_neurons(3);
+    start_genome.add_connection(0, 2, 1.0);
+    start_genome.add_connection(1, 2, 1.0);
+    let mut population = Population::create_population_from(start_genome, 150);
+    let mut champion = population.get_champion();
+    while champion.fitness < 15.0 && population.generation() < 100 {
+        population.evolve(&mut coordinator, &p, true);
+        for failure in coordinator.take_failures() {
+            eprintln!("{}", failure);
+        }
+        champion = population.get_champion();
+        println!(
+            "Generation {}: fitness {}",
+            population.generation(),
+            champion.fitness
+        );
+    }
+    println!("{:?}", champion.genome);
+
+    // Closing the connections ends the workers
+    drop(coordinator);
+    for worker in &mut workers {
+        worker.wait().unwrap();
+    }
+}
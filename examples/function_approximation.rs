@@ -8,6 +8,7 @@ extern crate rusty_dashed;
 #[cfg(feature = "telemetry")]
 mod telemetry_helper;
 
+use rustneat::refine::{Optimizer, Refinement};
 use rustneat::{Environment, NeatParams, NeuralNetwork, Organism, Population};
 
 static mut BEST_FITNESS: f64 = 0.0;
@@ -44,6 +45,12 @@ fn main() {
     let mut population = Population::create_population(150);
     let mut environment = FunctionApproximation;
     let mut champion: Option<Organism> = None;
+    // Fine-tune the weights of the 10 best organisms of every generation
+    let refinement = Refinement {
+        top_k: Some(10),
+        ..Refinement::new(Optimizer::CmaEs { sigma: 0.5 })
+    };
+    let loss = |network: &NeuralNetwork| -FunctionApproximation.test(&mut network.clone());
 
     #[cfg(feature = "telemetry")]
     telemetry_helper::enable_telemetry("?max_fitness=100&ioNeurons=1,2", true);
@@ -62,7 +69,8 @@ fn main() {
     std::thread::sleep(std::time::Duration::from_millis(2000));
 
     while champion.is_none() {
-        population.evolve(&mut environment, &p);
+        population.evolve(&mut environment, &p, true);
+        population.refine(&environment, &refinement, loss, true);
         for organism in population.get_organisms() {
             if organism.fitness >= 96f64 {
                 champion = Some(organism.clone());
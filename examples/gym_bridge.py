@@ -0,0 +1,55 @@
+#!/usr/bin/env python3
+"""Runs a Gym environment for rustneat's ProcessEnvironment.
+
+Usage: gym_bridge.py ENVIRONMENT [--render]
+
+Speaks the JSON-lines protocol of `rustneat::process` on stdin/stdout. For
+discrete action spaces, a single network output is rounded to the action, and
+several outputs are reduced to the index of the largest one.
+"""
+import json
+import sys
+
+try:
+    import gymnasium as gym
+except ImportError:
+    import gym
+
+name = sys.argv[1]
+render = "--render" in sys.argv[2:]
+env = gym.make(name, render_mode="human") if render else gym.make(name)
+discrete = hasattr(env.action_space, "n")
+
+
+def to_list(observation):
+    return [float(x) for x in getattr(observation, "flat", observation)]
+
+
+def to_action(outputs):
+    if not discrete:
+        return outputs
+    if len(outputs) == 1:
+        return min(max(int(round(outputs[0])), 0), env.action_space.n - 1)
+    return max(range(len(outputs)), key=lambda i: outputs[i])
+
+
+for line in sys.stdin:
+    message = json.loads(line)
+    if message["type"] == "reset":
+        result = env.reset(seed=message["episode"])
+        # Older versions of gym only return the observation
+        observation = result[0] if isinstance(result, tuple) else result
+        reply = {"observation": to_list(observation)}
+    else:
+        result = env.step(to_action(message["action"]))
+        if len(result) == 5:
+            observation, reward, terminated, truncated, _ = result
+            done = terminated or truncated
+        else:
+            observation, reward, done, _ = result
+        reply = {
+            "observation": to_list(observation),
+            "reward": float(reward),
+            "done": bool(done),
+        }
+    print(json.dumps(reply), flush=True)
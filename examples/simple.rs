@@ -1,33 +1,12 @@
 extern crate rand;
 extern crate rustneat;
 
-use rustneat::{Environment, NeatParams, NeuralNetwork, Organism, Population};
+use rustneat::dataset::DatasetEnvironment;
+use rustneat::{NeatParams, NeuralNetwork, Organism, Population};
 
 #[cfg(feature = "telemetry")]
 mod telemetry_helper;
 
-struct XORClassification;
-
-impl Environment for XORClassification {
-    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
-        let nn = organism.make_network();
-        let mut output = vec![0f64];
-        let mut distance: f64;
-        nn.activate(vec![0f64, 0f64], &mut output);
-        distance = (0f64 - output[0]).powi(2);
-        nn.activate(vec![0f64, 1f64], &mut output);
-        distance += (1f64 - output[0]).powi(2);
-        nn.activate(vec![1f64, 0f64], &mut output);
-        distance += (1f64 - output[0]).powi(2);
-        nn.activate(vec![1f64, 1f64], &mut output);
-        distance += (0f64 - output[0]).powi(2);
-
-        let fitness = 16.0 / (1.0 + distance);
-
-        fitness
-    }
-}
-
 fn main() {
     #[cfg(feature = "telemetry")]
     telemetry_helper::enable_telemetry("?max_fitness=16", true);
@@ -42,7 +21,7 @@ fn main() {
     start_genome.add_connection(0, 2, 1.0);
     start_genome.add_connection(1, 2, 1.0);
     let mut population = Population::create_population_from(start_genome, 150);
-    let mut environment = XORClassification;
+    let mut environment = DatasetEnvironment::xor();
     let mut champion: Option<Organism> = None;
     let mut best_organism = None;
     let mut best_fitness = 0.0;
@@ -0,0 +1,235 @@
+use crate::{Environment, Genome, NeatParams, NeuralNetwork, Organism, Population};
+use rand::seq::SliceRandom;
+use rayon::prelude::*;
+
+/// Which islands of an `Archipelago` send migrants to which
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    /// Island `i` sends to island `i + 1`, the last one to the first one
+    Ring,
+    /// Every island sends to every other island
+    FullyConnected,
+    /// Every island sends to one other island, chosen at random at every
+    /// migration
+    Random,
+}
+
+/// Island model: several `Population`s that evolve independently, and
+/// periodically exchange their best organisms. Keeping the populations apart
+/// preserves diversity, which delays premature convergence on hard problems.
+///
+/// Each island can use its own `NeatParams`. The islands share their
+/// innovation ids, so that the genes of a migrant mean the same in every
+/// island: offspring are created one island after the other, and only the
+/// evaluation runs in parallel.
+#[derive(Debug)]
+pub struct Archipelago<G: Genome = NeuralNetwork> {
+    /// The sub-populations
+    pub islands: Vec<Population<G>>,
+    /// How migrants travel between the islands
+    pub topology: Topology,
+    /// Number of generations between migrations, or 0 to never migrate
+    pub migration_interval: usize,
+    /// Number of organisms each island sends to each of its destinations
+    pub n_migrants: usize,
+    /// The next innovation id, shared by all islands
+    innovation_id: usize,
+    /// Number of calls to `evolve()` so far
+    generation: usize,
+}
+
+impl<G: Genome> Archipelago<G> {
+    /// Create `n_islands` islands of `island_size` organisms each, connected
+    /// in a ring. Two organisms migrate every ten generations.
+    pub fn create_archipelago(n_islands: usize, island_size: usize) -> Archipelago<G> {
+        Self::from_islands(
+            (0..n_islands)
+                .map(|_| Population::create_population(island_size))
+                .collect(),
+        )
+    }
+    /// Create an archipelago from existing populations.
+    pub fn from_islands(islands: Vec<Population<G>>) -> Archipelago<G> {
+        assert!(!islands.is_empty());
+        let innovation_id = islands
+            .iter()
+            .map(|island| island.innovation_id())
+            .max()
+            .unwrap();
+        Archipelago {
+            islands,
+            topology: Topology::Ring,
+            migration_interval: 10,
+            n_migrants: 2,
+            innovation_id,
+            generation: 0,
+        }
+    }
+
+    /// Counts the number of organisms on all islands
+    pub fn size(&self) -> usize {
+        self.islands.iter().map(|island| island.size()).sum()
+    }
+    /// The current generation, i.e. the number of calls to `evolve()`
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+    /// Get the best-performing organism of all islands.
+    pub fn get_champion(&self) -> Organism<G> {
+        self.islands
+            .iter()
+            .map(|island| island.get_champion())
+            .fold(None, |best: Option<Organism<G>>, champion| match best {
+                Some(best) if best.fitness >= champion.fitness => Some(best),
+                _ => Some(champion),
+            })
+            .unwrap()
+    }
+
+    /// Evolve every island to the next generation (see
+    /// `Population::evolve()`), and migrate if it is time to. `params` holds
+    /// either one `NeatParams` for all islands, or one per island.
+    pub fn evolve(
+        &mut self,
+        env: &mut dyn Environment<G>,
+        params: &[NeatParams],
+        in_parallel: bool,
+    ) {
+        assert!(
+            params.len() == 1 || params.len() == self.islands.len(),
+            "need one NeatParams for all islands or one per island"
+        );
+        self.generation += 1;
+        for (i, island) in self.islands.iter_mut().enumerate() {
+            island.set_innovation_id(self.innovation_id);
+            island.reproduce(&params[i % params.len()]);
+            self.innovation_id = island.innovation_id();
+        }
+
+        let env: &dyn Environment<G> = env;
+        if in_parallel {
+            let streams = crate::rng::streams();
+            self.islands
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(i, island)| streams.run(i, || island.evaluate(env, true)));
+        } else {
+            for island in &mut self.islands {
+                island.evaluate(env, false);
+            }
+        }
+
+        if self.generation.checked_rem(self.migration_interval) == Some(0) {
+            self.migrate();
+        }
+    }
+
+    /// Send the best organisms of every island to its destinations, where
+    /// they replace the worst ones. All migrants are chosen before any
+    /// arrives.
+    pub fn migrate(&mut self) {
+        let n = self.islands.len();
+        if n < 2 || self.n_migrants == 0 {
+            return;
+        }
+        let mut rng = crate::rng::thread_rng();
+        let emigrants = self
+            .islands
+            .iter()
+            .map(|island| island.fittest(self.n_migrants))
+            .collect::<Vec<_>>();
+        for (source, migrants) in emigrants.into_iter().enumerate() {
+            let destinations = match self.topology {
+                Topology::Ring => vec![(source + 1) % n],
+                Topology::FullyConnected => (0..n).filter(|i| *i != source).collect(),
+                Topology::Random => {
+                    let others = (0..n).filter(|i| *i != source).collect::<Vec<_>>();
+                    vec![*others.choose(&mut rng).unwrap()]
+                }
+            };
+            for destination in destinations {
+                self.islands[destination].immigrate(migrants.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mutation;
+
+    struct Connections;
+    impl Environment<NeuralNetwork> for Connections {
+        fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+            organism.n_connections() as f64
+        }
+    }
+
+    #[test]
+    fn archipelago_should_keep_its_size_and_share_innovation_ids() {
+        let mut p = NeatParams::default(2, 1);
+        p.mutate_add_neuron_pr = 0.5;
+        let mut other = p.clone();
+        other.mutate_add_conn_pr = 0.9;
+        let mut archipelago = Archipelago::<NeuralNetwork>::create_archipelago(3, 20);
+        archipelago.topology = Topology::FullyConnected;
+        archipelago.migration_interval = 2;
+        for island in &mut archipelago.islands {
+            island.enable_phylogeny();
+        }
+        for _ in 0..10 {
+            archipelago.evolve(
+                &mut Connections,
+                &[p.clone(), other.clone(), p.clone()],
+                true,
+            );
+            assert_eq!(archipelago.size(), 60);
+        }
+        assert_eq!(archipelago.generation(), 10);
+
+        // Every neuron added by mutation got an id no other island used
+        let mut created = Vec::new();
+        for island in &archipelago.islands {
+            for record in island.phylogeny().unwrap().organisms.values() {
+                for mutation in &record.mutations {
+                    if let Mutation::AddNeuron { neuron_id } = mutation {
+                        created.push(*neuron_id);
+                    }
+                }
+            }
+        }
+        assert!(!created.is_empty());
+        let n_created = created.len();
+        created.sort();
+        created.dedup();
+        assert_eq!(created.len(), n_created);
+    }
+
+    #[test]
+    fn migration_should_replace_worst_organisms() {
+        let p = NeatParams::default(1, 1);
+        let mut archipelago = Archipelago::<NeuralNetwork>::create_archipelago(2, 5);
+        archipelago.evolve(&mut Connections, &[p], false);
+        let mut best = archipelago.islands[0].get_champion();
+        best.genome = NeuralNetwork::with_neurons(3);
+        for i in 0..3 {
+            best.genome.add_connection(i, (i + 1) % 3, 1.0);
+        }
+        best.fitness = 1000.0;
+        archipelago.islands[0].immigrate(vec![best]);
+        archipelago.migrate();
+        let champion = archipelago.islands[1].get_champion();
+        assert_eq!(champion.fitness, 1000.0);
+        assert!(champion.parents.is_empty());
+        assert_eq!(archipelago.islands[1].size(), 5);
+        let ids = archipelago.islands[1]
+            .get_organisms()
+            .map(|o| o.id)
+            .collect::<Vec<_>>();
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(ids.len(), unique.len());
+    }
+}
@@ -0,0 +1,119 @@
+use super::{clamp_output, control_params, Benchmark};
+use crate::{Environment, NeatParams, NeuralNetwork};
+
+const GRAVITY: f64 = 9.8;
+const MASS_CART: f64 = 1.0;
+const MASS_POLE: f64 = 0.1;
+/// Half the length of the pole
+const LENGTH: f64 = 0.5;
+const FORCE: f64 = 10.0;
+const TIME_STEP: f64 = 0.02;
+const MAX_POSITION: f64 = 2.4;
+/// 12 degrees
+const MAX_ANGLE: f64 = 0.2094384;
+
+/// Balance a pole on a cart, by pushing the cart left or right with a fixed
+/// force, like `CartPole` in OpenAI Gym. The cart must stay within 2.4 m of
+/// the center, and the pole within 12 degrees of upright.
+///
+/// The controller observes the position and velocity of the cart and the angle
+/// and angular velocity of the pole, scaled to about `[-1, 1]`, and pushes
+/// right if its output is above 0.5. The fitness is the fraction of
+/// `max_steps` the pole stays up, averaged over several starting states.
+#[derive(Debug, Clone)]
+pub struct CartPole {
+    /// Length of an episode
+    pub max_steps: usize,
+}
+
+impl CartPole {
+    /// Episodes of 500 steps (10 s), like Gym's `CartPole-v1`
+    pub fn new() -> CartPole {
+        CartPole { max_steps: 500 }
+    }
+
+    /// Starting states `[x, x', theta, theta']`
+    fn starts() -> [[f64; 4]; 4] {
+        [
+            [0.0, 0.0, 0.05, 0.0],
+            [0.0, 0.0, -0.1, 0.0],
+            [1.0, 0.5, 0.0, -0.3],
+            [-1.5, 0.0, 0.1, 0.5],
+        ]
+    }
+
+    /// Number of steps the pole stays up from `state`, up to `max_steps`
+    fn balance(&self, network: &NeuralNetwork, mut state: [f64; 4], max_steps: usize) -> usize {
+        let mut runner = network.make_stateful();
+        let mut output = [0.0];
+        for step in 0..max_steps {
+            let [x, x_dot, theta, theta_dot] = state;
+            runner.step(
+                &[
+                    x / MAX_POSITION,
+                    x_dot / 2.0,
+                    theta / MAX_ANGLE,
+                    theta_dot / 2.0,
+                ],
+                &mut output,
+            );
+            let force = if clamp_output(output[0]) > 0.5 {
+                FORCE
+            } else {
+                -FORCE
+            };
+
+            let (sin, cos) = theta.sin_cos();
+            let total_mass = MASS_CART + MASS_POLE;
+            let temp = (force + MASS_POLE * LENGTH * theta_dot * theta_dot * sin) / total_mass;
+            let theta_acc = (GRAVITY * sin - cos * temp)
+                / (LENGTH * (4.0 / 3.0 - MASS_POLE * cos * cos / total_mass));
+            let x_acc = temp - MASS_POLE * LENGTH * theta_acc * cos / total_mass;
+            state = [
+                x + TIME_STEP * x_dot,
+                x_dot + TIME_STEP * x_acc,
+                theta + TIME_STEP * theta_dot,
+                theta_dot + TIME_STEP * theta_acc,
+            ];
+            if state[0].abs() > MAX_POSITION || state[2].abs() > MAX_ANGLE {
+                return step;
+            }
+        }
+        max_steps
+    }
+}
+
+impl Default for CartPole {
+    fn default() -> CartPole {
+        CartPole::new()
+    }
+}
+
+impl Environment for CartPole {
+    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+        let starts = CartPole::starts();
+        starts
+            .iter()
+            .map(|start| self.balance(organism, *start, self.max_steps) as f64)
+            .sum::<f64>()
+            / (starts.len() * self.max_steps) as f64
+    }
+}
+
+impl Benchmark for CartPole {
+    fn n_inputs(&self) -> usize {
+        4
+    }
+    fn n_outputs(&self) -> usize {
+        1
+    }
+    fn params(&self) -> NeatParams {
+        control_params(4, 1)
+    }
+    /// Balances from every starting state for the whole episode
+    fn solves(&self, genome: &NeuralNetwork) -> bool {
+        CartPole::starts()
+            .iter()
+            .all(|start| self.balance(genome, *start, self.max_steps) == self.max_steps)
+    }
+}
@@ -0,0 +1,58 @@
+use super::{bit_input, memory_params, play, Benchmark, Trial};
+use crate::{Environment, NeatParams, NeuralNetwork};
+
+/// Reproduce a sequence of bits after seeing all of it. The bits are fed one
+/// per tick as `[1, 0]` or `[-1, 0]`, followed by a delimiter `[0, 1]`, and
+/// then `length` ticks of `[0, 0]` after which the output must be the bits
+/// (1 or 0), in order. Every sequence of `length` bits is tried.
+#[derive(Debug, Clone)]
+pub struct CopyTask {
+    /// Length of the sequences
+    pub length: usize,
+}
+
+impl CopyTask {
+    /// Sequences of `length` bits
+    pub fn new(length: usize) -> CopyTask {
+        CopyTask { length }
+    }
+
+    fn trials(&self) -> Vec<Trial> {
+        (0..1usize << self.length)
+            .map(|sequence| {
+                let bit = |i: usize| sequence >> i & 1 == 1;
+                let mut trial = Trial::new();
+                for i in 0..self.length {
+                    trial.tick(vec![bit_input(bit(i)), 0.0], None);
+                }
+                trial.tick(vec![0.0, 1.0], None);
+                for i in 0..self.length {
+                    trial.tick(vec![0.0, 0.0], Some(bit(i) as usize as f64));
+                }
+                trial
+            })
+            .collect()
+    }
+}
+
+impl Environment for CopyTask {
+    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+        play(organism, &self.trials()).0
+    }
+}
+
+impl Benchmark for CopyTask {
+    fn n_inputs(&self) -> usize {
+        2
+    }
+    fn n_outputs(&self) -> usize {
+        1
+    }
+    fn params(&self) -> NeatParams {
+        memory_params(2, 1)
+    }
+    /// Copies every bit of every sequence
+    fn solves(&self, genome: &NeuralNetwork) -> bool {
+        play(genome, &self.trials()).1
+    }
+}
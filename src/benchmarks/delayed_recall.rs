@@ -0,0 +1,57 @@
+use super::{bit_input, memory_params, play, Benchmark, Trial};
+use crate::{Environment, NeatParams, NeuralNetwork};
+
+/// Remember a bit over a delay. The bit is fed as `[1, 0]` or `[-1, 0]`,
+/// followed by `delay` ticks of `[0, 0]` and a query tick `[0, 1]`, after
+/// which the output must be the bit (1 or 0). Both bits are tried with every
+/// delay from 1 to `max_delay`.
+#[derive(Debug, Clone)]
+pub struct DelayedRecall {
+    /// Longest delay between the bit and the query
+    pub max_delay: usize,
+}
+
+impl DelayedRecall {
+    /// Delays from 1 to `max_delay` ticks
+    pub fn new(max_delay: usize) -> DelayedRecall {
+        DelayedRecall { max_delay }
+    }
+
+    fn trials(&self) -> Vec<Trial> {
+        let mut trials = Vec::new();
+        for delay in 1..=self.max_delay {
+            for &bit in &[false, true] {
+                let mut trial = Trial::new();
+                trial.tick(vec![bit_input(bit), 0.0], None);
+                for _ in 0..delay {
+                    trial.tick(vec![0.0, 0.0], None);
+                }
+                trial.tick(vec![0.0, 1.0], Some(bit as usize as f64));
+                trials.push(trial);
+            }
+        }
+        trials
+    }
+}
+
+impl Environment for DelayedRecall {
+    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+        play(organism, &self.trials()).0
+    }
+}
+
+impl Benchmark for DelayedRecall {
+    fn n_inputs(&self) -> usize {
+        2
+    }
+    fn n_outputs(&self) -> usize {
+        1
+    }
+    fn params(&self) -> NeatParams {
+        memory_params(2, 1)
+    }
+    /// Recalls both bits after every delay
+    fn solves(&self, genome: &NeuralNetwork) -> bool {
+        play(genome, &self.trials()).1
+    }
+}
@@ -0,0 +1,237 @@
+use super::{clamp_output, control_params, Benchmark};
+use crate::{Environment, NeatParams, NeuralNetwork};
+
+const GRAVITY: f64 = -9.8;
+const MASS_CART: f64 = 1.0;
+const MASS_POLE_1: f64 = 0.1;
+/// Half the length of the long pole
+const LENGTH_1: f64 = 0.5;
+const MASS_POLE_2: f64 = 0.01;
+/// Half the length of the short pole
+const LENGTH_2: f64 = 0.05;
+/// Friction of the poles' hinges
+const MU_P: f64 = 0.000002;
+const FORCE: f64 = 10.0;
+/// Integration time step; every action lasts two of them
+const TAU: f64 = 0.01;
+const MAX_POSITION: f64 = 2.4;
+/// 36 degrees
+const MAX_ANGLE: f64 = 0.628329;
+/// Length of the evolution episodes without velocities, which the Gruau
+/// fitness is normalized by
+const GRUAU_STEPS: usize = 1000;
+/// Number of final steps over which the Gruau fitness measures oscillations
+const WIGGLE_STEPS: usize = 100;
+/// Length of the test that a solution must pass
+const SOLUTION_STEPS: usize = 100_000;
+
+/// `[x, x', theta_1, theta_1', theta_2, theta_2']`
+type State = [f64; 6];
+
+/// Balance two poles of different lengths on the same cart, the classic NEAT
+/// benchmark (Stanley & Miikkulainen 2002, after Wieland 1991). The cart must
+/// stay within 2.4 m of the center, and both poles within 36 degrees of
+/// upright, starting with the long pole at 4 degrees. The output of the
+/// controller in `[0, 1]` is mapped to a force between -10 N and 10 N.
+///
+/// With velocities, the controller observes the full state of the cart and the
+/// poles, and the fitness is the fraction of `max_steps` both poles stay up.
+/// Without velocities, it only observes the position and the angles, and must
+/// estimate velocities from its own recurrent state. Then the fitness is the
+/// Gruau "anti-wiggle" fitness, which rewards balancing without oscillating:
+/// `0.1 * steps / 1000 + 0.9 * 0.75 / wiggle`, where `wiggle` is the sum of
+/// `|x| + |x'| + |theta_1| + |theta_1'|` over the last 100 steps (the second
+/// term is 0 if the poles fall within 100 steps). As in the original, the
+/// last input is a constant 0.5, which networks use as a bias.
+///
+/// `params()` only solves the version with velocities. Networks settle every
+/// activation (`STEPS` steps of `DELTA_T = TAU`), so they keep little memory
+/// between activations, and evolution has not solved the version without
+/// velocities with any parameters so far: the best controllers balance for a
+/// few hundred steps. Its fitness still measures the progress of evolution.
+#[derive(Debug, Clone)]
+pub struct DoublePole {
+    /// Whether the controller observes the velocities
+    pub velocities: bool,
+    /// Length of an episode
+    pub max_steps: usize,
+}
+
+impl DoublePole {
+    /// The Markovian version: full state, episodes of 100 000 steps (30 min)
+    pub fn with_velocities() -> DoublePole {
+        DoublePole {
+            velocities: true,
+            max_steps: SOLUTION_STEPS,
+        }
+    }
+
+    /// The non-Markovian version: position and angles only, episodes of 1000
+    /// steps with the Gruau fitness
+    pub fn without_velocities() -> DoublePole {
+        DoublePole {
+            velocities: false,
+            max_steps: GRUAU_STEPS,
+        }
+    }
+
+    /// The state the poles start from during evolution
+    fn start() -> State {
+        [0.0, 0.0, 0.07, 0.0, 0.0, 0.0]
+    }
+
+    fn observe(&self, state: &State) -> Vec<f64> {
+        if self.velocities {
+            vec![
+                state[0] / 4.8,
+                state[1] / 2.0,
+                state[2] / 0.52,
+                state[3] / 2.0,
+                state[4] / 0.52,
+                state[5] / 2.0,
+                0.5,
+            ]
+        } else {
+            vec![state[0] / 4.8, state[2] / 0.52, state[4] / 0.52, 0.5]
+        }
+    }
+
+    /// Balance from `state` for up to `max_steps`. Returns the number of steps
+    /// both poles stayed up, and the sum of the wiggle over the last 100 of
+    /// them.
+    fn balance(&self, network: &NeuralNetwork, mut state: State, max_steps: usize) -> (usize, f64) {
+        let mut runner = network.make_stateful();
+        let mut output = [0.0];
+        let mut wiggles = vec![0.0; WIGGLE_STEPS];
+        for step in 0..max_steps {
+            runner.step(&self.observe(&state), &mut output);
+            let force = (clamp_output(output[0]) - 0.5) * 2.0 * FORCE;
+            for _ in 0..2 {
+                state = rk4(force, &state);
+            }
+            wiggles[step % WIGGLE_STEPS] =
+                state[0].abs() + state[1].abs() + state[2].abs() + state[3].abs();
+            if state[0].abs() > MAX_POSITION
+                || state[2].abs() > MAX_ANGLE
+                || state[4].abs() > MAX_ANGLE
+            {
+                return (step, wiggles.iter().sum());
+            }
+        }
+        (max_steps, wiggles.iter().sum())
+    }
+
+    /// The generalization test of Gruau et al. (1996): balance for 1000 steps
+    /// from at least 200 of 625 starting states, spread over the position and
+    /// velocity of the cart and the angle and angular velocity of the long
+    /// pole
+    fn generalizes(&self, network: &NeuralNetwork) -> bool {
+        let levels = [0.05, 0.25, 0.5, 0.75, 0.95];
+        let mut successes = 0;
+        for x in &levels {
+            for x_dot in &levels {
+                for theta in &levels {
+                    for theta_dot in &levels {
+                        let start = [
+                            x * 4.32 - 2.16,
+                            x_dot * 2.70 - 1.35,
+                            theta * 0.12566304 - 0.06283152,
+                            theta_dot * 0.30019504 - 0.15009752,
+                            0.0,
+                            0.0,
+                        ];
+                        if self.balance(network, start, GRUAU_STEPS).0 == GRUAU_STEPS {
+                            successes += 1;
+                        }
+                    }
+                }
+            }
+        }
+        successes >= 200
+    }
+}
+
+/// Derivatives of the state when applying `force`
+fn derivatives(force: f64, state: &State) -> State {
+    let (sin_1, cos_1) = state[2].sin_cos();
+    let (sin_2, cos_2) = state[4].sin_cos();
+    let ml_1 = LENGTH_1 * MASS_POLE_1;
+    let ml_2 = LENGTH_2 * MASS_POLE_2;
+    let friction_1 = MU_P * state[3] / ml_1;
+    let friction_2 = MU_P * state[5] / ml_2;
+    let fi_1 = ml_1 * state[3] * state[3] * sin_1
+        + 0.75 * MASS_POLE_1 * cos_1 * (friction_1 + GRAVITY * sin_1);
+    let fi_2 = ml_2 * state[5] * state[5] * sin_2
+        + 0.75 * MASS_POLE_2 * cos_2 * (friction_2 + GRAVITY * sin_2);
+    let mi_1 = MASS_POLE_1 * (1.0 - 0.75 * cos_1 * cos_1);
+    let mi_2 = MASS_POLE_2 * (1.0 - 0.75 * cos_2 * cos_2);
+    let x_acc = (force + fi_1 + fi_2) / (mi_1 + mi_2 + MASS_CART);
+    [
+        state[1],
+        x_acc,
+        state[3],
+        -0.75 * (x_acc * cos_1 + GRAVITY * sin_1 + friction_1) / LENGTH_1,
+        state[5],
+        -0.75 * (x_acc * cos_2 + GRAVITY * sin_2 + friction_2) / LENGTH_2,
+    ]
+}
+
+/// One fourth-order Runge-Kutta step of `TAU`
+fn rk4(force: f64, state: &State) -> State {
+    let add = |a: &State, b: &State, h: f64| {
+        let mut sum = *a;
+        for (s, b) in sum.iter_mut().zip(b) {
+            *s += h * b;
+        }
+        sum
+    };
+    let k1 = derivatives(force, state);
+    let k2 = derivatives(force, &add(state, &k1, TAU / 2.0));
+    let k3 = derivatives(force, &add(state, &k2, TAU / 2.0));
+    let k4 = derivatives(force, &add(state, &k3, TAU));
+    let mut next = *state;
+    for i in 0..6 {
+        next[i] += TAU / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]);
+    }
+    next
+}
+
+impl Environment for DoublePole {
+    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+        let (steps, wiggle) = self.balance(organism, DoublePole::start(), self.max_steps);
+        if self.velocities {
+            steps as f64 / self.max_steps as f64
+        } else {
+            let balanced = 0.1 * steps as f64 / GRUAU_STEPS as f64;
+            if steps < WIGGLE_STEPS {
+                balanced
+            } else {
+                balanced + 0.9 * 0.75 / wiggle
+            }
+        }
+    }
+}
+
+impl Benchmark for DoublePole {
+    fn n_inputs(&self) -> usize {
+        if self.velocities {
+            7
+        } else {
+            4
+        }
+    }
+    fn n_outputs(&self) -> usize {
+        1
+    }
+    /// Solve the version with velocities. Without velocities, they do not
+    /// solve it (see `DoublePole`).
+    fn params(&self) -> NeatParams {
+        control_params(self.n_inputs(), 1)
+    }
+    /// Balances for 100 000 steps from the usual start. Without velocities,
+    /// also passes the generalization test of Gruau et al.
+    fn solves(&self, genome: &NeuralNetwork) -> bool {
+        self.balance(genome, DoublePole::start(), SOLUTION_STEPS).0 == SOLUTION_STEPS
+            && (self.velocities || self.generalizes(genome))
+    }
+}
@@ -0,0 +1,162 @@
+//! Classic control benchmarks, simulated in Rust: single pole balancing,
+//! double pole balancing with and without velocity inputs, and mountain car.
+//! Each benchmark is an `Environment` for `NeuralNetwork`s. The control tasks
+//! know parameters with which evolution solves them, so they double as
+//! regression tests for the quality of evolution; the exception is double
+//! pole balancing without velocities, which is not solved yet (see
+//! `DoublePole`).
+//!
+//! Sequence tasks test memory instead: N-bit parity, delayed recall, the copy
+//! task and a T-maze feed their inputs tick by tick, and score the outputs at
+//! the ticks where the answer is due. They tell whether changes to the
+//! networks or to evolution improve recurrent capabilities: so far, only
+//! short delayed recalls and T-mazes are solved reliably.
+//!
+//! Controllers are run with `make_stateful()`, so that recurrent networks can
+//! remember past observations, and reset at the start of every episode. All
+//! starting states are fixed, which makes the fitness deterministic.
+//!
+//! ```
+//! use rustneat::benchmarks::{Benchmark, CartPole};
+//! use rustneat::Environment;
+//!
+//! let benchmark = CartPole::new();
+//! let mut genome = benchmark.initial_genome();
+//! assert!(benchmark.test(&mut genome) < 1.0);
+//! ```
+
+use crate::{Environment, NeatParams, NeuralNetwork};
+
+mod cart_pole;
+mod copy_task;
+mod delayed_recall;
+mod double_pole;
+mod mountain_car;
+mod parity;
+mod t_maze;
+pub use self::cart_pole::*;
+pub use self::copy_task::*;
+pub use self::delayed_recall::*;
+pub use self::double_pole::*;
+pub use self::mountain_car::*;
+pub use self::parity::*;
+pub use self::t_maze::*;
+
+/// A task with a test of whether a controller solves it
+pub trait Benchmark: Environment {
+    /// Number of observations given to the controller
+    fn n_inputs(&self) -> usize;
+    /// Number of outputs of the controller
+    fn n_outputs(&self) -> usize;
+    /// The best parameters known to evolve controllers for the benchmark
+    /// with. They are not guaranteed to solve it: for tasks without a known
+    /// solution, like double pole balancing without velocities and long
+    /// sequence tasks, evolution with them only makes progress in fitness.
+    fn params(&self) -> NeatParams;
+    /// Whether `genome` solves the benchmark. This can be stricter than a
+    /// high fitness, e.g. balance for longer than during evolution.
+    fn solves(&self, genome: &NeuralNetwork) -> bool;
+    /// The genome to start evolution from: every input connected to every
+    /// output, with zero weights, as in the original NEAT
+    fn initial_genome(&self) -> NeuralNetwork {
+        let n_inputs = self.n_inputs();
+        let n_outputs = self.n_outputs();
+        let mut genome = NeuralNetwork::with_neurons(n_inputs + n_outputs);
+        for input in 0..n_inputs {
+            for output in n_inputs..n_inputs + n_outputs {
+                genome.add_connection(input, output, 0.0);
+            }
+        }
+        genome
+    }
+}
+
+/// Parameters that solve the benchmarks: strong truncation selection, small
+/// perturbations of the weights, and little structural change, since the
+/// initial genomes already connect every input to every output
+fn control_params(n_inputs: usize, n_outputs: usize) -> NeatParams {
+    NeatParams {
+        mutation_pr: 0.75,
+        cull_fraction: 0.95,
+        mutate_add_conn_pr: 0.1,
+        mutate_del_conn_pr: 0.0,
+        mutate_add_neuron_pr: 0.03,
+        mutate_del_neuron_pr: 0.0,
+        weight_mutate_var: 0.3,
+        weight_mutate_pr: 0.5,
+        bias_mutate_var: 0.1,
+        bias_mutate_pr: 0.2,
+        ..NeatParams::default(n_inputs, n_outputs)
+    }
+}
+
+/// Parameters for the sequence tasks, which need hidden neurons and recurrent
+/// connections to remember their inputs
+fn memory_params(n_inputs: usize, n_outputs: usize) -> NeatParams {
+    NeatParams {
+        mutate_add_conn_pr: 0.3,
+        mutate_add_neuron_pr: 0.1,
+        ..control_params(n_inputs, n_outputs)
+    }
+}
+
+/// Network outputs are not bounded; controllers read them in `[0, 1]`
+fn clamp_output(output: f64) -> f64 {
+    output.clamp(0.0, 1.0)
+}
+
+/// A sequence of inputs, fed one per tick, with the expected output at some
+/// of the ticks
+struct Trial {
+    inputs: Vec<Vec<f64>>,
+    targets: Vec<Option<f64>>,
+}
+
+impl Trial {
+    fn new() -> Trial {
+        Trial {
+            inputs: Vec::new(),
+            targets: Vec::new(),
+        }
+    }
+
+    /// Add a tick with `input`, and the output expected after it, if scored
+    fn tick(&mut self, input: Vec<f64>, target: Option<f64>) {
+        self.inputs.push(input);
+        self.targets.push(target);
+    }
+}
+
+/// Play the trials with `network`, reset for each. Every scored tick scores
+/// `1 - |target - output|`, with the output clamped to `[0, 1]`, and is
+/// correct if the output is on the same side of 0.5 as the target. Returns
+/// the average score, and whether all ticks were correct.
+fn play(network: &NeuralNetwork, trials: &[Trial]) -> (f64, bool) {
+    let mut runner = network.make_stateful();
+    let mut output = [0.0];
+    let mut score = 0.0;
+    let mut n_scored = 0;
+    let mut all_correct = true;
+    for trial in trials {
+        runner.reset();
+        for (input, target) in trial.inputs.iter().zip(&trial.targets) {
+            runner.step(input, &mut output);
+            if let Some(target) = target {
+                let output = clamp_output(output[0]);
+                score += 1.0 - (target - output).abs();
+                n_scored += 1;
+                all_correct &= (output > 0.5) == (*target > 0.5);
+            }
+        }
+    }
+    (score / n_scored.max(1) as f64, all_correct)
+}
+
+/// A bit, as the input of a sequence task
+fn bit_input(bit: bool) -> f64 {
+    if bit {
+        1.0
+    } else {
+        -1.0
+    }
+}
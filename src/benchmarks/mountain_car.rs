@@ -0,0 +1,95 @@
+use super::{clamp_output, control_params, Benchmark};
+use crate::{Environment, NeatParams, NeuralNetwork};
+
+const MIN_POSITION: f64 = -1.2;
+const MAX_POSITION: f64 = 0.6;
+const MAX_SPEED: f64 = 0.07;
+const GOAL_POSITION: f64 = 0.5;
+const POWER: f64 = 0.001;
+const GRAVITY: f64 = 0.0025;
+
+/// Drive an underpowered car up a hill, like `MountainCar` in OpenAI Gym: the
+/// car has to swing back and forth in the valley to gain momentum. The
+/// controller observes the position and velocity of the car, scaled to about
+/// `[-1, 1]`, and its output in `[0, 1]` is mapped to a thrust between full
+/// left and full right.
+///
+/// From each starting position, reaching the goal at step `t` scores
+/// `1 + (max_steps - t) / max_steps`, so faster is better, and failing scores
+/// how far right the car got, in `[0, 1]`. The fitness is the average score.
+#[derive(Debug, Clone)]
+pub struct MountainCar {
+    /// Length of an episode
+    pub max_steps: usize,
+}
+
+impl MountainCar {
+    /// Episodes of 200 steps, like Gym's `MountainCar-v0`
+    pub fn new() -> MountainCar {
+        MountainCar { max_steps: 200 }
+    }
+
+    /// Starting positions, at rest
+    fn starts() -> [f64; 4] {
+        [-0.6, -0.5, -0.4, -0.2]
+    }
+
+    /// Score of an episode from `position`, see the struct documentation
+    fn drive(&self, network: &NeuralNetwork, mut position: f64) -> f64 {
+        let mut runner = network.make_stateful();
+        let mut output = [0.0];
+        let mut velocity = 0.0;
+        let mut max_position = position;
+        for step in 0..self.max_steps {
+            runner.step(&[(position + 0.3) / 0.9, velocity / MAX_SPEED], &mut output);
+            let thrust = clamp_output(output[0]) * 2.0 - 1.0;
+            velocity += thrust * POWER - GRAVITY * (3.0 * position).cos();
+            velocity = velocity.clamp(-MAX_SPEED, MAX_SPEED);
+            position += velocity;
+            if position < MIN_POSITION {
+                position = MIN_POSITION;
+                velocity = 0.0;
+            }
+            if position >= GOAL_POSITION {
+                return 1.0 + (self.max_steps - step - 1) as f64 / self.max_steps as f64;
+            }
+            max_position = max_position.max(position);
+        }
+        (max_position - MIN_POSITION) / (MAX_POSITION - MIN_POSITION)
+    }
+}
+
+impl Default for MountainCar {
+    fn default() -> MountainCar {
+        MountainCar::new()
+    }
+}
+
+impl Environment for MountainCar {
+    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+        let starts = MountainCar::starts();
+        starts
+            .iter()
+            .map(|start| self.drive(organism, *start))
+            .sum::<f64>()
+            / starts.len() as f64
+    }
+}
+
+impl Benchmark for MountainCar {
+    fn n_inputs(&self) -> usize {
+        2
+    }
+    fn n_outputs(&self) -> usize {
+        1
+    }
+    fn params(&self) -> NeatParams {
+        control_params(2, 1)
+    }
+    /// Reaches the goal from every starting position
+    fn solves(&self, genome: &NeuralNetwork) -> bool {
+        MountainCar::starts()
+            .iter()
+            .all(|start| self.drive(genome, *start) >= 1.0)
+    }
+}
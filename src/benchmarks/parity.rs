@@ -0,0 +1,55 @@
+use super::{bit_input, memory_params, play, Benchmark, Trial};
+use crate::{Environment, NeatParams, NeuralNetwork};
+
+/// Tell whether a sequence of bits has an odd number of ones. The bits are
+/// fed one per tick as `[1, 0]` or `[-1, 0]`, followed by a query tick
+/// `[0, 1]`, after which the output must be 1 for odd parity and 0 otherwise.
+/// Every sequence of `n_bits` bits is tried.
+#[derive(Debug, Clone)]
+pub struct Parity {
+    /// Length of the sequences
+    pub n_bits: usize,
+}
+
+impl Parity {
+    /// Sequences of `n_bits` bits
+    pub fn new(n_bits: usize) -> Parity {
+        Parity { n_bits }
+    }
+
+    fn trials(&self) -> Vec<Trial> {
+        (0..1usize << self.n_bits)
+            .map(|sequence| {
+                let mut trial = Trial::new();
+                for i in 0..self.n_bits {
+                    trial.tick(vec![bit_input(sequence >> i & 1 == 1), 0.0], None);
+                }
+                let odd = sequence.count_ones() % 2 == 1;
+                trial.tick(vec![0.0, 1.0], Some(odd as usize as f64));
+                trial
+            })
+            .collect()
+    }
+}
+
+impl Environment for Parity {
+    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+        play(organism, &self.trials()).0
+    }
+}
+
+impl Benchmark for Parity {
+    fn n_inputs(&self) -> usize {
+        2
+    }
+    fn n_outputs(&self) -> usize {
+        1
+    }
+    fn params(&self) -> NeatParams {
+        memory_params(2, 1)
+    }
+    /// Answers correctly for every sequence
+    fn solves(&self, genome: &NeuralNetwork) -> bool {
+        play(genome, &self.trials()).1
+    }
+}
@@ -0,0 +1,63 @@
+use super::{memory_params, play, Benchmark, Trial};
+use crate::{Environment, NeatParams, NeuralNetwork};
+
+/// The T-maze of Bakker (2002): walk down a corridor and turn towards the
+/// goal at the junction, where the side of the goal was only shown at the
+/// start. The agent advances one cell per tick, observing `[0, 1, 1]` at the
+/// start if the goal is left and `[1, 1, 0]` if it is right, `[1, 0, 1]` in
+/// the corridor and `[0, 1, 0]` at the junction. After the junction, the
+/// output must be 1 to turn right and 0 to turn left. Both sides are tried.
+#[derive(Debug, Clone)]
+pub struct TMaze {
+    /// Number of corridor cells between the start and the junction
+    pub length: usize,
+}
+
+impl TMaze {
+    /// A corridor of `length` cells
+    pub fn new(length: usize) -> TMaze {
+        TMaze { length }
+    }
+
+    fn trials(&self) -> Vec<Trial> {
+        [false, true]
+            .iter()
+            .map(|&right| {
+                let mut trial = Trial::new();
+                let start = if right {
+                    vec![1.0, 1.0, 0.0]
+                } else {
+                    vec![0.0, 1.0, 1.0]
+                };
+                trial.tick(start, None);
+                for _ in 0..self.length {
+                    trial.tick(vec![1.0, 0.0, 1.0], None);
+                }
+                trial.tick(vec![0.0, 1.0, 0.0], Some(right as usize as f64));
+                trial
+            })
+            .collect()
+    }
+}
+
+impl Environment for TMaze {
+    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+        play(organism, &self.trials()).0
+    }
+}
+
+impl Benchmark for TMaze {
+    fn n_inputs(&self) -> usize {
+        3
+    }
+    fn n_outputs(&self) -> usize {
+        1
+    }
+    fn params(&self) -> NeatParams {
+        memory_params(3, 1)
+    }
+    /// Turns towards the goal on both sides
+    fn solves(&self, genome: &NeuralNetwork) -> bool {
+        play(genome, &self.trials()).1
+    }
+}
@@ -1,133 +1,47 @@
-#[macro_use]
-extern crate blackbox_derive;
-#[macro_use]
-extern crate slog;
-
-use blackbox::BlackboxInput;
-use blackbox_derive::make_optimizer;
-use slog::Logger;
-
 use chrono::{Timelike, Utc};
-use rustneat::{Environment, NeatParams, NeuralNetwork, Organism, Population};
-
-struct XORClassification;
-
-impl Environment for XORClassification {
-    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
-        let nn = organism.make_network();
-        let mut output = vec![0f64];
-        let mut distance: f64;
-        nn.activate(vec![0f64, 0f64], &mut output);
-        distance = (0f64 - output[0]).powi(2);
-        nn.activate(vec![0f64, 1f64], &mut output);
-        distance += (1f64 - output[0]).powi(2);
-        nn.activate(vec![1f64, 0f64], &mut output);
-        distance += (1f64 - output[0]).powi(2);
-        nn.activate(vec![1f64, 1f64], &mut output);
-        distance += (0f64 - output[0]).powi(2);
-
-        let fitness = 16.0 / (1.0 + distance);
-
-        fitness
-    }
-}
-
-fn run(p: &NeatParams, n_gen: usize) -> f64 {
-    let start_genome = NeuralNetwork::with_neurons(3);
-    let mut population = Population::create_population_from(start_genome, 200);
-    let mut environment = XORClassification;
-    for _ in 0..n_gen {
-        population.evolve(&mut environment, p,true);
-    }
-
-    let mut best_fitness = 0.0;
-    for organism in population.get_organisms() {
-        if organism.fitness > best_fitness {
-            best_fitness = organism.fitness;
-        }
-    }
-    best_fitness
-}
+use rustneat::dataset::DatasetEnvironment;
+use rustneat::tune::{self, SearchSpace, Strategy, Tuner};
+use rustneat::{NeatParams, NeuralNetwork};
 
-make_optimizer! {
-    Configuration {
-        remove_after_n_generations: usize = 5 .. 40,
-        species_elite: usize = 1 .. 5,
+// Tunes the parameters for XOR. Trials are logged to hyper_opt.jsonl, so that
+// an interrupted search continues where it stopped.
+//
+//     cargo run --release --features cli --bin hyper_opt -- [trials]
 
-        mutation_pr: f64 = 0.2 .. 1.0,
-        interspecie_mate_pr: f64 = 0.0 .. 0.002,
-        cull_fraction: f64 = 0.05 .. 0.3,
+const RANGES: &str = r#"
+remove_after_n_generations = { min = 5, max = 40, integer = true }
+species_elite = { min = 1, max = 5, integer = true }
 
-        // n_conn_to_mutate: 0,
-        mutate_add_conn_pr: f64 = 0.1..0.5,
-        mutate_del_conn_pr: f64 = 0.1..0.5
-        mutate_add_neuron_pr: f64 = 0.01..0.04,
-        mutate_del_neuron_pr: f64 = 0.01..0.04,
+mutation_pr = { min = 0.2, max = 1.0 }
+interspecie_mate_pr = { min = 0.0, max = 0.002 }
+cull_fraction = { min = 0.05, max = 0.3 }
 
-        // weight_init_mean: 0.0,
-        weight_init_var: f64 = 0.5 .. 2.0,
-        weight_mutate_var: f64 = 0.2 .. 2.0,
-        weight_mutate_pr: f64 = 0.2 .. 0.8,
-        weight_replace_pr: f64 = 0.01 .. 0.2,
+mutate_add_conn_pr = { min = 0.1, max = 0.5 }
+mutate_del_conn_pr = { min = 0.1, max = 0.5 }
+mutate_add_neuron_pr = { min = 0.01, max = 0.04 }
+mutate_del_neuron_pr = { min = 0.01, max = 0.04 }
 
-        // bias_init_mean: 0.0,
-        bias_init_var: f64 = 0.5 .. 2.0,
-        bias_mutate_var: f64 = 0.2 .. 2.0,
-        bias_mutate_pr: f64 = 0.2 .. 0.8,
-        bias_replace_pr: f64 = 0.01 .. 0.2,
+weight_init_var = { min = 0.5, max = 2.0 }
+weight_mutate_var = { min = 0.2, max = 2.0, log = true }
+weight_mutate_pr = { min = 0.2, max = 0.8 }
+weight_replace_pr = { min = 0.01, max = 0.2 }
 
-        include_weak_disjoint_gene: f64 = 0.1 .. 0.3,
+bias_init_var = { min = 0.5, max = 2.0 }
+bias_mutate_var = { min = 0.2, max = 2.0, log = true }
+bias_mutate_pr = { min = 0.2, max = 0.8 }
+bias_replace_pr = { min = 0.01, max = 0.2 }
 
-        compatibility_threshold: f64 = 2.0 .. 4.0,
-        distance_weight_coef: f64 = 0.0 .. 0.5,
-        distance_disjoint_coef: f64 = 0.5 .. 1.0,
-    }
+include_weak_disjoint_gene = { min = 0.1, max = 0.3 }
 
-    const N_GEN: usize = 100; // generations per round
-    const N_POPULATIONS: usize = 26; // populations per iteration
-    let p = NeatParams {
-        n_inputs: 2,
-        n_outputs: 1,
-        remove_after_n_generations,
-        species_elite,
-
-        mutation_pr,
-        interspecie_mate_pr,
-        cull_fraction,
-
-        mutate_add_conn_pr,
-        mutate_del_conn_pr,
-        mutate_add_neuron_pr,
-        mutate_del_neuron_pr,
-        include_weak_disjoint_gene,
-
-        weight_init_mean: 0.0,
-        weight_init_var,
-        weight_mutate_var,
-        weight_mutate_pr,
-        weight_replace_pr,
-
-        bias_init_mean: 0.0,
-        bias_init_var,
-        bias_mutate_var,
-        bias_mutate_pr,
-        bias_replace_pr,
-
-        compatibility_threshold,
-        distance_weight_coef,
-        distance_disjoint_coef,
-    };
-    // Take the average of N rounds
-    let score = (0..N_POPULATIONS)
-        .map(|_| run(&p, N_GEN))
-        .sum::<f64>() / N_POPULATIONS as f64;
-    println!("Iteration... Score = {}", score);
-    score
-
-}
+compatibility_threshold = { min = 2.0, max = 4.0 }
+distance_weight_coef = { min = 0.0, max = 0.5 }
+distance_disjoint_coef = { min = 0.5, max = 1.0 }
+"#;
 
 fn main() {
-    let log = slog::Logger::root(slog::Discard, o!());
+    let n_trials = std::env::args()
+        .nth(1)
+        .map_or(2000, |n| n.parse().expect("the number of trials"));
     let now = Utc::now();
     println!(
         "Start: {:02}:{:02}:{:02}",
@@ -136,10 +50,29 @@ fn main() {
         now.second()
     );
 
-    const N_ITER: usize = 2000;
-    let config = Configuration::bayesian_search(12, N_ITER, log.clone());
-    println!("Score: {}", config.evaluate(log));
-    println!("Config: {:?}", config);
+    let base = NeatParams::default(2, 1);
+    let tuner = Tuner::new(
+        SearchSpace::from_toml(RANGES).unwrap(),
+        base.clone(),
+        NeuralNetwork::with_neurons(3),
+    )
+    .with_strategy(Strategy::tpe())
+    .with_seeds((0..26).collect())
+    .with_generations(100)
+    .with_population_size(200)
+    .with_log("hyper_opt.jsonl");
+
+    let trials = tuner
+        .run(&DatasetEnvironment::xor(), n_trials, |trial| {
+            println!("Iteration {}... Score = {}", trial.id, trial.score)
+        })
+        .unwrap();
+    let best = tune::best(&trials).unwrap();
+    println!("Score: {}", best.score);
+    println!(
+        "Config: {:?}",
+        tuner.space().apply(&base, &best.values).unwrap()
+    );
 
     println!("\nExecution time: {}", (Utc::now() - now).num_seconds());
 }
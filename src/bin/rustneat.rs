@@ -0,0 +1,872 @@
+//! Run experiments from a config file, and look at their results.
+//!
+//! ```text
+//! rustneat run <config> [--out <dir>]
+//! rustneat resume <checkpoint> [--generations <n>] [--out <dir>]
+//! rustneat inspect <genome> [--inputs <n> --outputs <n>]
+//! rustneat render <genome> [--format dot|svg] [--inputs <n> --outputs <n>] [-o <file>]
+//! rustneat simplify <genome> [--tolerance <w>] [--inputs <n> --outputs <n>] [-o <file>]
+//! rustneat diff <genome> <genome> [--format text|json] [--params <file>]
+//! rustneat eval <config> <genome>
+//! rustneat tune <config> [--out <dir>]
+//! ```
+//!
+//! The config is TOML, or JSON if the file name ends in `.json`:
+//!
+//! ```toml
+//! population = 150            # number of organisms, 150 by default
+//! seed = 42                   # makes the run reproducible, random by default
+//! parallel = true             # evaluate on all cores, the default
+//! output = "runs/cart_pole"   # where to write results, "rustneat-run" by default
+//! checkpoint_every = 10       # generations between checkpoints, 10 by default
+//!
+//! [environment]
+//! type = "cart_pole"
+//!
+//! [params]                    # overrides of the environment's NeatParams
+//! mutation_pr = 0.8
+//!
+//! [stop]                      # stop at the first criterion that is met
+//! generations = 100           # after this many generations
+//! fitness = 0.99              # when the champion reaches this fitness
+//! stagnation = 30             # after this many generations without improvement
+//! solved = true               # when the champion solves the benchmark (only
+//!                             # for benchmark environments)
+//! ```
+//!
+//! The environments are `xor`, `dataset` (`path`, `inputs`, and optionally
+//! `loss` and `validation`, the fraction of examples held out), the benchmarks
+//! `cart_pole`, `double_pole` (`velocities`, true by default), `mountain_car`,
+//! `parity` (`bits`), `delayed_recall` (`max_delay`), `copy` (`length`) and
+//! `t_maze` (`length`), and `process` (`program`, `args`, `inputs`, `outputs`,
+//! and optionally `episodes` and `max_steps`, see `rustneat::process`).
+//!
+//! `run` writes `checkpoint.json` and the genome of the champion,
+//! `champion.json`, to the output directory. Wherever a genome is expected,
+//! a checkpoint can be given instead, which stands for its champion.
+//! `simplify` removes the neurons and connections of a genome that do not
+//! affect its outputs, and those with a weight of at most `--tolerance`, 0 by
+//! default (see `NeuralNetwork::simplify()`), before it is exported. With
+//! phased pruning (`pruning_threshold` in `[params]`), `run` also prints the
+//! mean complexity of the genomes in every phase that ends.
+//!
+//! `diff` lines up the genes of two genomes, e.g. a parent and its child, to
+//! show where the distance between them comes from (see
+//! `NeuralNetwork::diff()`). The distance coefficients are those of the
+//! `NeatParams` file given with `--params`, or else of the checkpoint given,
+//! or the defaults.
+//!
+//! `tune` searches for better parameters (see `rustneat::tune`), each trial
+//! evolving for `stop.generations`. It appends the trials to `trials.jsonl`,
+//! from which it continues when run again, and writes the parameters of the
+//! best trial to `params.json`:
+//!
+//! ```toml
+//! [tune]
+//! trials = 100                # number of trials
+//! seeds = 3                   # runs per trial, from `seed` on, 3 by default
+//! strategy = { type = "tpe", startup = 10, gamma = 0.25, candidates = 24, batch = 4 }
+//!
+//! [tune.ranges]
+//! mutation_pr = { min = 0.2, max = 1.0 }
+//! weight_mutate_var = { min = 0.05, max = 2.0, log = true }
+//! species_elite = { min = 1, max = 5, integer = true }
+//! ```
+//!
+//! The strategy can also be `{ type = "random" }`, the default, or
+//! `{ type = "grid", steps = 5 }`.
+
+use rustneat::benchmarks::{
+    Benchmark, CartPole, CopyTask, DelayedRecall, DoublePole, MountainCar, Parity, TMaze,
+};
+use rustneat::dataset::{Dataset, DatasetEnvironment, Loss};
+use rustneat::process::ProcessEnvironment;
+use rustneat::tune::{self, SearchSpace, Strategy, Tuner};
+use rustneat::{Environment, NeatParams, NeuralNetwork, Phase, PhaseStats, Population};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage:
+    rustneat run <config> [--out <dir>]
+    rustneat resume <checkpoint> [--generations <n>] [--out <dir>]
+    rustneat inspect <genome> [--inputs <n> --outputs <n>]
+    rustneat render <genome> [--format dot|svg] [--inputs <n> --outputs <n>] [-o <file>]
+    rustneat simplify <genome> [--tolerance <w>] [--inputs <n> --outputs <n>] [-o <file>]
+    rustneat diff <genome> <genome> [--format text|json] [--params <file>]
+    rustneat eval <config> <genome>
+    rustneat tune <config> [--out <dir>]";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    environment: EnvironmentConfig,
+    #[serde(default = "default_population")]
+    population: usize,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default = "yes")]
+    parallel: bool,
+    #[serde(default = "default_output")]
+    output: PathBuf,
+    #[serde(default = "default_checkpoint_every")]
+    checkpoint_every: usize,
+    /// Overrides of single fields of the `NeatParams`
+    #[serde(default)]
+    params: Value,
+    #[serde(default)]
+    stop: Stop,
+    #[serde(default)]
+    tune: Option<TuneConfig>,
+}
+
+fn default_population() -> usize {
+    150
+}
+fn default_output() -> PathBuf {
+    PathBuf::from("rustneat-run")
+}
+fn default_checkpoint_every() -> usize {
+    10
+}
+fn yes() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct Stop {
+    generations: Option<usize>,
+    fitness: Option<f64>,
+    stagnation: Option<usize>,
+    #[serde(default)]
+    solved: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+struct TuneConfig {
+    trials: usize,
+    #[serde(default = "default_tune_seeds")]
+    seeds: u64,
+    #[serde(default = "default_strategy")]
+    strategy: Strategy,
+    ranges: SearchSpace,
+}
+
+fn default_tune_seeds() -> u64 {
+    3
+}
+fn default_strategy() -> Strategy {
+    Strategy::Random
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum EnvironmentConfig {
+    Xor,
+    Dataset {
+        path: PathBuf,
+        inputs: usize,
+        #[serde(default = "default_loss")]
+        loss: Loss,
+        #[serde(default)]
+        validation: f64,
+    },
+    CartPole,
+    DoublePole {
+        #[serde(default = "yes")]
+        velocities: bool,
+    },
+    MountainCar,
+    Parity {
+        bits: usize,
+    },
+    DelayedRecall {
+        max_delay: usize,
+    },
+    Copy {
+        length: usize,
+    },
+    TMaze {
+        length: usize,
+    },
+    Process {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+        inputs: usize,
+        outputs: usize,
+        episodes: Option<usize>,
+        max_steps: Option<usize>,
+    },
+}
+
+fn default_loss() -> Loss {
+    Loss::MeanSquaredError
+}
+
+/// An environment built from its config, with what is needed to evolve in it
+struct Task {
+    kind: TaskKind,
+    n_inputs: usize,
+    n_outputs: usize,
+    params: NeatParams,
+    initial_genome: NeuralNetwork,
+}
+
+enum TaskKind {
+    Dataset(DatasetEnvironment),
+    Benchmark(Box<dyn Benchmark>),
+    Process(ProcessEnvironment),
+}
+
+impl Environment for Task {
+    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+        match &self.kind {
+            TaskKind::Dataset(environment) => environment.test(organism),
+            TaskKind::Benchmark(benchmark) => benchmark.test(organism),
+            TaskKind::Process(environment) => environment.test(organism),
+        }
+    }
+}
+
+impl Task {
+    fn new(config: &EnvironmentConfig) -> Result<Task> {
+        Ok(match config {
+            EnvironmentConfig::Xor => {
+                let mut initial_genome = NeuralNetwork::with_neurons(3);
+                initial_genome.add_connection(0, 2, 1.0);
+                initial_genome.add_connection(1, 2, 1.0);
+                Task {
+                    kind: TaskKind::Dataset(DatasetEnvironment::xor()),
+                    n_inputs: 2,
+                    n_outputs: 1,
+                    params: NeatParams::optimized_for_xor3(2, 1),
+                    initial_genome,
+                }
+            }
+            EnvironmentConfig::Dataset {
+                path,
+                inputs,
+                loss,
+                validation,
+            } => {
+                let dataset = Dataset::from_csv(path, *inputs)?;
+                let n_outputs = dataset
+                    .targets
+                    .first()
+                    .map(Vec::len)
+                    .ok_or_else(|| format!("{} has no examples", path.display()))?;
+                let mut environment = DatasetEnvironment::new(dataset, *loss);
+                if *validation > 0.0 {
+                    environment = environment.with_validation_split(*validation);
+                }
+                Task::connected(TaskKind::Dataset(environment), *inputs, n_outputs)
+            }
+            EnvironmentConfig::CartPole => Task::benchmark(CartPole::new()),
+            EnvironmentConfig::DoublePole { velocities: true } => {
+                Task::benchmark(DoublePole::with_velocities())
+            }
+            EnvironmentConfig::DoublePole { velocities: false } => {
+                Task::benchmark(DoublePole::without_velocities())
+            }
+            EnvironmentConfig::MountainCar => Task::benchmark(MountainCar::new()),
+            EnvironmentConfig::Parity { bits } => Task::benchmark(Parity::new(*bits)),
+            EnvironmentConfig::DelayedRecall { max_delay } => {
+                Task::benchmark(DelayedRecall::new(*max_delay))
+            }
+            EnvironmentConfig::Copy { length } => Task::benchmark(CopyTask::new(*length)),
+            EnvironmentConfig::TMaze { length } => Task::benchmark(TMaze::new(*length)),
+            EnvironmentConfig::Process {
+                program,
+                args,
+                inputs,
+                outputs,
+                episodes,
+                max_steps,
+            } => {
+                let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+                let mut environment = ProcessEnvironment::new(program, &args, *outputs);
+                environment.episodes = episodes.unwrap_or(1);
+                environment.max_steps = *max_steps;
+                Task::connected(TaskKind::Process(environment), *inputs, *outputs)
+            }
+        })
+    }
+
+    fn benchmark<B: Benchmark + 'static>(benchmark: B) -> Task {
+        Task {
+            n_inputs: benchmark.n_inputs(),
+            n_outputs: benchmark.n_outputs(),
+            params: benchmark.params(),
+            initial_genome: benchmark.initial_genome(),
+            kind: TaskKind::Benchmark(Box::new(benchmark)),
+        }
+    }
+
+    /// Start from every input connected to every output with zero weights,
+    /// like the benchmarks
+    fn connected(kind: TaskKind, n_inputs: usize, n_outputs: usize) -> Task {
+        let mut initial_genome = NeuralNetwork::with_neurons(n_inputs + n_outputs);
+        for input in 0..n_inputs {
+            for output in n_inputs..n_inputs + n_outputs {
+                initial_genome.add_connection(input, output, 0.0);
+            }
+        }
+        Task {
+            kind,
+            n_inputs,
+            n_outputs,
+            params: NeatParams::default(n_inputs, n_outputs),
+            initial_genome,
+        }
+    }
+
+    fn solves(&self, genome: &NeuralNetwork) -> Option<bool> {
+        match &self.kind {
+            TaskKind::Benchmark(benchmark) => Some(benchmark.solves(genome)),
+            _ => None,
+        }
+    }
+}
+
+impl Config {
+    fn load(path: &Path) -> Result<Config> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        let config = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&text)?
+        } else {
+            toml::from_str(&text)?
+        };
+        Ok(config)
+    }
+
+    /// `base` with the fields given in `params` replaced, validated
+    fn params(&self, base: &NeatParams) -> Result<NeatParams> {
+        match &self.params {
+            Value::Null => Ok(base.clone()),
+            Value::Object(overrides) => Ok(base.with_overrides(overrides)?),
+            _ => Err("`params` must be a table of parameters".into()),
+        }
+    }
+}
+
+/// Everything needed to continue a run
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    config: Config,
+    population: Population,
+    best_fitness: f64,
+    last_improvement: usize,
+}
+
+impl Checkpoint {
+    fn load(path: &Path) -> Result<Checkpoint> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&text)?)
+    }
+}
+
+fn save_json<T: serde::Serialize>(path: &Path, value: &T) -> Result<()> {
+    fs::write(path, serde_json::to_string(value)?)
+        .map_err(|e| format!("could not write {}: {}", path.display(), e).into())
+}
+
+/// Load a genome, or the champion of a checkpoint together with its config
+fn load_genome(path: &Path) -> Result<(NeuralNetwork, Option<Config>)> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    if let Ok(checkpoint) = serde_json::from_str::<Checkpoint>(&text) {
+        return Ok((
+            checkpoint.population.get_champion().genome,
+            Some(checkpoint.config),
+        ));
+    }
+    Ok((serde_json::from_str(&text)?, None))
+}
+
+/// Split `args` into positional arguments and the values of `--options`
+fn parse_options(
+    args: &[String],
+    options: &[&str],
+) -> Result<(Vec<String>, HashMap<String, String>)> {
+    let mut positional = Vec::new();
+    let mut values = HashMap::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg.starts_with('-') {
+            if !options.contains(&arg.as_str()) {
+                return Err(format!("unknown option {}\n{}", arg, USAGE).into());
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value of {}", arg))?;
+            values.insert(arg.clone(), value.clone());
+        } else {
+            positional.push(arg.clone());
+        }
+    }
+    Ok((positional, values))
+}
+
+fn number_option<T: FromStr>(options: &HashMap<String, String>, name: &str) -> Result<Option<T>> {
+    match options.get(name) {
+        Some(value) => {
+            Ok(Some(value.parse().map_err(|_| {
+                format!("{} must be a number, not {}", name, value)
+            })?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// The number of inputs and outputs to show a genome with: from the options,
+/// or else from the environment of the checkpoint it came from
+fn io_sizes(options: &HashMap<String, String>, config: Option<&Config>) -> Result<(usize, usize)> {
+    match (
+        number_option(options, "--inputs")?,
+        number_option(options, "--outputs")?,
+        config,
+    ) {
+        (Some(n_inputs), Some(n_outputs), _) => Ok((n_inputs, n_outputs)),
+        (None, None, Some(config)) => {
+            let task = Task::new(&config.environment)?;
+            Ok((task.n_inputs, task.n_outputs))
+        }
+        (None, None, None) => Ok((0, 0)),
+        _ => Err("--inputs and --outputs must be given together".into()),
+    }
+}
+
+fn run(config: Config, output: Option<&String>) -> Result<()> {
+    if let Some(seed) = config.seed {
+        rustneat::seed(seed);
+    }
+    let task = Task::new(&config.environment)?;
+    let population =
+        Population::create_population_from(task.initial_genome.clone(), config.population);
+    evolve(
+        task,
+        Checkpoint {
+            config,
+            population,
+            best_fitness: 0.0,
+            last_improvement: 0,
+        },
+        None,
+        output,
+    )
+}
+
+fn resume(path: &Path, generations: Option<usize>, output: Option<&String>) -> Result<()> {
+    let checkpoint = Checkpoint::load(path)?;
+    // The state of the random number generator is not saved, so continue
+    // with a seed of its own for every generation it is resumed from
+    if let Some(seed) = checkpoint.config.seed {
+        rustneat::seed(seed.wrapping_add(checkpoint.population.generation() as u64));
+    }
+    let task = Task::new(&checkpoint.config.environment)?;
+    let until = generations.map(|n| checkpoint.population.generation() + n);
+    evolve(task, checkpoint, until, output)
+}
+
+/// Evolve until a stop criterion is met, or generation `until` if given
+fn evolve(
+    mut task: Task,
+    mut run: Checkpoint,
+    until: Option<usize>,
+    output: Option<&String>,
+) -> Result<()> {
+    let params = run.config.params(&task.params)?;
+    let output = output.map_or_else(|| run.config.output.clone(), PathBuf::from);
+    fs::create_dir_all(&output)
+        .map_err(|e| format!("could not create {}: {}", output.display(), e))?;
+    let stop = run.config.stop.clone();
+    let until = until.or(stop.generations);
+    if stop.solved && task.solves(&task.initial_genome).is_none() {
+        return Err("`solved` is only a stop criterion for benchmark environments".into());
+    }
+    if until.is_none() && stop.fitness.is_none() && stop.stagnation.is_none() && !stop.solved {
+        return Err("no stop criterion in the config".into());
+    }
+
+    let reason = loop {
+        let generation = run.population.generation();
+        if until.is_some_and(|until| generation >= until) {
+            break "generation limit reached";
+        }
+        let phases = run.population.phase_stats().len();
+        run.population
+            .evolve(&mut task, &params, run.config.parallel);
+        if phases > 0 && run.population.phase_stats().len() > phases {
+            print_phase(&run.population.phase_stats()[phases - 1]);
+        }
+
+        let generation = run.population.generation();
+        let champion = run.population.get_champion();
+        if champion.fitness > run.best_fitness {
+            run.best_fitness = champion.fitness;
+            run.last_improvement = generation;
+        }
+        println!(
+            "generation {}: best fitness {:.6}, {} species, {} neurons, {} connections",
+            generation,
+            champion.fitness,
+            run.population.species.len(),
+            champion.genome.n_neurons(),
+            champion.genome.n_connections()
+        );
+        if generation.is_multiple_of(run.config.checkpoint_every.max(1)) {
+            save_json(&output.join("checkpoint.json"), &run)?;
+        }
+
+        if stop
+            .fitness
+            .is_some_and(|fitness| champion.fitness >= fitness)
+        {
+            break "fitness reached";
+        }
+        if stop
+            .stagnation
+            .is_some_and(|n| generation - run.last_improvement >= n)
+        {
+            break "no improvement";
+        }
+        if stop.solved && task.solves(&champion.genome) == Some(true) {
+            break "solved";
+        }
+    };
+
+    save_json(&output.join("checkpoint.json"), &run)?;
+    save_json(
+        &output.join("champion.json"),
+        &run.population.get_champion().genome,
+    )?;
+    println!(
+        "stopped after generation {}: {}. Results written to {}",
+        run.population.generation(),
+        reason,
+        output.display()
+    );
+    Ok(())
+}
+
+/// Summary of a phase of phased pruning that just ended
+fn print_phase(stats: &PhaseStats) {
+    println!(
+        "{} from generation {} to {}: mean complexity {:.1} to {:.1} (min {:.1}, max {:.1}), \
+         best fitness {:.6}",
+        match stats.phase {
+            Phase::Complexifying => "complexified",
+            Phase::Simplifying => "simplified",
+        },
+        stats.first_generation,
+        stats.last_generation,
+        stats.start_complexity,
+        stats.end_complexity,
+        stats.min_complexity,
+        stats.max_complexity,
+        stats.best_fitness
+    );
+}
+
+fn inspect(genome: &NeuralNetwork, n_inputs: usize, n_outputs: usize) {
+    let neurons = genome.sorted_neuron_ids();
+    let hidden = neurons.len().saturating_sub(n_inputs + n_outputs);
+    let weights = genome
+        .connections
+        .values()
+        .map(|gene| gene.weight)
+        .collect::<Vec<_>>();
+    let mut activations = HashMap::new();
+    for gene in genome.neurons.values() {
+        *activations
+            .entry(format!("{:?}", gene.activation))
+            .or_insert(0) += 1;
+    }
+    let mut activations = activations.into_iter().collect::<Vec<_>>();
+    activations.sort();
+
+    println!(
+        "neurons:      {} ({} inputs, {} outputs, {} hidden)",
+        neurons.len(),
+        n_inputs,
+        n_outputs,
+        hidden
+    );
+    println!("connections:  {}", genome.n_connections());
+    match genome.depth() {
+        Some(depth) => println!("recurrent:    no, depth {}", depth),
+        None => println!(
+            "recurrent:    yes, cycles through {} connections",
+            genome.recurrent_connections().len()
+        ),
+    }
+    println!(
+        "fan-in/out:   at most {} / {}",
+        genome.fan_in().values().max().unwrap_or(&0),
+        genome.fan_out().values().max().unwrap_or(&0)
+    );
+    for (i, outputs) in genome.influence(n_inputs, n_outputs).iter().enumerate() {
+        let outputs = outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, reached)| **reached)
+            .map(|(o, _)| o.to_string())
+            .collect::<Vec<_>>();
+        println!(
+            "input {:<7} {}",
+            format!("{}:", i),
+            if outputs.is_empty() {
+                "influences no output".to_string()
+            } else {
+                format!("influences outputs {}", outputs.join(", "))
+            }
+        );
+    }
+    if !weights.is_empty() {
+        println!(
+            "weights:      {:.4} to {:.4}, {} zero",
+            weights.iter().cloned().fold(f64::INFINITY, f64::min),
+            weights.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            weights.iter().filter(|w| **w == 0.0).count()
+        );
+    }
+    println!(
+        "plastic:      {} connections",
+        genome
+            .connections
+            .values()
+            .filter(|gene| gene.plasticity.is_some())
+            .count()
+    );
+    println!(
+        "modulatory:   {} neurons",
+        genome
+            .neurons
+            .values()
+            .filter(|gene| gene.modulatory)
+            .count()
+    );
+    for (activation, count) in activations {
+        println!("activation:   {} {}", count, activation);
+    }
+}
+
+fn eval(config: &Config, genome: &NeuralNetwork) -> Result<()> {
+    if let Some(seed) = config.seed {
+        rustneat::seed(seed);
+    }
+    let task = Task::new(&config.environment)?;
+    match &task.kind {
+        TaskKind::Dataset(environment) => println!("{}", environment.evaluate(genome)),
+        _ => println!("fitness {:.6}", task.test(&mut genome.clone())),
+    }
+    if let Some(solved) = task.solves(genome) {
+        println!("solved: {}", if solved { "yes" } else { "no" });
+    }
+    Ok(())
+}
+
+fn tune(config: &Config, output: Option<&String>) -> Result<()> {
+    let tune_config = config
+        .tune
+        .as_ref()
+        .ok_or("no [tune] section in the config")?;
+    let generations = config
+        .stop
+        .generations
+        .ok_or("tuning needs `stop.generations`, the generations per trial")?;
+    let output = output.map_or_else(|| config.output.clone(), PathBuf::from);
+    fs::create_dir_all(&output)
+        .map_err(|e| format!("could not create {}: {}", output.display(), e))?;
+    if let Some(seed) = config.seed {
+        rustneat::seed(seed);
+    }
+    let task = Task::new(&config.environment)?;
+    let base = config.params(&task.params)?;
+    let first_seed = config.seed.unwrap_or(0);
+    let tuner = Tuner::new(
+        tune_config.ranges.clone(),
+        base.clone(),
+        task.initial_genome.clone(),
+    )
+    .with_strategy(tune_config.strategy.clone())
+    .with_seeds((first_seed..first_seed + tune_config.seeds).collect())
+    .with_generations(generations)
+    .with_population_size(config.population)
+    .with_log(output.join("trials.jsonl"));
+
+    let trials = tuner.run(&task, tune_config.trials, |trial| {
+        println!(
+            "trial {}: score {:.6}, {:?}",
+            trial.id, trial.score, trial.values
+        )
+    })?;
+    let best = tune::best(&trials).ok_or("no trials")?;
+    save_json(
+        &output.join("params.json"),
+        &tuner.space().apply(&base, &best.values)?,
+    )?;
+    println!(
+        "best trial {}: score {:.6}. Parameters written to {}",
+        best.id,
+        best.score,
+        output.join("params.json").display()
+    );
+    Ok(())
+}
+
+fn main_() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let command = args.first().ok_or(USAGE)?;
+    let rest = &args[1..];
+    match command.as_str() {
+        "run" => {
+            let (positional, options) = parse_options(rest, &["--out"])?;
+            match positional.as_slice() {
+                [config] => run(Config::load(Path::new(config))?, options.get("--out")),
+                _ => Err(USAGE.into()),
+            }
+        }
+        "resume" => {
+            let (positional, options) = parse_options(rest, &["--generations", "--out"])?;
+            match positional.as_slice() {
+                [checkpoint] => resume(
+                    Path::new(checkpoint),
+                    number_option(&options, "--generations")?,
+                    options.get("--out"),
+                ),
+                _ => Err(USAGE.into()),
+            }
+        }
+        "inspect" => {
+            let (positional, options) = parse_options(rest, &["--inputs", "--outputs"])?;
+            match positional.as_slice() {
+                [genome] => {
+                    let (genome, config) = load_genome(Path::new(genome))?;
+                    let (n_inputs, n_outputs) = io_sizes(&options, config.as_ref())?;
+                    inspect(&genome, n_inputs, n_outputs);
+                    Ok(())
+                }
+                _ => Err(USAGE.into()),
+            }
+        }
+        "render" => {
+            let (positional, options) =
+                parse_options(rest, &["--format", "--inputs", "--outputs", "-o"])?;
+            match positional.as_slice() {
+                [genome] => {
+                    let (genome, config) = load_genome(Path::new(genome))?;
+                    let (n_inputs, n_outputs) = io_sizes(&options, config.as_ref())?;
+                    let rendered = match options.get("--format").map(String::as_str) {
+                        None | Some("dot") => genome.to_dot(n_inputs, n_outputs),
+                        Some("svg") => genome.to_svg(n_inputs, n_outputs),
+                        Some(format) => return Err(format!("unknown format {}", format).into()),
+                    };
+                    match options.get("-o") {
+                        Some(path) => fs::write(path, rendered)
+                            .map_err(|e| format!("could not write {}: {}", path, e).into()),
+                        None => {
+                            print!("{}", rendered);
+                            Ok(())
+                        }
+                    }
+                }
+                _ => Err(USAGE.into()),
+            }
+        }
+        "simplify" => {
+            let (positional, options) =
+                parse_options(rest, &["--tolerance", "--inputs", "--outputs", "-o"])?;
+            match positional.as_slice() {
+                [genome] => {
+                    let (mut genome, config) = load_genome(Path::new(genome))?;
+                    if config.is_none() && !options.contains_key("--inputs") {
+                        return Err("--inputs and --outputs are needed for a genome".into());
+                    }
+                    let (n_inputs, n_outputs) = io_sizes(&options, config.as_ref())?;
+                    let (neurons, connections) = (genome.n_neurons(), genome.n_connections());
+                    genome.simplify(
+                        n_inputs,
+                        n_outputs,
+                        number_option(&options, "--tolerance")?.unwrap_or(0.0),
+                    );
+                    eprintln!(
+                        "{} to {} neurons, {} to {} connections",
+                        neurons,
+                        genome.n_neurons(),
+                        connections,
+                        genome.n_connections()
+                    );
+                    match options.get("-o") {
+                        Some(path) => save_json(Path::new(path), &genome),
+                        None => {
+                            println!("{}", serde_json::to_string(&genome)?);
+                            Ok(())
+                        }
+                    }
+                }
+                _ => Err(USAGE.into()),
+            }
+        }
+        "diff" => {
+            let (positional, options) = parse_options(rest, &["--format", "--params"])?;
+            match positional.as_slice() {
+                [first, second] => {
+                    let (first, first_config) = load_genome(Path::new(first))?;
+                    let (second, second_config) = load_genome(Path::new(second))?;
+                    let params = match (options.get("--params"), first_config.or(second_config)) {
+                        (Some(path), _) => NeatParams::from_file(path)?,
+                        (None, Some(config)) => {
+                            config.params(&Task::new(&config.environment)?.params)?
+                        }
+                        (None, None) => NeatParams::default(0, 0),
+                    };
+                    let diff = first.diff(&second, &params);
+                    match options.get("--format").map(String::as_str) {
+                        None | Some("text") => print!("{}", diff),
+                        Some("json") => println!("{}", diff.to_json()),
+                        Some(format) => return Err(format!("unknown format {}", format).into()),
+                    }
+                    Ok(())
+                }
+                _ => Err(USAGE.into()),
+            }
+        }
+        "eval" => {
+            let (positional, _) = parse_options(rest, &[])?;
+            match positional.as_slice() {
+                [config, genome] => {
+                    let (genome, _) = load_genome(Path::new(genome))?;
+                    eval(&Config::load(Path::new(config))?, &genome)
+                }
+                _ => Err(USAGE.into()),
+            }
+        }
+        "tune" => {
+            let (positional, options) = parse_options(rest, &["--out"])?;
+            match positional.as_slice() {
+                [config] => tune(&Config::load(Path::new(config))?, options.get("--out")),
+                _ => Err(USAGE.into()),
+            }
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn main() {
+    if let Err(e) = main_() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -1,34 +1,13 @@
 extern crate rand;
 extern crate rustneat;
 
-use rustneat::{Environment, NeatParams, NeuralNetwork, Organism, Population};
+use rustneat::dataset::DatasetEnvironment;
+use rustneat::{NeatParams, NeuralNetwork, Organism, Population};
 use std::io::Write;
 
 // This example measure average XOR performance, and should be useful to check
 // that changes in the algorithm doesn't break the algorithm
 
-struct XORClassification;
-
-impl Environment for XORClassification {
-    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
-        let nn = organism.make_network();
-        let mut output = vec![0f64];
-        let mut distance: f64;
-        nn.activate(vec![0f64, 0f64], &mut output);
-        distance = (0f64 - output[0]).powi(2);
-        nn.activate(vec![0f64, 1f64], &mut output);
-        distance += (1f64 - output[0]).powi(2);
-        nn.activate(vec![1f64, 0f64], &mut output);
-        distance += (1f64 - output[0]).powi(2);
-        nn.activate(vec![1f64, 1f64], &mut output);
-        distance += (0f64 - output[0]).powi(2);
-
-        let fitness = 16.0 / (1.0 + distance);
-
-        fitness
-    }
-}
-
 fn main() {
     // let p = NeatParams {
     // n_inputs: 2,
@@ -93,7 +72,7 @@ fn solve_time_perf(p: &NeatParams, n_exp: usize, n_gen: usize, population_size:
         std::io::stdout().flush().unwrap();
         let start_genome = NeuralNetwork::with_neurons(3);
         let mut population = Population::create_population_from(start_genome, population_size);
-        let mut environment = XORClassification;
+        let mut environment = DatasetEnvironment::xor();
 
         let mut champion: Option<Organism> = None;
         let mut i = 0;
@@ -145,7 +124,7 @@ fn fixed_generations_perf(p: &NeatParams) {
         std::io::stdout().flush().unwrap();
         let start_genome = NeuralNetwork::with_neurons(3);
         let mut population = Population::create_population_from(start_genome, 150);
-        let mut environment = XORClassification;
+        let mut environment = DatasetEnvironment::xor();
 
         for i in 0..N_GEN {
             population.evolve(&mut environment, &p,true);
@@ -0,0 +1,517 @@
+//! Generation of self-contained Rust and C source code from a network, for
+//! deployment on targets where this crate cannot run, like microcontrollers.
+//!
+//! The generated function has the weights compiled in and all connections
+//! unrolled. It uses no heap, no standard library (the Rust code is `no_std`
+//! compatible, and the C code includes no headers) and no dependencies: the
+//! activation functions come with their own implementations of `exp` and
+//! `sin`, accurate to a few ulps.
+//!
+//! In Rust, the signature is
+//! `pub fn name(input: &[f64; N_INPUTS], output: &mut [f64; N_OUTPUTS])`,
+//! and in C it is
+//! `void name(const double input[N_INPUTS], double output[N_OUTPUTS])`.
+
+use crate::model::Model;
+use crate::nn::{Activation, FeedForward, NeuralNetwork};
+use std::fmt::Write;
+
+/// How the generated code updates the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Update {
+    /// Evaluate every neuron once in topological order, like `FeedForward`.
+    /// Only possible for acyclic networks.
+    FeedForward,
+    /// Integrate the continuous-time network, like `Ctrnn::activate`
+    Ctrnn,
+}
+
+/// Target language of the generated code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// Rust, `no_std` compatible
+    Rust,
+    /// C99 without any includes
+    C,
+}
+
+impl NeuralNetwork {
+    /// Generate a function `name` computing the network as it would be
+    /// activated with `n_inputs` inputs and `n_outputs` outputs. See
+    /// `Model::generate()`.
+    pub fn generate(
+        &self,
+        n_inputs: usize,
+        n_outputs: usize,
+        name: &str,
+        language: Language,
+        update: Update,
+    ) -> Option<String> {
+        self.to_model(n_inputs, n_outputs)
+            .generate(name, language, update)
+    }
+}
+
+impl Model {
+    /// Generate a function `name` that computes the network. Returns `None`
+    /// when asking for a feed-forward update of a network with recurrent
+    /// connections, when `name` is not an identifier in `language`, or when a
+    /// weight, bias or integration constant is not finite, since those have
+    /// no literal.
+    pub fn generate(&self, name: &str, language: Language, update: Update) -> Option<String> {
+        if !is_identifier(name, language) {
+            return None;
+        }
+        let finite = self.connections.iter().all(|c| c.weight.is_finite())
+            && self.neurons.iter().all(|n| {
+                n.bias.is_finite()
+                    && (update == Update::FeedForward
+                        || (self.integration.delta_t / n.tau).is_finite())
+            });
+        if !finite {
+            return None;
+        }
+        let mut code = Code {
+            language,
+            prefix: name.to_string(),
+            text: String::new(),
+        };
+        let (neurons, index) = self.layout();
+        let n = neurons.len();
+        let n_inputs = self.n_inputs().min(n);
+        let n_outputs = self.n_outputs();
+        let activations = neurons.iter().map(|n| n.activation).collect::<Vec<_>>();
+        let bias = neurons.iter().map(|n| n.bias).collect::<Vec<_>>();
+        let connections = self
+            .connections
+            .iter()
+            .map(|c| (index[&c.from], index[&c.to], c.weight))
+            .collect::<Vec<_>>();
+        let mut has_outgoing = vec![false; n];
+        for c in &connections {
+            has_outgoing[c.0] = true;
+        }
+
+        let description = match update {
+            Update::FeedForward => "Feed-forward",
+            Update::Ctrnn => "Continuous-time recurrent",
+        };
+        code.helpers(&activations);
+        code.line(
+            0,
+            &format!(
+                "{} Generated by rustneat {}. {} network with {} inputs and {} outputs.",
+                code.comment(),
+                env!("CARGO_PKG_VERSION"),
+                description,
+                self.n_inputs(),
+                n_outputs
+            ),
+        );
+        code.signature(self.n_inputs(), n_outputs);
+
+        match update {
+            Update::FeedForward => {
+                let network = FeedForward::new(bias.clone(), activations.clone(), &connections)?;
+                for &i in &network.order {
+                    if !has_outgoing[i] && !(n_inputs..n_inputs + n_outputs).contains(&i) {
+                        continue;
+                    }
+                    let mut sum = if i < n_inputs {
+                        format!("input[{}]", i)
+                    } else {
+                        "0.0".to_string()
+                    };
+                    for &(source, weight) in &network.incoming[i] {
+                        sum += &term(weight, &format!("a{}", source));
+                    }
+                    code.declare(1, &format!("y{}", i), &sum);
+                    if has_outgoing[i] {
+                        let activation = code
+                            .activation(activations[i], &format!("y{} + {}", i, literal(bias[i])));
+                        code.declare(1, &format!("a{}", i), &activation);
+                    }
+                }
+                for j in 0..n_outputs {
+                    let value = if n_inputs + j < n {
+                        format!("y{}", n_inputs + j)
+                    } else {
+                        "0.0".to_string()
+                    };
+                    code.line(1, &format!("output[{}] = {};", j, value));
+                }
+            }
+            Update::Ctrnn => {
+                let mut incoming = vec![Vec::new(); n];
+                for &(source, target, weight) in &connections {
+                    incoming[target].push((source, weight));
+                }
+                for sources in &mut incoming {
+                    sources.sort_by_key(|x| x.0);
+                }
+                match code.language {
+                    Language::Rust => code.line(1, &format!("let mut y = [0.0f64; {}];", n)),
+                    Language::C => {
+                        code.line(1, &format!("double y[{}];", n));
+                        code.line(1, "int step;");
+                        for i in 0..n {
+                            code.line(1, &format!("y[{}] = 0.0;", i));
+                        }
+                    }
+                }
+                for i in 0..n_inputs {
+                    code.line(1, &format!("y[{}] = input[{}];", i, i));
+                }
+                match code.language {
+                    Language::Rust => {
+                        code.line(1, &format!("for _ in 0..{} {{", self.integration.steps))
+                    }
+                    Language::C => code.line(
+                        1,
+                        &format!(
+                            "for (step = 0; step < {}; step++) {{",
+                            self.integration.steps
+                        ),
+                    ),
+                }
+                if code.language == Language::C {
+                    // Declarations first, for older C compilers
+                    for i in (0..n).filter(|i| has_outgoing[*i]) {
+                        code.line(2, &format!("double a{};", i));
+                    }
+                    for i in 0..n {
+                        code.line(2, &format!("double d{};", i));
+                    }
+                }
+                for i in (0..n).filter(|i| has_outgoing[*i]) {
+                    let activation = code
+                        .activation(activations[i], &format!("y[{}] + {}", i, literal(bias[i])));
+                    code.assign_or_declare(2, &format!("a{}", i), &activation);
+                }
+                for (i, sources) in incoming.iter().enumerate() {
+                    let mut sum = "0.0".to_string();
+                    for &(source, weight) in sources {
+                        sum += &term(weight, &format!("a{}", source));
+                    }
+                    let input = if i < n_inputs {
+                        format!("input[{}]", i)
+                    } else {
+                        "0.0".to_string()
+                    };
+                    code.assign_or_declare(
+                        2,
+                        &format!("d{}", i),
+                        &format!("({}) - y[{}] + {}", sum, i, input),
+                    );
+                }
+                for (i, neuron) in neurons.iter().enumerate() {
+                    let delta_t_tau = 1.0 / neuron.tau * self.integration.delta_t;
+                    code.line(
+                        2,
+                        &format!("y[{}] = y[{}] + {} * d{};", i, i, literal(delta_t_tau), i),
+                    );
+                }
+                code.line(1, "}");
+                for j in 0..n_outputs {
+                    let value = if n_inputs + j < n {
+                        format!("y[{}]", n_inputs + j)
+                    } else {
+                        "0.0".to_string()
+                    };
+                    code.line(1, &format!("output[{}] = {};", j, value));
+                }
+            }
+        }
+        code.line(0, "}");
+        Some(code.text)
+    }
+}
+
+/// Format a finite float so it reads back exactly, in both Rust and C
+fn literal(x: f64) -> String {
+    let s = format!("{:?}", x);
+    if s.contains('.') || s.contains('e') {
+        s
+    } else {
+        format!("{}.0", s)
+    }
+}
+
+/// Whether `name` can name a function in `language`
+fn is_identifier(name: &str, language: Language) -> bool {
+    let mut chars = name.chars();
+    let valid = match chars.next() {
+        Some(c) => {
+            (c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    };
+    let keywords = match language {
+        Language::Rust => RUST_KEYWORDS,
+        Language::C => C_KEYWORDS,
+    };
+    valid && name != "_" && !keywords.split_whitespace().any(|k| k == name)
+}
+
+const RUST_KEYWORDS: &str = "abstract as async await become box break const continue crate do \
+    dyn else enum extern false final fn for if impl in let loop macro match mod move mut override \
+    priv pub ref return self Self static struct super trait true try type typeof unsafe unsized \
+    use virtual where while yield";
+const C_KEYWORDS: &str = "auto break case char const continue default do double else enum extern \
+    float for goto if inline int long register restrict return short signed sizeof static struct \
+    switch typedef union unsigned void volatile while _Bool _Complex _Imaginary";
+
+/// ` + weight * value`, or ` - |weight| * value` for negative weights
+fn term(weight: f64, value: &str) -> String {
+    if weight < 0.0 {
+        format!(" - {} * {}", literal(-weight), value)
+    } else {
+        format!(" + {} * {}", literal(weight), value)
+    }
+}
+
+struct Code {
+    language: Language,
+    prefix: String,
+    text: String,
+}
+
+impl Code {
+    fn line(&mut self, indent: usize, line: &str) {
+        writeln!(self.text, "{}{}", "    ".repeat(indent), line).unwrap();
+    }
+
+    fn comment(&self) -> &'static str {
+        match self.language {
+            Language::Rust => "///",
+            Language::C => "//",
+        }
+    }
+
+    fn signature(&mut self, n_inputs: usize, n_outputs: usize) {
+        let signature = match self.language {
+            Language::Rust => format!(
+                "pub fn {}(input: &[f64; {}], output: &mut [f64; {}]) {{",
+                self.prefix, n_inputs, n_outputs
+            ),
+            Language::C => format!(
+                "void {}(const double input[{}], double output[{}]) {{",
+                self.prefix,
+                n_inputs.max(1),
+                n_outputs.max(1)
+            ),
+        };
+        self.line(0, &signature);
+    }
+
+    fn declare(&mut self, indent: usize, name: &str, value: &str) {
+        let line = match self.language {
+            Language::Rust => format!("let {}: f64 = {};", name, value),
+            Language::C => format!("double {} = {};", name, value),
+        };
+        self.line(indent, &line);
+    }
+
+    /// Declare in Rust, assign to a variable declared before in C
+    fn assign_or_declare(&mut self, indent: usize, name: &str, value: &str) {
+        match self.language {
+            Language::Rust => self.declare(indent, name, value),
+            Language::C => self.line(indent, &format!("{} = {};", name, value)),
+        }
+    }
+
+    fn activation(&self, activation: Activation, value: &str) -> String {
+        format!("{}_{}({})", self.prefix, activation_name(activation), value)
+    }
+
+    /// Emit the helper functions needed by `activations`
+    fn helpers(&mut self, activations: &[Activation]) {
+        let mut used = activations.to_vec();
+        used.sort_by_key(|a| activation_name(*a));
+        used.dedup();
+        if used.iter().any(|a| {
+            matches!(
+                a,
+                Activation::Sigmoid | Activation::Tanh | Activation::Gaussian
+            )
+        }) {
+            self.helper(EXP_RUST, EXP_C);
+        }
+        for activation in used {
+            let (rust, c) = match activation {
+                Activation::Sigmoid => (SIGMOID_RUST, SIGMOID_C),
+                Activation::Tanh => (TANH_RUST, TANH_C),
+                Activation::Relu => (RELU_RUST, RELU_C),
+                Activation::Identity => (IDENTITY_RUST, IDENTITY_C),
+                Activation::Gaussian => (GAUSSIAN_RUST, GAUSSIAN_C),
+                Activation::Sine => (SINE_RUST, SINE_C),
+                Activation::Abs => (ABS_RUST, ABS_C),
+            };
+            self.helper(rust, c);
+        }
+    }
+
+    fn helper(&mut self, rust: &str, c: &str) {
+        let template = match self.language {
+            Language::Rust => rust,
+            Language::C => c,
+        };
+        self.text += &template.replace("PREFIX", &self.prefix);
+    }
+}
+
+// `exp(x) = 2^k * exp(r)` with `|r| <= ln(2) / 2`, where `ln(2) * k` is
+// subtracted in two parts to keep the precision, and `exp(r)` is a Taylor
+// series up to `r^13`.
+const EXP_RUST: &str = "#[inline]
+fn PREFIX_exp(x: f64) -> f64 {
+    let x = if x > 709.0 { 709.0 } else if x < -708.0 { -708.0 } else { x };
+    let t = x * 1.4426950408889634;
+    let k = if t >= 0.0 { (t + 0.5) as i64 } else { (t - 0.5) as i64 };
+    let r = x - k as f64 * 0.6931471803691238 - k as f64 * 1.9082149292705877e-10;
+    let mut p = 1.0;
+    let mut n = 13.0;
+    while n > 0.5 {
+        p = 1.0 + r / n * p;
+        n -= 1.0;
+    }
+    p * f64::from_bits(((k + 1023) as u64) << 52)
+}
+";
+const EXP_C: &str = "static double PREFIX_exp(double x) {
+    union { double d; unsigned long long u; } scale;
+    double t, r, p, n;
+    long long k;
+    if (x > 709.0) { x = 709.0; } else if (x < -708.0) { x = -708.0; }
+    t = x * 1.4426950408889634;
+    k = t >= 0.0 ? (long long)(t + 0.5) : (long long)(t - 0.5);
+    r = x - (double)k * 0.6931471803691238 - (double)k * 1.9082149292705877e-10;
+    p = 1.0;
+    for (n = 13.0; n > 0.5; n -= 1.0) {
+        p = 1.0 + r / n * p;
+    }
+    scale.u = (unsigned long long)(k + 1023) << 52;
+    return p * scale.d;
+}
+";
+const SIGMOID_RUST: &str = "#[inline]
+fn PREFIX_sigmoid(x: f64) -> f64 {
+    let y = x * 5.0;
+    let y = if y < -60.0 { -60.0 } else if y > 60.0 { 60.0 } else { y };
+    1.0 / (1.0 + PREFIX_exp(-y))
+}
+";
+const SIGMOID_C: &str = "static double PREFIX_sigmoid(double x) {
+    double y = x * 5.0;
+    if (y < -60.0) { y = -60.0; } else if (y > 60.0) { y = 60.0; }
+    return 1.0 / (1.0 + PREFIX_exp(-y));
+}
+";
+const TANH_RUST: &str = "#[inline]
+fn PREFIX_tanh(x: f64) -> f64 {
+    if x > 20.0 {
+        return 1.0;
+    }
+    if x < -20.0 {
+        return -1.0;
+    }
+    let y = PREFIX_exp(2.0 * x);
+    (y - 1.0) / (y + 1.0)
+}
+";
+const TANH_C: &str = "static double PREFIX_tanh(double x) {
+    double y;
+    if (x > 20.0) { return 1.0; }
+    if (x < -20.0) { return -1.0; }
+    y = PREFIX_exp(2.0 * x);
+    return (y - 1.0) / (y + 1.0);
+}
+";
+const RELU_RUST: &str = "#[inline]
+fn PREFIX_relu(x: f64) -> f64 {
+    if x > 0.0 { x } else { 0.0 }
+}
+";
+const RELU_C: &str = "static double PREFIX_relu(double x) {
+    return x > 0.0 ? x : 0.0;
+}
+";
+const IDENTITY_RUST: &str = "#[inline]
+fn PREFIX_identity(x: f64) -> f64 {
+    x
+}
+";
+const IDENTITY_C: &str = "static double PREFIX_identity(double x) {
+    return x;
+}
+";
+const GAUSSIAN_RUST: &str = "#[inline]
+fn PREFIX_gaussian(x: f64) -> f64 {
+    PREFIX_exp(-x * x)
+}
+";
+const GAUSSIAN_C: &str = "static double PREFIX_gaussian(double x) {
+    return PREFIX_exp(-x * x);
+}
+";
+const ABS_RUST: &str = "#[inline]
+fn PREFIX_abs(x: f64) -> f64 {
+    if x < 0.0 { -x } else { x }
+}
+";
+const ABS_C: &str = "static double PREFIX_abs(double x) {
+    return x < 0.0 ? -x : x;
+}
+";
+// Reduced to `[-pi/2, pi/2]`, then Taylor series up to `r^19`
+const SINE_RUST: &str = "#[inline]
+fn PREFIX_sine(x: f64) -> f64 {
+    let t = x / 6.283185307179586;
+    let k = if t >= 0.0 { (t + 0.5) as i64 } else { (t - 0.5) as i64 };
+    let mut r = x - k as f64 * 6.283185307179586;
+    if r > 1.5707963267948966 {
+        r = 3.141592653589793 - r;
+    } else if r < -1.5707963267948966 {
+        r = -3.141592653589793 - r;
+    }
+    let r2 = r * r;
+    let mut p = 1.0;
+    let mut n = 19.0;
+    while n > 2.5 {
+        p = 1.0 - r2 / (n * (n - 1.0)) * p;
+        n -= 2.0;
+    }
+    r * p
+}
+";
+const SINE_C: &str = "static double PREFIX_sine(double x) {
+    double t = x / 6.283185307179586;
+    long long k = t >= 0.0 ? (long long)(t + 0.5) : (long long)(t - 0.5);
+    double r = x - (double)k * 6.283185307179586;
+    double r2, p, n;
+    if (r > 1.5707963267948966) {
+        r = 3.141592653589793 - r;
+    } else if (r < -1.5707963267948966) {
+        r = -3.141592653589793 - r;
+    }
+    r2 = r * r;
+    p = 1.0;
+    for (n = 19.0; n > 2.5; n -= 2.0) {
+        p = 1.0 - r2 / (n * (n - 1.0)) * p;
+    }
+    return r * p;
+}
+";
+
+fn activation_name(activation: Activation) -> &'static str {
+    match activation {
+        Activation::Sigmoid => "sigmoid",
+        Activation::Tanh => "tanh",
+        Activation::Relu => "relu",
+        Activation::Identity => "identity",
+        Activation::Gaussian => "gaussian",
+        Activation::Sine => "sine",
+        Activation::Abs => "abs",
+    }
+}
@@ -0,0 +1,464 @@
+//! Fitness from supervised datasets.
+//!
+//! A `DatasetEnvironment` activates a network (see `make_network()`) with the
+//! inputs of every example of a `Dataset`, compares the outputs to the
+//! targets with a `Loss`, and turns the loss into a fitness. Datasets can be
+//! built in memory or loaded from CSV files, and part of them can be held out
+//! for validation: evolution only sees the training examples, and comparing
+//! the validation fitness of the champion with its training fitness shows
+//! when it starts overfitting.
+//!
+//! ```
+//! use rustneat::dataset::{Dataset, DatasetEnvironment, Loss};
+//! use rustneat::{Environment, NeuralNetwork};
+//!
+//! let csv = "x,y\n0.0,0.0\n0.5,1.0\n1.0,2.0\n";
+//! let dataset = Dataset::read_csv(csv.as_bytes(), 1).unwrap();
+//! let environment = DatasetEnvironment::new(dataset, Loss::MeanAbsoluteError);
+//!
+//! let mut network = NeuralNetwork::with_neurons(2);
+//! network.add_connection(0, 1, 1.0);
+//! let fitness = environment.test(&mut network);
+//! assert!(fitness > 0.0 && fitness < 1.0);
+//! println!("{}", environment.evaluate(&network));
+//! ```
+
+pub use crate::train::Dataset;
+use crate::{Environment, NeuralNetwork};
+use rand::seq::SliceRandom;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::{error, fmt};
+
+/// Probabilities are clamped to `[EPSILON, 1 - EPSILON]` in the cross-entropy
+const EPSILON: f64 = 1e-7;
+
+/// Errors when loading a `Dataset`
+#[derive(Debug)]
+pub enum DatasetError {
+    /// The file could not be read
+    Io(io::Error),
+    /// A line is not a row of numbers with the same number of columns as the
+    /// others, or has no targets
+    Parse {
+        /// Line number, starting from 1
+        line: usize,
+        /// What is wrong with it
+        message: String,
+    },
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DatasetError::Io(e) => write!(f, "could not read dataset: {}", e),
+            DatasetError::Parse { line, message } => {
+                write!(f, "invalid dataset on line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl error::Error for DatasetError {}
+
+impl From<io::Error> for DatasetError {
+    fn from(e: io::Error) -> DatasetError {
+        DatasetError::Io(e)
+    }
+}
+
+impl Dataset {
+    /// Load a CSV file where every row is an example: the first `n_inputs`
+    /// columns are the inputs and the others the targets. See `read_csv()`.
+    pub fn from_csv<P: AsRef<Path>>(path: P, n_inputs: usize) -> Result<Dataset, DatasetError> {
+        Dataset::read_csv(BufReader::new(File::open(path)?), n_inputs)
+    }
+
+    /// Read comma-separated examples, the first `n_inputs` columns being the
+    /// inputs and the others the targets. The first row is skipped if it is
+    /// not numeric (a header), as are empty lines and lines starting with `#`.
+    pub fn read_csv<R: BufRead>(reader: R, n_inputs: usize) -> Result<Dataset, DatasetError> {
+        let mut dataset = Dataset::default();
+        let mut n_columns = None;
+        let mut first_row = true;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let is_header_candidate = first_row;
+            first_row = false;
+            let row = line
+                .split(',')
+                .map(|field| field.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>();
+            let row = match row {
+                Ok(row) => row,
+                Err(_) if is_header_candidate => continue,
+                Err(e) => {
+                    return Err(DatasetError::Parse {
+                        line: i + 1,
+                        message: e.to_string(),
+                    })
+                }
+            };
+            let expected = *n_columns.get_or_insert(row.len());
+            if row.len() != expected {
+                return Err(DatasetError::Parse {
+                    line: i + 1,
+                    message: format!("{} columns instead of {}", row.len(), expected),
+                });
+            }
+            if row.len() <= n_inputs {
+                return Err(DatasetError::Parse {
+                    line: i + 1,
+                    message: format!("{} columns, but {} inputs", row.len(), n_inputs),
+                });
+            }
+            dataset.targets.push(row[n_inputs..].to_vec());
+            dataset.inputs.push(row[..n_inputs].to_vec());
+        }
+        Ok(dataset)
+    }
+
+    /// Shuffle the examples and split them in two: the first part for
+    /// training, the second with `validation_fraction` of the examples for
+    /// validation.
+    pub fn split(&self, validation_fraction: f64) -> (Dataset, Dataset) {
+        let mut examples = (0..self.len()).collect::<Vec<_>>();
+        examples.shuffle(&mut crate::rng::thread_rng());
+        let n_validation = (self.len() as f64 * validation_fraction).round() as usize;
+        let (validation, training) = examples.split_at(n_validation.min(self.len()));
+        let subset = |examples: &[usize]| Dataset {
+            inputs: examples.iter().map(|&i| self.inputs[i].clone()).collect(),
+            targets: examples.iter().map(|&i| self.targets[i].clone()).collect(),
+        };
+        (subset(training), subset(validation))
+    }
+}
+
+/// How outputs are compared to targets. All are averaged over the examples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Loss {
+    /// Mean of the squared differences, over examples and outputs
+    MeanSquaredError,
+    /// Mean of the absolute differences, over examples and outputs
+    MeanAbsoluteError,
+    /// With one output, the binary cross-entropy of the output clamped to
+    /// `[0, 1]`. With several, the categorical cross-entropy of their softmax.
+    CrossEntropy,
+    /// Fraction of the examples classified correctly. With one output, the
+    /// class is whether it is above 0.5, with several, the one with the highest
+    /// output. Unlike the others, higher is better.
+    Accuracy,
+}
+
+impl Loss {
+    /// Compute the loss of `outputs`, given the `targets` of every example
+    pub fn compute(self, outputs: &[Vec<f64>], targets: &[Vec<f64>]) -> f64 {
+        assert_eq!(outputs.len(), targets.len());
+        if outputs.is_empty() {
+            return 0.0;
+        }
+        let pairs = outputs.iter().zip(targets);
+        let total: f64 = match self {
+            Loss::MeanSquaredError | Loss::MeanAbsoluteError => {
+                let n_values: usize = targets.iter().map(Vec::len).sum();
+                let sum: f64 = pairs
+                    .flat_map(|(output, target)| output.iter().zip(target))
+                    .map(|(o, t)| match self {
+                        Loss::MeanSquaredError => (o - t).powi(2),
+                        _ => (o - t).abs(),
+                    })
+                    .sum();
+                return sum / n_values.max(1) as f64;
+            }
+            Loss::CrossEntropy => pairs
+                .map(|(output, target)| {
+                    if output.len() == 1 {
+                        let p = output[0].clamp(EPSILON, 1.0 - EPSILON);
+                        -(target[0] * p.ln() + (1.0 - target[0]) * (1.0 - p).ln())
+                    } else {
+                        let max = output.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                        let log_sum = output.iter().map(|o| (o - max).exp()).sum::<f64>().ln();
+                        -output
+                            .iter()
+                            .zip(target)
+                            .map(|(o, t)| t * (o - max - log_sum))
+                            .sum::<f64>()
+                    }
+                })
+                .sum(),
+            Loss::Accuracy => pairs
+                .filter(|(output, target)| class(output) == class(target))
+                .count() as f64,
+        };
+        total / outputs.len() as f64
+    }
+
+    /// Whether higher values are better, i.e. for `Accuracy`
+    pub fn higher_is_better(self) -> bool {
+        self == Loss::Accuracy
+    }
+}
+
+/// Class of an example, see `Loss::Accuracy`
+fn class(values: &[f64]) -> usize {
+    if values.len() == 1 {
+        (values[0] > 0.5) as usize
+    } else {
+        (0..values.len()).fold(0, |best, i| if values[i] > values[best] { i } else { best })
+    }
+}
+
+/// Losses and fitnesses of a network on the training and validation examples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Evaluation {
+    /// Loss on the training examples
+    pub training_loss: f64,
+    /// Fitness on the training examples, the one used by evolution
+    pub training_fitness: f64,
+    /// Loss on the validation examples, if any
+    pub validation_loss: Option<f64>,
+    /// Fitness on the validation examples, if any
+    pub validation_fitness: Option<f64>,
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "training loss {:.6} (fitness {:.6})",
+            self.training_loss, self.training_fitness
+        )?;
+        if let (Some(loss), Some(fitness)) = (self.validation_loss, self.validation_fitness) {
+            write!(f, ", validation loss {:.6} (fitness {:.6})", loss, fitness)?;
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates networks on the examples of a dataset. See the module
+/// documentation.
+pub struct DatasetEnvironment {
+    training: Dataset,
+    validation: Option<Dataset>,
+    /// How outputs are compared to targets
+    pub loss: Loss,
+    fitness: Box<dyn Fn(f64) -> f64 + Send + Sync>,
+}
+
+impl DatasetEnvironment {
+    /// Evaluate networks with `loss` on all examples of `dataset`. The fitness
+    /// is `1 / (1 + loss)`, or the accuracy itself for `Loss::Accuracy`.
+    pub fn new(dataset: Dataset, loss: Loss) -> DatasetEnvironment {
+        DatasetEnvironment {
+            training: dataset,
+            validation: None,
+            loss,
+            fitness: if loss.higher_is_better() {
+                Box::new(|accuracy| accuracy)
+            } else {
+                Box::new(|loss| 1.0 / (1.0 + loss))
+            },
+        }
+    }
+
+    /// The XOR problem, with the fitness used throughout the examples:
+    /// `16 / (1 + e)`, where `e` is the sum of the squared errors on the four
+    /// cases, so 16 for a perfect network
+    pub fn xor() -> DatasetEnvironment {
+        let dataset = Dataset::new(
+            vec![
+                vec![0.0, 0.0],
+                vec![0.0, 1.0],
+                vec![1.0, 0.0],
+                vec![1.0, 1.0],
+            ],
+            vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]],
+        );
+        DatasetEnvironment::new(dataset, Loss::MeanSquaredError)
+            .with_fitness(|loss| 16.0 / (1.0 + 4.0 * loss))
+    }
+
+    /// Use `validation` to measure the validation fitness. Evolution never
+    /// sees these examples.
+    pub fn with_validation(mut self, validation: Dataset) -> DatasetEnvironment {
+        self.validation = Some(validation);
+        self
+    }
+
+    /// Hold out a random `fraction` of the examples for validation, see
+    /// `Dataset::split()`
+    pub fn with_validation_split(mut self, fraction: f64) -> DatasetEnvironment {
+        let (training, validation) = self.training.split(fraction);
+        self.training = training;
+        self.validation = Some(validation);
+        self
+    }
+
+    /// Turn losses into fitnesses with `fitness`, which must not return
+    /// negative values
+    pub fn with_fitness<F>(mut self, fitness: F) -> DatasetEnvironment
+    where
+        F: Fn(f64) -> f64 + Send + Sync + 'static,
+    {
+        self.fitness = Box::new(fitness);
+        self
+    }
+
+    /// The examples evolution is evaluated on
+    pub fn training(&self) -> &Dataset {
+        &self.training
+    }
+
+    /// The held-out examples, if any
+    pub fn validation(&self) -> Option<&Dataset> {
+        self.validation.as_ref()
+    }
+
+    /// Outputs of `network` for every example of `dataset`
+    pub fn predict(&self, network: &NeuralNetwork, dataset: &Dataset) -> Vec<Vec<f64>> {
+        let ctrnn = network.make_network();
+        dataset
+            .inputs
+            .iter()
+            .zip(&dataset.targets)
+            .map(|(input, target)| {
+                let mut output = vec![0.0; target.len()];
+                ctrnn.activate(input.clone(), &mut output);
+                output
+            })
+            .collect()
+    }
+
+    /// Loss of `network` on `dataset`
+    pub fn loss_on(&self, network: &NeuralNetwork, dataset: &Dataset) -> f64 {
+        self.loss
+            .compute(&self.predict(network, dataset), &dataset.targets)
+    }
+
+    /// Fitness corresponding to `loss`
+    pub fn fitness(&self, loss: f64) -> f64 {
+        (self.fitness)(loss)
+    }
+
+    /// Losses and fitnesses of `network`, typically the champion, on the
+    /// training and validation examples
+    pub fn evaluate(&self, network: &NeuralNetwork) -> Evaluation {
+        let training_loss = self.loss_on(network, &self.training);
+        let validation_loss = self
+            .validation
+            .as_ref()
+            .map(|validation| self.loss_on(network, validation));
+        Evaluation {
+            training_loss,
+            training_fitness: self.fitness(training_loss),
+            validation_loss,
+            validation_fitness: validation_loss.map(|loss| self.fitness(loss)),
+        }
+    }
+}
+
+impl Environment for DatasetEnvironment {
+    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+        self.fitness(self.loss_on(organism, &self.training))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dataset, DatasetEnvironment, DatasetError, Loss};
+    use crate::{Environment, NeuralNetwork};
+
+    #[test]
+    fn csv_should_skip_header_and_comments() {
+        let csv = "a, b, target\n# comment\n1, 2, 3\n\n4.5, -5, 6e1\n";
+        let dataset = Dataset::read_csv(csv.as_bytes(), 2).unwrap();
+        assert_eq!(dataset.inputs, vec![vec![1.0, 2.0], vec![4.5, -5.0]]);
+        assert_eq!(dataset.targets, vec![vec![3.0], vec![60.0]]);
+    }
+
+    #[test]
+    fn csv_should_report_line_of_invalid_row() {
+        let error = Dataset::read_csv("1,2,3\n4,5,6\n7,x,9\n".as_bytes(), 2).unwrap_err();
+        match error {
+            DatasetError::Parse { line, .. } => assert_eq!(line, 3),
+            e => panic!("unexpected error {}", e),
+        }
+        let error = Dataset::read_csv("1,2,3\n4,5\n".as_bytes(), 1).unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+        assert!(Dataset::read_csv("1,2\n".as_bytes(), 2).is_err());
+    }
+
+    #[test]
+    fn losses_should_match_definitions() {
+        let outputs = vec![vec![0.9], vec![0.2]];
+        let targets = vec![vec![1.0], vec![1.0]];
+        let mse = Loss::MeanSquaredError.compute(&outputs, &targets);
+        assert!((mse - (0.01 + 0.64) / 2.0).abs() < 1e-12);
+        let mae = Loss::MeanAbsoluteError.compute(&outputs, &targets);
+        assert!((mae - 0.45).abs() < 1e-12);
+        let ce = Loss::CrossEntropy.compute(&outputs, &targets);
+        assert!((ce + (0.9f64.ln() + 0.2f64.ln()) / 2.0).abs() < 1e-12);
+        assert_eq!(Loss::Accuracy.compute(&outputs, &targets), 0.5);
+
+        let outputs = vec![vec![2.0, 0.0, 0.0], vec![0.0, 1.0, 3.0]];
+        let targets = vec![vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]];
+        assert_eq!(Loss::Accuracy.compute(&outputs, &targets), 0.5);
+        let softmax = |o: &[f64], i: usize| o[i].exp() / o.iter().map(|o| o.exp()).sum::<f64>();
+        let ce = Loss::CrossEntropy.compute(&outputs, &targets);
+        let expected = -(softmax(&outputs[0], 0).ln() + softmax(&outputs[1], 1).ln()) / 2.0;
+        assert!((ce - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn xor_fitness_should_be_sixteen_over_one_plus_squared_error() {
+        let mut network = NeuralNetwork::with_neurons(4);
+        network.neurons.get_mut(&3).unwrap().bias = -0.2;
+        network.add_connection(0, 3, 1.2);
+        network.add_connection(1, 3, -0.7);
+        network.add_connection(3, 2, 0.9);
+        network.add_connection(1, 2, 0.4);
+        let ctrnn = network.make_network();
+        let mut distance = 0.0;
+        for (input, target) in &[
+            ([0.0, 0.0], 0.0),
+            ([0.0, 1.0], 1.0),
+            ([1.0, 0.0], 1.0),
+            ([1.0, 1.0], 0.0),
+        ] {
+            let mut output = [0.0];
+            ctrnn.activate(input.to_vec(), &mut output);
+            distance += (target - output[0]).powi(2);
+        }
+        let fitness = DatasetEnvironment::xor().test(&mut network);
+        assert!((fitness - 16.0 / (1.0 + distance)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn validation_split_should_hold_out_examples() {
+        let inputs = (0..10).map(|i| vec![i as f64]).collect::<Vec<_>>();
+        let dataset = Dataset::new(inputs.clone(), inputs);
+        let environment =
+            DatasetEnvironment::new(dataset, Loss::MeanAbsoluteError).with_validation_split(0.3);
+        assert_eq!(environment.training().len(), 7);
+        let validation = environment.validation().unwrap();
+        assert_eq!(validation.len(), 3);
+        for input in &validation.inputs {
+            assert!(!environment.training().inputs.contains(input));
+        }
+
+        let evaluation = environment.evaluate(&NeuralNetwork::with_neurons(2));
+        let validation_loss = validation.inputs.iter().map(|x| x[0]).sum::<f64>() / 3.0;
+        assert!((evaluation.validation_loss.unwrap() - validation_loss).abs() < 1e-12);
+        assert_eq!(
+            evaluation.validation_fitness,
+            Some(1.0 / (1.0 + validation_loss))
+        );
+    }
+}
@@ -0,0 +1,577 @@
+//! Fitness evaluation on worker processes, possibly on other machines.
+//!
+//! A `Coordinator` listens for workers, and implements `Environment` by
+//! sending each genome to an idle worker and waiting for its fitness, so it
+//! can be passed to `Population::evolve()` like any other environment. A
+//! `Worker` connects to the coordinator and evaluates the genomes it receives
+//! with one of the environments registered under a name; the worker binary is
+//! a small program of the user that registers their environments and calls
+//! `Worker::run()`.
+//!
+//! The protocol is newline-delimited JSON over TCP. The coordinator sends
+//! `{"id": 7, "environment": "xor", "genome": {...}}`, the worker answers
+//! `{"id": 7, "fitness": 3.2}`, or `{"id": 7, "error": "..."}` if it cannot
+//! evaluate the genome.
+//!
+//! A worker that does not answer within `Coordinator::timeout`, disconnects
+//! or breaks the protocol is dropped, and the genome is sent to another
+//! worker, up to `max_attempts` times. A worker that answers with an error
+//! stays in the pool, since another worker would fail in the same way, and
+//! the genome gets `failure_fitness`. Workers can join at any time. What went
+//! wrong is kept until `Coordinator::take_failures()` is called.
+//!
+//! With `in_parallel`, `Population::evolve()` evaluates as many genomes at
+//! once as rayon has threads; use `rayon::ThreadPoolBuilder` to raise that
+//! to the number of workers.
+//!
+//! ```no_run
+//! use rustneat::distributed::{Coordinator, Worker};
+//! use rustneat::{Environment, NeatParams, NeuralNetwork, Population};
+//!
+//! struct Simulation;
+//! impl Environment for Simulation {
+//!     fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+//!         organism.n_connections() as f64
+//!     }
+//! }
+//!
+//! // In the worker processes
+//! let mut worker = Worker::new();
+//! worker.register("simulation", Simulation);
+//! worker.run("127.0.0.1:7878").unwrap();
+//!
+//! // In the coordinator process
+//! let mut coordinator: Coordinator = Coordinator::bind("0.0.0.0:7878", "simulation").unwrap();
+//! let mut population = Population::create_population(150);
+//! population.evolve(&mut coordinator, &NeatParams::default(2, 1), true);
+//! ```
+
+use crate::{Environment, Genome, NeuralNetwork};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::marker::PhantomData;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Serialize)]
+struct Request<'a, G> {
+    id: usize,
+    environment: &'a str,
+    genome: &'a G,
+}
+
+#[derive(Deserialize)]
+struct OwnedRequest<G> {
+    id: usize,
+    environment: String,
+    genome: G,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Response {
+    id: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fitness: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Something that went wrong while a `Coordinator` evaluated a genome
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvaluationFailure {
+    /// No worker was idle within `Coordinator::timeout`
+    NoIdleWorker {
+        /// Id of the genome, counting from 0 in the order of evaluation
+        genome: usize,
+    },
+    /// The connection to a worker broke, timed out or got out of sync; the
+    /// worker was dropped
+    WorkerFailed {
+        /// Id of the genome
+        genome: usize,
+        /// Address of the worker
+        worker: SocketAddr,
+        /// What happened to the connection
+        message: String,
+    },
+    /// A worker answered with an error
+    Rejected {
+        /// Id of the genome
+        genome: usize,
+        /// Address of the worker
+        worker: SocketAddr,
+        /// The error sent by the worker
+        message: String,
+    },
+    /// The genome got `Coordinator::failure_fitness`, after the failures
+    /// recorded before
+    Unevaluated {
+        /// Id of the genome
+        genome: usize,
+    },
+}
+
+impl fmt::Display for EvaluationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvaluationFailure::NoIdleWorker { genome } => {
+                write!(f, "no idle worker for genome {}", genome)
+            }
+            EvaluationFailure::WorkerFailed {
+                genome,
+                worker,
+                message,
+            } => write!(f, "worker {} failed on genome {}: {}", worker, genome, message),
+            EvaluationFailure::Rejected {
+                genome,
+                worker,
+                message,
+            } => write!(
+                f,
+                "worker {} could not evaluate genome {}: {}",
+                worker, genome, message
+            ),
+            EvaluationFailure::Unevaluated { genome } => {
+                write!(f, "could not evaluate genome {}", genome)
+            }
+        }
+    }
+}
+
+/// Why a worker did not evaluate a genome
+enum Failure {
+    /// The connection is broken, timed out or out of sync
+    Connection(io::Error),
+    /// The worker answered with an error, e.g. for an unknown environment
+    Rejected(String),
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Failure {
+        Failure::Connection(e)
+    }
+}
+
+impl From<serde_json::Error> for Failure {
+    fn from(e: serde_json::Error) -> Failure {
+        Failure::Connection(e.into())
+    }
+}
+
+/// Connection to a worker
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    peer: SocketAddr,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Connection> {
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            peer: stream.peer_addr()?,
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    /// Send a request and wait for the fitness
+    fn evaluate(&mut self, request: &str, id: usize, timeout: Duration) -> Result<f64, Failure> {
+        self.writer.write_all(request.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        self.reader.get_ref().set_read_timeout(Some(timeout))?;
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "worker disconnected").into());
+        }
+        let response: Response = serde_json::from_str(&line)?;
+        match response {
+            Response {
+                id: response_id, ..
+            } if response_id != id => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("answer to request {} instead of {}", response_id, id),
+            )
+            .into()),
+            Response {
+                fitness: Some(fitness),
+                ..
+            } => Ok(fitness),
+            Response { error, .. } => Err(Failure::Rejected(
+                error.unwrap_or_else(|| "no fitness".to_string()),
+            )),
+        }
+    }
+}
+
+/// Workers waiting for a genome
+struct Pool {
+    idle: Mutex<VecDeque<Connection>>,
+    available: Condvar,
+    closed: AtomicBool,
+}
+
+/// Evaluates genomes on the workers connected to it. See the module
+/// documentation.
+pub struct Coordinator<G = NeuralNetwork> {
+    environment: String,
+    address: SocketAddr,
+    pool: Arc<Pool>,
+    next_id: AtomicUsize,
+    /// Longest time to wait for a fitness, and for an idle worker
+    pub timeout: Duration,
+    /// Number of workers a genome is sent to before giving up on it
+    pub max_attempts: usize,
+    /// Fitness of genomes that could not be evaluated
+    pub failure_fitness: f64,
+    failures: Mutex<Vec<EvaluationFailure>>,
+    genome: PhantomData<fn(&G)>,
+}
+
+impl<G> Coordinator<G> {
+    /// Listen for workers on `address`. Genomes are evaluated with the
+    /// environment the workers registered as `environment`.
+    pub fn bind<A: ToSocketAddrs>(address: A, environment: &str) -> io::Result<Coordinator<G>> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let pool = Arc::new(Pool {
+            idle: Mutex::new(VecDeque::new()),
+            available: Condvar::new(),
+            closed: AtomicBool::new(false),
+        });
+        let accepting = pool.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accepting.closed.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(connection) = stream.and_then(Connection::new) {
+                    accepting.idle.lock().unwrap().push_back(connection);
+                    accepting.available.notify_one();
+                }
+            }
+        });
+        Ok(Coordinator {
+            environment: environment.to_string(),
+            address,
+            pool,
+            next_id: AtomicUsize::new(0),
+            timeout: Duration::from_secs(60),
+            max_attempts: 3,
+            failure_fitness: 0.0,
+            failures: Mutex::new(Vec::new()),
+            genome: PhantomData,
+        })
+    }
+
+    /// The address workers connect to
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Number of workers currently waiting for a genome
+    pub fn n_idle_workers(&self) -> usize {
+        self.pool.idle.lock().unwrap().len()
+    }
+
+    /// What went wrong since the last call, in order
+    pub fn take_failures(&self) -> Vec<EvaluationFailure> {
+        std::mem::take(&mut *self.failures.lock().unwrap())
+    }
+
+    fn record(&self, failure: EvaluationFailure) {
+        self.failures.lock().unwrap().push(failure);
+    }
+
+    /// Wait until `n` workers are connected and idle, or `timeout` passes.
+    /// Returns whether they are.
+    pub fn wait_for_workers(&self, n: usize, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut idle = self.pool.idle.lock().unwrap();
+        while idle.len() < n {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            idle = self
+                .pool
+                .available
+                .wait_timeout(idle, deadline - now)
+                .unwrap()
+                .0;
+        }
+        true
+    }
+
+    /// Take the longest idle worker, waiting up to `timeout` for one
+    fn take_worker(&self) -> Option<Connection> {
+        let deadline = Instant::now() + self.timeout;
+        let mut idle = self.pool.idle.lock().unwrap();
+        loop {
+            if let Some(connection) = idle.pop_front() {
+                return Some(connection);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            idle = self
+                .pool
+                .available
+                .wait_timeout(idle, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    fn return_worker(&self, connection: Connection) {
+        self.pool.idle.lock().unwrap().push_back(connection);
+        self.pool.available.notify_one();
+    }
+}
+
+impl<G> Drop for Coordinator<G> {
+    fn drop(&mut self) {
+        // Wake up the accepting thread so that it ends
+        self.pool.closed.store(true, Ordering::SeqCst);
+        TcpStream::connect(self.address).ok();
+    }
+}
+
+impl<G: Genome + Serialize> Environment<G> for Coordinator<G> {
+    fn test(&self, organism: &mut G) -> f64 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let request = serde_json::to_string(&Request {
+            id,
+            environment: &self.environment,
+            genome: &*organism,
+        })
+        .unwrap();
+        for _ in 0..self.max_attempts {
+            let mut worker = match self.take_worker() {
+                Some(worker) => worker,
+                None => {
+                    self.record(EvaluationFailure::NoIdleWorker { genome: id });
+                    break;
+                }
+            };
+            match worker.evaluate(&request, id, self.timeout) {
+                Ok(fitness) => {
+                    self.return_worker(worker);
+                    return fitness;
+                }
+                Err(Failure::Connection(e)) => self.record(EvaluationFailure::WorkerFailed {
+                    genome: id,
+                    worker: worker.peer,
+                    message: e.to_string(),
+                }),
+                Err(Failure::Rejected(message)) => {
+                    self.record(EvaluationFailure::Rejected {
+                        genome: id,
+                        worker: worker.peer,
+                        message,
+                    });
+                    self.return_worker(worker);
+                    break;
+                }
+            }
+        }
+        self.record(EvaluationFailure::Unevaluated { genome: id });
+        self.failure_fitness
+    }
+}
+
+/// Evaluates the genomes sent by a `Coordinator`. See the module
+/// documentation.
+pub struct Worker<G = NeuralNetwork> {
+    environments: HashMap<String, Box<dyn Environment<G>>>,
+}
+
+impl<G: Genome> Default for Worker<G> {
+    fn default() -> Worker<G> {
+        Worker {
+            environments: HashMap::new(),
+        }
+    }
+}
+
+impl<G: Genome + DeserializeOwned> Worker<G> {
+    /// Create a worker without environments
+    pub fn new() -> Worker<G> {
+        Worker::default()
+    }
+
+    /// Make `environment` available to coordinators as `name`
+    pub fn register<E: Environment<G> + 'static>(&mut self, name: &str, environment: E) {
+        self.environments
+            .insert(name.to_string(), Box::new(environment));
+    }
+
+    /// Connect to the coordinator at `address` and evaluate genomes until it
+    /// closes the connection.
+    pub fn run<A: ToSocketAddrs>(&self, address: A) -> io::Result<()> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        self.serve(BufReader::new(stream.try_clone()?), stream)
+    }
+
+    /// Answer the requests read from `reader`
+    fn serve<R: BufRead, W: Write>(&self, reader: R, mut writer: W) -> io::Result<()> {
+        for line in reader.lines() {
+            let response = self.answer(&line?);
+            writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn answer(&self, line: &str) -> Response {
+        let request: OwnedRequest<G> = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                // Keep the id if the rest is broken
+                let id = serde_json::from_str::<serde_json::Value>(line)
+                    .ok()
+                    .and_then(|value| value["id"].as_u64())
+                    .unwrap_or(0) as usize;
+                return Response {
+                    id,
+                    fitness: None,
+                    error: Some(format!("invalid request: {}", e)),
+                };
+            }
+        };
+        let OwnedRequest {
+            id,
+            environment,
+            mut genome,
+        } = request;
+        match self.environments.get(&environment) {
+            Some(environment) => Response {
+                id,
+                fitness: Some(environment.test(&mut genome)),
+                error: None,
+            },
+            None => Response {
+                id,
+                fitness: None,
+                error: Some(format!("unknown environment {:?}", environment)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Connections;
+    impl Environment for Connections {
+        fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+            organism.n_connections() as f64
+        }
+    }
+
+    fn spawn_worker(address: SocketAddr) {
+        thread::spawn(move || {
+            let mut worker = Worker::new();
+            worker.register("connections", Connections);
+            worker.run(address).unwrap();
+        });
+    }
+
+    fn genome(n_connections: usize) -> NeuralNetwork {
+        let mut genome = NeuralNetwork::with_neurons(n_connections);
+        for i in 0..n_connections {
+            genome.add_connection(i, (i + 1) % n_connections, 0.5);
+        }
+        genome
+    }
+
+    #[test]
+    fn coordinator_should_evaluate_on_workers() {
+        let coordinator = Coordinator::bind("127.0.0.1:0", "connections").unwrap();
+        for _ in 0..3 {
+            spawn_worker(coordinator.local_addr());
+        }
+        assert!(coordinator.wait_for_workers(3, Duration::from_secs(10)));
+        let fitnesses = (1..20)
+            .map(|n| coordinator.test(&mut genome(n)))
+            .collect::<Vec<_>>();
+        assert_eq!(fitnesses, (1..20).map(|n| n as f64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn failed_workers_should_be_dropped_and_genomes_redispatched() {
+        let mut coordinator = Coordinator::bind("127.0.0.1:0", "connections").unwrap();
+        coordinator.timeout = Duration::from_millis(500);
+        // A worker that never answers, and one that hangs up
+        let silent = TcpStream::connect(coordinator.local_addr()).unwrap();
+        let hung_up = TcpStream::connect(coordinator.local_addr()).unwrap();
+        assert!(coordinator.wait_for_workers(2, Duration::from_secs(10)));
+        drop(hung_up);
+        spawn_worker(coordinator.local_addr());
+        assert!(coordinator.wait_for_workers(3, Duration::from_secs(10)));
+
+        // The first genome goes to the bad workers first
+        for n in 1..5 {
+            assert_eq!(coordinator.test(&mut genome(n)), n as f64);
+        }
+        assert_eq!(coordinator.n_idle_workers(), 1);
+        let failures = coordinator.take_failures();
+        assert_eq!(failures.len(), 2);
+        assert!(failures.iter().all(|failure| match failure {
+            EvaluationFailure::WorkerFailed { genome, .. } => *genome == 0,
+            _ => false,
+        }));
+        assert!(coordinator.take_failures().is_empty());
+        drop(silent);
+    }
+
+    #[test]
+    fn unknown_environment_should_fail_evaluation() {
+        let mut coordinator = Coordinator::bind("127.0.0.1:0", "missing").unwrap();
+        coordinator.timeout = Duration::from_millis(500);
+        coordinator.failure_fitness = -1.0;
+        spawn_worker(coordinator.local_addr());
+        assert!(coordinator.wait_for_workers(1, Duration::from_secs(10)));
+        assert_eq!(coordinator.test(&mut genome(2)), -1.0);
+        // The worker is fine, only the config is wrong
+        assert_eq!(coordinator.n_idle_workers(), 1);
+        assert_eq!(coordinator.test(&mut genome(3)), -1.0);
+        assert_eq!(coordinator.n_idle_workers(), 1);
+        let failures = coordinator.take_failures();
+        assert_eq!(failures.len(), 4);
+        assert!(matches!(failures[0], EvaluationFailure::Rejected { genome: 0, .. }));
+        assert_eq!(failures[1], EvaluationFailure::Unevaluated { genome: 0 });
+    }
+
+    #[cfg(feature = "evolution")]
+    #[test]
+    fn population_should_evolve_with_distributed_evaluation() {
+        use crate::{NeatParams, Population};
+
+        let mut coordinator = Coordinator::bind("127.0.0.1:0", "connections").unwrap();
+        for _ in 0..4 {
+            spawn_worker(coordinator.local_addr());
+        }
+        assert!(coordinator.wait_for_workers(4, Duration::from_secs(10)));
+        let p = NeatParams::default(2, 1);
+        let mut population = Population::create_population(30);
+        for _ in 0..5 {
+            population.evolve(&mut coordinator, &p, true);
+        }
+        let mut champion = population.get_champion();
+        assert!(champion.fitness > 0.0);
+        assert_eq!(champion.fitness, Connections.test(&mut champion.genome));
+        assert_eq!(coordinator.n_idle_workers(), 4);
+    }
+}
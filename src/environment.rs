@@ -6,3 +6,15 @@ pub trait Environment<G: Genome = NeuralNetwork>: Sync {
     /// against
     fn test(&self, organism: &mut G) -> f64;
 }
+
+/// The fitness `env` gives `organism`. NaN, e.g. from a network whose state
+/// diverged, counts as 0, the lowest fitness.
+#[cfg(feature = "evolution")]
+pub(crate) fn fitness<G: Genome>(env: &dyn Environment<G>, organism: &mut G) -> f64 {
+    let fitness = env.test(organism);
+    if fitness.is_nan() {
+        0.0
+    } else {
+        fitness
+    }
+}
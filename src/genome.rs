@@ -1,4 +1,5 @@
-use crate::{NeatParams, NeuralNetwork};
+use crate::{Mutation, NeatParams, NeuralNetwork};
+use serde_derive::{Deserialize, Serialize};
 
 /// Implementing `Genome` conceptually means that the implementor "has a
 /// genome", and the implementor can be called an "organism".
@@ -7,12 +8,27 @@ pub trait Genome: Clone + Default + Send + std::fmt::Debug {
     /// mutations
     fn mutate(&mut self, innovation_id: &mut usize, p: &NeatParams);
 
+    /// Same as `mutate`, but also returns the mutation operations that were
+    /// applied, so that they can be recorded in the lineage of the organism.
+    /// The default implementation can only tell that *something* happened.
+    fn mutate_tracked(&mut self, innovation_id: &mut usize, p: &NeatParams) -> Vec<Mutation> {
+        self.mutate(innovation_id, p);
+        vec![Mutation::Unspecified]
+    }
+
     /// `fittest` is true if `other` is more fit.
     fn mate(&self, other: &Self, fittest: bool, p: &NeatParams) -> Self;
 
     /// TODO: how should it be implemented for e.g. a composed organism?
     fn distance(&self, other: &Self, p: &NeatParams) -> f64;
 
+    /// Size of the genome, e.g. the number of neurons and connections of a
+    /// network, used to regulate its growth (see `NeatParams::parsimony_coef`
+    /// and `NeatParams::pruning_threshold`). 0 if unknown.
+    fn complexity(&self) -> usize {
+        0
+    }
+
     /// Compare another Genome for species equality
     // TODO This should be impl Eq
     fn is_same_specie(&self, other: &Self, p: &NeatParams) -> bool {
@@ -22,12 +38,22 @@ pub trait Genome: Clone + Default + Send + std::fmt::Debug {
 
 /// Used in algorithm just to group an organism (genome) with its fitness, and
 /// also in the interface to get the fitness of organisms
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct Organism<G = NeuralNetwork> {
     /// The genome of this organism
     pub genome: G,
     /// The fitness calculated as part of the NEAT algorithm
     pub fitness: f64,
+    /// Unique id of this organism within its `Population`. Assigned by the
+    /// population when the organism is born.
+    pub id: usize,
+    /// Ids of the organisms this one was bred from. The fittest parent comes
+    /// first. Empty for organisms of the initial population.
+    pub parents: Vec<usize>,
+    /// The mutation operations applied to this organism after mating
+    pub mutations: Vec<Mutation>,
+    /// The generation in which this organism was born
+    pub generation: usize,
 }
 impl<G: Genome> Organism<G> {
     /// Create a new organism with fitness 0.0.
@@ -35,18 +61,29 @@ impl<G: Genome> Organism<G> {
         Organism {
             genome: organism,
             fitness: 0.0,
+            id: 0,
+            parents: Vec::new(),
+            mutations: Vec::new(),
+            generation: 0,
         }
     }
     /// Returns a cloned `Organism` with a mutated genome
     pub fn mutate(&mut self, innovation_id: &mut usize, p: &NeatParams) {
-        self.genome.mutate(innovation_id, p)
+        let mutations = self.genome.mutate_tracked(innovation_id, p);
+        self.mutations.extend(mutations);
     }
     /// Mate with another organism -- this mates the two genomes.
     pub fn mate(&self, other: &Self, p: &NeatParams) -> Organism<G> {
-        Organism::new(
-            self.genome
-                .mate(&other.genome, self.fitness > other.fitness, p),
-        )
+        let fittest = self.fitness > other.fitness;
+        let mut child = Organism::new(self.genome.mate(&other.genome, fittest, p));
+        child.parents = if self.id == other.id {
+            vec![self.id]
+        } else if fittest {
+            vec![self.id, other.id]
+        } else {
+            vec![other.id, self.id]
+        };
+        child
     }
     ///
     pub fn distance(&self, other: &Self, p: &NeatParams) -> f64 {
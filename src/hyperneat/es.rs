@@ -0,0 +1,412 @@
+use super::{Cppn, HyperNeat, Point};
+use crate::nn::NeuralNetwork;
+use indexmap::map::IndexMap;
+use std::collections::HashMap;
+
+/// ES-HyperNEAT (evolvable-substrate HyperNEAT): instead of fixing the hidden
+/// neurons in the substrate, they are placed where the weight pattern of the
+/// CPPN holds the most information.
+///
+/// Starting from every input, the 2D space `[-1, 1]^2` is divided as a
+/// quadtree as long as the CPPN weights in a square vary more than
+/// `division_threshold` (at least down to `initial_depth`, at most down to
+/// `max_depth`). Squares whose weights vary less than `variance_threshold` are
+/// not explored further, and only become hidden neurons if they lie on a band,
+/// i.e. if their weight differs by more than `band_threshold` from both
+/// neighbours along one axis. The search is repeated from the discovered
+/// hidden neurons `iteration_level` times, and then backwards from every
+/// output, which only connects to hidden neurons discovered before. Finally,
+/// hidden neurons that are not on a path from an input to an output are
+/// removed.
+///
+/// The inputs, outputs, weight scale, LEO, bias output and activation come
+/// from `hyperneat`. Its hidden layers and `weight_threshold` are ignored.
+#[derive(Debug, Clone)]
+pub struct EsHyperNeat {
+    /// Inputs and outputs of the substrate, which must be 2D, and how to
+    /// express connections
+    pub hyperneat: HyperNeat,
+    /// Depth down to which the quadtree is always divided. The root square is
+    /// at depth 1.
+    pub initial_depth: usize,
+    /// Maximum depth of the quadtree
+    pub max_depth: usize,
+    /// A square is divided further if the variance of its weights exceeds this
+    pub division_threshold: f64,
+    /// A square is explored for hidden neurons if the variance of its weights
+    /// is at least this
+    pub variance_threshold: f64,
+    /// Minimum difference with both neighbours for a point to be expressed
+    pub band_threshold: f64,
+    /// Number of searches from hidden neurons discovered in the previous
+    /// search
+    pub iteration_level: usize,
+}
+
+/// A square of the quadtree, with the CPPN weight at its center
+struct QuadPoint {
+    x: f64,
+    y: f64,
+    width: f64,
+    level: usize,
+    weight: f64,
+    leo: f64,
+    children: Vec<QuadPoint>,
+}
+
+impl QuadPoint {
+    fn leaf_weights(&self, weights: &mut Vec<f64>) {
+        if self.children.is_empty() {
+            weights.push(self.weight);
+        } else {
+            for child in &self.children {
+                child.leaf_weights(weights);
+            }
+        }
+    }
+    /// Variance of the weights of the leaves below this square
+    fn variance(&self) -> f64 {
+        if self.children.is_empty() {
+            return 0.0;
+        }
+        let mut weights = Vec::new();
+        self.leaf_weights(&mut weights);
+        let mean = weights.iter().sum::<f64>() / weights.len() as f64;
+        weights.iter().map(|w| (w - mean).powi(2)).sum::<f64>() / weights.len() as f64
+    }
+}
+
+impl EsHyperNeat {
+    /// Defaults from the ES-HyperNEAT paper: depth between 2 and 4, division
+    /// and variance thresholds of 0.03, band threshold of 0.3, one iteration
+    pub fn new(hyperneat: HyperNeat) -> EsHyperNeat {
+        EsHyperNeat {
+            hyperneat,
+            initial_depth: 2,
+            max_depth: 4,
+            division_threshold: 0.03,
+            variance_threshold: 0.03,
+            band_threshold: 0.3,
+            iteration_level: 1,
+        }
+    }
+
+    /// Discover the hidden neurons and query the CPPN to build the substrate
+    /// network. Its neurons are the inputs, the outputs and then the hidden
+    /// neurons, in the order of `hidden_positions()`. Panics if the substrate
+    /// is not 2D.
+    pub fn build(&self, cppn: &NeuralNetwork) -> NeuralNetwork {
+        let (hidden, connections) = self.discover(&Cppn::new(cppn));
+        let hyperneat = &self.hyperneat;
+        let n_inputs = hyperneat.substrate.inputs.len();
+        let n_outputs = hyperneat.substrate.outputs.len();
+        let mut network = NeuralNetwork::with_neurons(n_inputs + n_outputs + hidden.len());
+        for gene in network.neurons.values_mut().skip(n_inputs) {
+            gene.activation = hyperneat.activation;
+        }
+        if hyperneat.bias {
+            let cppn = Cppn::new(cppn);
+            let mut output = vec![0.0; hyperneat.cppn_outputs()];
+            let origin = vec![0.0; 2];
+            let bias_output = 1 + hyperneat.leo as usize;
+            let positions = hyperneat.substrate.outputs.iter().chain(hidden.iter());
+            for (id, position) in positions.enumerate() {
+                cppn.query(&origin, position, &mut output);
+                network.neurons.get_mut(&(n_inputs + id)).unwrap().bias =
+                    output[bias_output] * hyperneat.weight_scale;
+            }
+        }
+        for ((source, target), weight) in connections {
+            network.add_connection(source, target, weight * hyperneat.weight_scale);
+        }
+        network
+    }
+
+    /// Positions of the hidden neurons that `build()` discovers
+    pub fn hidden_positions(&self, cppn: &NeuralNetwork) -> Vec<Point> {
+        self.discover(&Cppn::new(cppn)).0
+    }
+
+    /// Hidden neuron positions, and connections between neuron indices
+    /// (inputs, outputs, hidden) with their unscaled weight
+    fn discover(&self, cppn: &Cppn) -> (Vec<Point>, IndexMap<(usize, usize), f64>) {
+        let substrate = &self.hyperneat.substrate;
+        assert_eq!(
+            substrate.dimensions(),
+            2,
+            "ES-HyperNEAT needs a 2D substrate"
+        );
+        let n_inputs = substrate.inputs.len();
+        let n_outputs = substrate.outputs.len();
+        let first_hidden = n_inputs + n_outputs;
+        let mut hidden: Vec<Point> = Vec::new();
+        let mut index: HashMap<(u64, u64), usize> = HashMap::new();
+        let mut connections = IndexMap::new();
+
+        // Find or add a hidden neuron, returns its index and whether it is new
+        let mut hidden_neuron = |point: &Point, hidden: &mut Vec<Point>| {
+            let key = (point[0].to_bits(), point[1].to_bits());
+            if let Some(i) = index.get(&key) {
+                (*i, false)
+            } else {
+                let i = first_hidden + hidden.len();
+                index.insert(key, i);
+                hidden.push(point.clone());
+                (i, true)
+            }
+        };
+
+        // From the inputs
+        let mut unexplored = Vec::new();
+        for (source, input) in substrate.inputs.iter().enumerate() {
+            for (point, weight) in self.search(cppn, input, true) {
+                let (target, new) = hidden_neuron(&point, &mut hidden);
+                if new {
+                    unexplored.push(target);
+                }
+                connections.insert((source, target), weight);
+            }
+        }
+        // From the hidden neurons
+        for _ in 0..self.iteration_level {
+            let mut discovered = Vec::new();
+            for source in unexplored {
+                let position = hidden[source - first_hidden].clone();
+                for (point, weight) in self.search(cppn, &position, true) {
+                    let (target, new) = hidden_neuron(&point, &mut hidden);
+                    if new {
+                        discovered.push(target);
+                    }
+                    connections.insert((source, target), weight);
+                }
+            }
+            unexplored = discovered;
+        }
+        // To the outputs, only from known hidden neurons
+        for (o, output) in substrate.outputs.iter().enumerate() {
+            for (point, weight) in self.search(cppn, output, false) {
+                let key = (point[0].to_bits(), point[1].to_bits());
+                if let Some(source) = index.get(&key) {
+                    connections.insert((*source, n_inputs + o), weight);
+                }
+            }
+        }
+
+        // Keep hidden neurons on a path from an input to an output
+        let reachable = |forward: bool, start: &mut dyn Iterator<Item = usize>| {
+            let mut reached = vec![false; first_hidden + hidden.len()];
+            let mut stack = start.collect::<Vec<_>>();
+            while let Some(neuron) = stack.pop() {
+                if reached[neuron] {
+                    continue;
+                }
+                reached[neuron] = true;
+                for &(source, target) in connections.keys() {
+                    let (from, to) = if forward {
+                        (source, target)
+                    } else {
+                        (target, source)
+                    };
+                    if from == neuron && !reached[to] {
+                        stack.push(to);
+                    }
+                }
+            }
+            reached
+        };
+        let from_inputs = reachable(true, &mut (0..n_inputs));
+        let to_outputs = reachable(false, &mut (n_inputs..first_hidden));
+        let mut new_index = (0..first_hidden).map(Some).collect::<Vec<_>>();
+        let mut kept = Vec::new();
+        for (h, point) in hidden.into_iter().enumerate() {
+            let i = first_hidden + h;
+            if from_inputs[i] && to_outputs[i] {
+                new_index.push(Some(first_hidden + kept.len()));
+                kept.push(point);
+            } else {
+                new_index.push(None);
+            }
+        }
+        let connections = connections
+            .into_iter()
+            .filter_map(|((source, target), weight)| {
+                Some(((new_index[source]?, new_index[target]?), weight))
+            })
+            .collect();
+        (kept, connections)
+    }
+
+    /// Points connected to `a` (outgoing: from `a`, otherwise: to `a`), with
+    /// the weight of the connection
+    fn search(&self, cppn: &Cppn, a: &[f64], outgoing: bool) -> Vec<(Point, f64)> {
+        let mut root = self.quad_point(cppn, a, outgoing, 0.0, 0.0, 1.0, 1);
+        self.divide(cppn, a, outgoing, &mut root);
+        let mut found = Vec::new();
+        self.prune(cppn, a, outgoing, &root, &mut found);
+        found
+    }
+
+    fn query(&self, cppn: &Cppn, a: &[f64], b: &[f64], outgoing: bool) -> (f64, f64) {
+        let mut output = vec![0.0; self.hyperneat.cppn_outputs()];
+        if outgoing {
+            cppn.query(a, b, &mut output);
+        } else {
+            cppn.query(b, a, &mut output);
+        }
+        let leo = if self.hyperneat.leo { output[1] } else { 1.0 };
+        (output[0], leo)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn quad_point(
+        &self,
+        cppn: &Cppn,
+        a: &[f64],
+        outgoing: bool,
+        x: f64,
+        y: f64,
+        width: f64,
+        level: usize,
+    ) -> QuadPoint {
+        let (weight, leo) = self.query(cppn, a, &[x, y], outgoing);
+        QuadPoint {
+            x,
+            y,
+            width,
+            level,
+            weight,
+            leo,
+            children: Vec::new(),
+        }
+    }
+
+    fn divide(&self, cppn: &Cppn, a: &[f64], outgoing: bool, node: &mut QuadPoint) {
+        let half = node.width / 2.0;
+        for &(dx, dy) in &[(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            let child = self.quad_point(
+                cppn,
+                a,
+                outgoing,
+                node.x + dx * half,
+                node.y + dy * half,
+                half,
+                node.level + 1,
+            );
+            node.children.push(child);
+        }
+        if node.level < self.initial_depth
+            || (node.level < self.max_depth && node.variance() > self.division_threshold)
+        {
+            for child in &mut node.children {
+                self.divide(cppn, a, outgoing, child);
+            }
+        }
+    }
+
+    fn prune(
+        &self,
+        cppn: &Cppn,
+        a: &[f64],
+        outgoing: bool,
+        node: &QuadPoint,
+        found: &mut Vec<(Point, f64)>,
+    ) {
+        for child in &node.children {
+            if !child.children.is_empty() && child.variance() >= self.variance_threshold {
+                self.prune(cppn, a, outgoing, child, found);
+            } else {
+                let difference = |dx: f64, dy: f64| {
+                    let point = [child.x + dx * child.width, child.y + dy * child.width];
+                    (child.weight - self.query(cppn, a, &point, outgoing).0).abs()
+                };
+                let horizontal = difference(-1.0, 0.0).min(difference(1.0, 0.0));
+                let vertical = difference(0.0, -1.0).min(difference(0.0, 1.0));
+                if horizontal.max(vertical) > self.band_threshold && child.leo > 0.0 {
+                    found.push((vec![child.x, child.y], child.weight));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EsHyperNeat;
+    use crate::hyperneat::{row, HyperNeat, Substrate};
+    use crate::nn::Activation;
+    use crate::{NeuralNetwork, NeuronGene};
+    use std::f64::consts::PI;
+
+    fn es_hyperneat() -> EsHyperNeat {
+        let substrate = Substrate::new(2, row(3, -1.0), row(2, 1.0));
+        EsHyperNeat::new(HyperNeat::new(substrate))
+    }
+
+    /// CPPN whose weight pattern is a gaussian of the distance between the x
+    /// coordinates plus `sin(pi * (y1 + y2))`, which has bands around `y = 0`
+    /// both from the inputs and from the outputs
+    fn cppn() -> NeuralNetwork {
+        let mut cppn = es_hyperneat().hyperneat.initial_cppn();
+        for &(id, activation) in &[(5, Activation::Gaussian), (6, Activation::Sine)] {
+            let mut gene = NeuronGene::new(0.0, id);
+            gene.activation = activation;
+            cppn.neurons.insert(id, gene);
+        }
+        cppn.add_connection(0, 5, -3.0);
+        cppn.add_connection(2, 5, 3.0);
+        cppn.add_connection(1, 6, PI);
+        cppn.add_connection(3, 6, PI);
+        cppn.add_connection(5, 4, 0.5);
+        cppn.add_connection(6, 4, 1.0);
+        cppn
+    }
+
+    #[test]
+    fn expressed_topology_should_be_deterministic() {
+        let es = es_hyperneat();
+        let cppn = cppn();
+        let network = es.build(&cppn);
+        let hidden = es.hidden_positions(&cppn);
+        assert!(!hidden.is_empty());
+        assert_eq!(network.n_neurons(), 5 + hidden.len());
+        for _ in 0..3 {
+            let other = es.build(&cppn);
+            assert_eq!(es.hidden_positions(&cppn), hidden);
+            let connections = network.connections.values().collect::<Vec<_>>();
+            let other_connections = other.connections.values().collect::<Vec<_>>();
+            assert_eq!(connections.len(), other_connections.len());
+            for (a, b) in connections.iter().zip(&other_connections) {
+                assert_eq!(
+                    (a.in_neuron_id(), a.out_neuron_id(), a.weight),
+                    (b.in_neuron_id(), b.out_neuron_id(), b.weight)
+                );
+            }
+        }
+        // Every hidden neuron is on a path from an input to an output
+        for id in 5..network.n_neurons() {
+            assert!(network.connections.keys().any(|c| c.1 == id));
+            assert!(network.connections.keys().any(|c| c.0 == id));
+        }
+        // Outputs are reached
+        assert!(network.connections.keys().any(|c| c.1 == 3 || c.1 == 4));
+    }
+
+    #[test]
+    fn uniform_weight_pattern_should_express_nothing() {
+        let es = es_hyperneat();
+        let mut cppn = es.hyperneat.initial_cppn();
+        cppn.add_connection(1, 4, 1.0);
+        // The weight depends on the source y only, which is constant in a search
+        let network = es.build(&cppn);
+        assert_eq!(network.n_neurons(), 5);
+        assert_eq!(network.n_connections(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn three_dimensional_substrates_should_panic() {
+        let substrate = Substrate::new(3, vec![vec![0.0; 3]], vec![vec![1.0; 3]]);
+        let es = EsHyperNeat::new(HyperNeat::new(substrate));
+        es.build(&es.hyperneat.initial_cppn());
+    }
+}
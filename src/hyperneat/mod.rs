@@ -0,0 +1,441 @@
+//! HyperNEAT: the evolved genome is a CPPN (compositional pattern producing
+//! network), a `NeuralNetwork` whose neurons mix several activation
+//! functions. The CPPN is not used as a controller itself; it is queried with
+//! the coordinates of pairs of neurons of a `Substrate` and answers with the
+//! weight of the connection between them. That way the same small CPPN can
+//! describe a large, regular network, like a controller for a grid of
+//! sensors.
+//!
+//! The CPPN has `2 * dimensions` inputs, the coordinates of the source and
+//! of the target neuron, and the outputs:
+//!
+//! * the weight of the connection,
+//! * the link expression output (LEO), if `HyperNeat::leo` is set: the
+//!   connection is expressed only when it is positive. Without LEO, the
+//!   connections with a weight below `HyperNeat::weight_threshold` (in
+//!   absolute value) are not expressed.
+//! * the bias of the target neuron, if `HyperNeat::bias` is set. It is
+//!   queried with the source at the origin.
+//!
+//! `HyperNeat` uses hidden neurons at fixed positions, while `EsHyperNeat`
+//! discovers them from the weight pattern of the CPPN.
+//!
+//! ```no_run
+//! use rustneat::hyperneat::{grid, HyperNeat, HyperNeatEnvironment, Substrate};
+//! # use rustneat::{Environment, NeuralNetwork, Population};
+//! # struct Task;
+//! # impl Environment for Task {
+//! #     fn test(&self, _: &mut NeuralNetwork) -> f64 { 0.0 }
+//! # }
+//! let substrate = Substrate::new(3, grid(8, 8, -1.0), grid(2, 2, 1.0))
+//!     .with_hidden_layer(grid(4, 4, 0.0));
+//! let hyperneat = HyperNeat::new(substrate);
+//! let p = hyperneat.cppn_params();
+//! let mut population = Population::create_population_from(hyperneat.initial_cppn(), 150);
+//! let mut environment = HyperNeatEnvironment::new(hyperneat, Task);
+//! population.evolve(&mut environment, &p, true);
+//! ```
+
+use crate::nn::{Activation, Ctrnn, FeedForward, NeuralNetwork};
+use crate::{Environment, NeatParams};
+
+mod es;
+pub use self::es::*;
+
+/// Coordinates of a neuron of a `Substrate`, one per dimension
+pub type Point = Vec<f64>;
+
+/// `n` points evenly spread on the x axis between -1 and 1, at height `y`.
+/// Useful for 2D substrates.
+pub fn row(n: usize, y: f64) -> Vec<Point> {
+    (0..n).map(|i| vec![spread(i, n), y]).collect()
+}
+
+/// `columns * rows` points on a grid between -1 and 1 in x and y, at depth
+/// `z`, row by row. Useful for 3D substrates.
+pub fn grid(columns: usize, rows: usize, z: f64) -> Vec<Point> {
+    (0..rows)
+        .flat_map(|r| (0..columns).map(move |c| vec![spread(c, columns), spread(r, rows), z]))
+        .collect()
+}
+
+fn spread(i: usize, n: usize) -> f64 {
+    if n <= 1 {
+        0.0
+    } else {
+        -1.0 + 2.0 * i as f64 / (n - 1) as f64
+    }
+}
+
+/// The geometry of the network built by `HyperNeat`: the position of every
+/// neuron, in layers.
+#[derive(Debug, Clone)]
+pub struct Substrate {
+    dimensions: usize,
+    /// Positions of the input neurons
+    pub inputs: Vec<Point>,
+    /// Positions of the neurons of every hidden layer, from the inputs to the
+    /// outputs
+    pub hidden_layers: Vec<Vec<Point>>,
+    /// Positions of the output neurons
+    pub outputs: Vec<Point>,
+    /// If false, only consecutive layers are connected, so the network is
+    /// feed-forward. If true, every neuron may connect to every neuron except
+    /// the inputs, including itself.
+    pub recurrent: bool,
+}
+
+impl Substrate {
+    /// Create a substrate without hidden layers. Panics if a point does not
+    /// have `dimensions` coordinates.
+    pub fn new(dimensions: usize, inputs: Vec<Point>, outputs: Vec<Point>) -> Substrate {
+        let substrate = Substrate {
+            dimensions,
+            inputs,
+            hidden_layers: Vec::new(),
+            outputs,
+            recurrent: false,
+        };
+        substrate.check(&substrate.inputs);
+        substrate.check(&substrate.outputs);
+        substrate
+    }
+    /// Add a hidden layer after the existing ones
+    pub fn with_hidden_layer(mut self, layer: Vec<Point>) -> Substrate {
+        self.check(&layer);
+        self.hidden_layers.push(layer);
+        self
+    }
+    /// Allow all connections, see `recurrent`
+    pub fn with_recurrence(mut self) -> Substrate {
+        self.recurrent = true;
+        self
+    }
+    /// Number of coordinates of every point
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+    /// Number of neurons
+    pub fn n_neurons(&self) -> usize {
+        self.positions().len()
+    }
+
+    fn check(&self, points: &[Point]) {
+        assert!(
+            points.iter().all(|p| p.len() == self.dimensions),
+            "Substrate: all points must have {} coordinates",
+            self.dimensions
+        );
+    }
+
+    /// Positions of all neurons, in the order of the neurons of the substrate
+    /// network: inputs, outputs, then hidden layers.
+    fn positions(&self) -> Vec<&Point> {
+        self.inputs
+            .iter()
+            .chain(self.outputs.iter())
+            .chain(self.hidden_layers.iter().flatten())
+            .collect()
+    }
+
+    /// Pairs `(source, target)` of neurons (indices into `positions()`) that
+    /// may be connected
+    fn candidate_connections(&self) -> Vec<(usize, usize)> {
+        let n_inputs = self.inputs.len();
+        let n = self.n_neurons();
+        if self.recurrent {
+            return (0..n)
+                .flat_map(|source| (n_inputs..n).map(move |target| (source, target)))
+                .collect();
+        }
+        // Index ranges of the layers, from inputs to outputs
+        let mut layers = Vec::new();
+        layers.push(0..n_inputs);
+        let mut start = n_inputs + self.outputs.len();
+        for layer in &self.hidden_layers {
+            layers.push(start..start + layer.len());
+            start += layer.len();
+        }
+        layers.push(n_inputs..n_inputs + self.outputs.len());
+        layers
+            .windows(2)
+            .flat_map(|pair| {
+                let targets = pair[1].clone();
+                pair[0]
+                    .clone()
+                    .flat_map(move |source| targets.clone().map(move |target| (source, target)))
+            })
+            .collect()
+    }
+}
+
+/// Builds substrate networks from CPPNs. See the module documentation.
+#[derive(Debug, Clone)]
+pub struct HyperNeat {
+    /// The neurons to connect
+    pub substrate: Substrate,
+    /// Without LEO, connections are only expressed if the magnitude of the
+    /// weight output exceeds this threshold, and the threshold is subtracted
+    /// from it.
+    pub weight_threshold: f64,
+    /// The outputs of the CPPN are multiplied by this to get weights and
+    /// biases
+    pub weight_scale: f64,
+    /// Whether the CPPN has a link expression output
+    pub leo: bool,
+    /// Whether the CPPN has a bias output
+    pub bias: bool,
+    /// Activation function of the hidden and output neurons of the substrate
+    pub activation: Activation,
+}
+
+impl HyperNeat {
+    /// Sane defaults: weight threshold of 0.2, weights scaled by 3, no LEO or
+    /// bias output, sigmoid substrate neurons
+    pub fn new(substrate: Substrate) -> HyperNeat {
+        HyperNeat {
+            substrate,
+            weight_threshold: 0.2,
+            weight_scale: 3.0,
+            leo: false,
+            bias: false,
+            activation: Activation::Sigmoid,
+        }
+    }
+    /// Number of inputs of the CPPN
+    pub fn cppn_inputs(&self) -> usize {
+        2 * self.substrate.dimensions()
+    }
+    /// Number of outputs of the CPPN
+    pub fn cppn_outputs(&self) -> usize {
+        1 + self.leo as usize + self.bias as usize
+    }
+    /// Default parameters to evolve CPPNs: the right number of inputs and
+    /// outputs, and all activation functions
+    pub fn cppn_params(&self) -> NeatParams {
+        NeatParams {
+            activation_mutate_pr: 0.1,
+            activation_options: vec![
+                Activation::Sigmoid,
+                Activation::Tanh,
+                Activation::Gaussian,
+                Activation::Sine,
+                Activation::Abs,
+                Activation::Identity,
+            ],
+            ..NeatParams::default(self.cppn_inputs(), self.cppn_outputs())
+        }
+    }
+    /// A CPPN with input and output neurons, but no connections, to start the
+    /// population from. The input neurons pass the coordinates on unchanged.
+    pub fn initial_cppn(&self) -> NeuralNetwork {
+        let mut cppn = NeuralNetwork::with_neurons(self.cppn_inputs() + self.cppn_outputs());
+        for id in 0..self.cppn_inputs() {
+            cppn.neurons.get_mut(&id).unwrap().activation = Activation::Identity;
+        }
+        cppn
+    }
+
+    /// Query the CPPN to build the substrate network. Its neurons are the
+    /// inputs, the outputs and then the hidden neurons of the substrate, so
+    /// it can be activated with `make_network()`, or `make_feed_forward()`
+    /// if the substrate is not recurrent.
+    pub fn build(&self, cppn: &NeuralNetwork) -> NeuralNetwork {
+        let positions = self.substrate.positions();
+        let n_inputs = self.substrate.inputs.len();
+        let mut network = NeuralNetwork::with_neurons(positions.len());
+        for gene in network.neurons.values_mut().skip(n_inputs) {
+            gene.activation = self.activation;
+        }
+
+        let cppn = Cppn::new(cppn);
+        let mut output = vec![0.0; self.cppn_outputs()];
+        if self.bias {
+            let origin = vec![0.0; self.substrate.dimensions()];
+            let bias_output = 1 + self.leo as usize;
+            for (id, position) in positions.iter().enumerate().skip(n_inputs) {
+                cppn.query(&origin, position, &mut output);
+                network.neurons.get_mut(&id).unwrap().bias =
+                    output[bias_output] * self.weight_scale;
+            }
+        }
+        for (source, target) in self.substrate.candidate_connections() {
+            cppn.query(positions[source], positions[target], &mut output);
+            let weight = output[0];
+            let expressed = if self.leo {
+                output[1] > 0.0
+            } else {
+                weight.abs() > self.weight_threshold
+            };
+            if expressed {
+                let weight = if self.leo {
+                    weight
+                } else {
+                    weight - self.weight_threshold * weight.signum()
+                };
+                network.add_connection(source, target, weight * self.weight_scale);
+            }
+        }
+        network
+    }
+}
+
+/// The fastest available phenotype of a CPPN
+enum Cppn {
+    FeedForward(FeedForward),
+    Ctrnn(Ctrnn),
+}
+
+impl Cppn {
+    fn new(cppn: &NeuralNetwork) -> Cppn {
+        match cppn.make_feed_forward() {
+            Some(network) => Cppn::FeedForward(network),
+            None => Cppn::Ctrnn(cppn.make_network()),
+        }
+    }
+    fn query(&self, source: &[f64], target: &[f64], output: &mut [f64]) {
+        let input = [source, target].concat();
+        for o in output.iter_mut() {
+            *o = 0.0;
+        }
+        match self {
+            Cppn::FeedForward(network) => network.activate(input, output),
+            Cppn::Ctrnn(network) => network.activate(input, output),
+        }
+    }
+}
+
+/// Evaluates CPPNs by building their substrate network with `hyperneat` and
+/// testing it in `environment`.
+pub struct HyperNeatEnvironment<E> {
+    /// Builds the substrate networks
+    pub hyperneat: HyperNeat,
+    /// Tests the substrate networks
+    pub environment: E,
+}
+
+impl<E: Environment> HyperNeatEnvironment<E> {
+    /// Wrap `environment`, which tests substrate networks, into an
+    /// environment that tests CPPNs
+    pub fn new(hyperneat: HyperNeat, environment: E) -> HyperNeatEnvironment<E> {
+        HyperNeatEnvironment {
+            hyperneat,
+            environment,
+        }
+    }
+}
+
+impl<E: Environment> Environment for HyperNeatEnvironment<E> {
+    fn test(&self, cppn: &mut NeuralNetwork) -> f64 {
+        let mut network = self.hyperneat.build(cppn);
+        self.environment.test(&mut network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{grid, row, HyperNeat, HyperNeatEnvironment, Substrate};
+    use crate::nn::Activation;
+    use crate::{Environment, NeuralNetwork};
+
+    /// CPPN whose weight output is the x coordinate of the target neuron
+    fn target_x_cppn(hyperneat: &HyperNeat) -> NeuralNetwork {
+        let mut cppn = hyperneat.initial_cppn();
+        cppn.add_connection(2, 4, 1.0);
+        cppn
+    }
+
+    #[test]
+    fn weights_below_threshold_should_not_be_expressed() {
+        let substrate = Substrate::new(2, row(3, -1.0), row(3, 1.0));
+        let hyperneat = HyperNeat::new(substrate);
+        let network = hyperneat.build(&target_x_cppn(&hyperneat));
+        assert_eq!(network.n_neurons(), 6);
+        // The output at x = 0 gets no connections
+        assert_eq!(network.n_connections(), 6);
+        for gene in network.connections.values() {
+            let expected = if gene.out_neuron_id() == 3 { -2.4 } else { 2.4 };
+            assert!((gene.weight - expected).abs() < 1e-9);
+        }
+        assert_eq!(network.neurons[&4].activation, Activation::Sigmoid);
+    }
+
+    #[test]
+    fn leo_should_decide_expression() {
+        let substrate = Substrate::new(2, row(3, -1.0), row(2, 1.0));
+        let mut hyperneat = HyperNeat::new(substrate);
+        hyperneat.leo = true;
+        let mut cppn = target_x_cppn(&hyperneat);
+        // LEO is the x coordinate of the source
+        cppn.add_connection(0, 5, 1.0);
+        let network = hyperneat.build(&cppn);
+        let connections = network.connections.keys().cloned().collect::<Vec<_>>();
+        assert_eq!(connections, vec![(2, 3), (2, 4)]);
+        assert!((network.connections[&(2, 3)].weight + 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bias_output_should_set_biases_of_non_input_neurons() {
+        let substrate =
+            Substrate::new(3, grid(2, 2, -1.0), grid(1, 1, 1.0)).with_hidden_layer(grid(2, 1, 0.0));
+        let mut hyperneat = HyperNeat::new(substrate);
+        hyperneat.bias = true;
+        let mut cppn = hyperneat.initial_cppn();
+        // Bias is the x coordinate of the target
+        cppn.add_connection(3, 7, 1.0);
+        let network = hyperneat.build(&cppn);
+        let bias = network.get_bias();
+        assert_eq!(bias, vec![0.0, 0.0, 0.0, 0.0, 0.0, -3.0, 3.0]);
+        // No weights: the weight output is not connected
+        assert_eq!(network.n_connections(), 0);
+    }
+
+    #[test]
+    fn layered_substrates_should_be_feed_forward() {
+        let substrate = Substrate::new(2, row(3, -1.0), row(2, 1.0)).with_hidden_layer(row(4, 0.0));
+        let mut hyperneat = HyperNeat::new(substrate.clone());
+        hyperneat.weight_threshold = 0.0;
+        let mut cppn = hyperneat.initial_cppn();
+        // Positive weight everywhere
+        cppn.neurons.get_mut(&0).unwrap().bias = 1.0;
+        cppn.neurons.get_mut(&0).unwrap().activation = Activation::Sigmoid;
+        cppn.add_connection(0, 4, 1.0);
+        let network = hyperneat.build(&cppn);
+        assert_eq!(network.n_connections(), 3 * 4 + 4 * 2);
+        assert!(network.make_feed_forward().is_some());
+
+        hyperneat.substrate = substrate.with_recurrence();
+        let network = hyperneat.build(&cppn);
+        assert_eq!(network.n_connections(), 9 * 6);
+        assert!(network.make_feed_forward().is_none());
+    }
+
+    #[test]
+    fn environment_should_test_the_substrate_network() {
+        struct CountConnections;
+        impl Environment for CountConnections {
+            fn test(&self, network: &mut NeuralNetwork) -> f64 {
+                network.n_connections() as f64
+            }
+        }
+        let hyperneat = HyperNeat::new(Substrate::new(2, row(3, -1.0), row(3, 1.0)));
+        let mut cppn = target_x_cppn(&hyperneat);
+        let environment = HyperNeatEnvironment::new(hyperneat, CountConnections);
+        assert_eq!(environment.test(&mut cppn), 6.0);
+    }
+
+    #[test]
+    fn mutation_should_keep_the_cppn_inputs_unchanged() {
+        use crate::Genome;
+        let hyperneat = HyperNeat::new(Substrate::new(2, row(3, -1.0), row(3, 1.0)));
+        let p = hyperneat.cppn_params();
+        let mut cppn = hyperneat.initial_cppn();
+        let mut innovation_id = cppn.n_neurons();
+        for _ in 0..100 {
+            cppn.mutate(&mut innovation_id, &p);
+        }
+        for id in 0..hyperneat.cppn_inputs() {
+            assert_eq!(cppn.neurons[&id].activation, Activation::Identity);
+        }
+    }
+}
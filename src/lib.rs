@@ -27,14 +27,16 @@ extern crate rusty_dashed;
 #[macro_use]
 extern crate serde_derive;
 
-#[cfg(feature = "telemetry")]
-extern crate serde_json;
-
 pub use self::environment::Environment;
 pub use self::genome::*;
 pub use self::nn::{ConnectionGene, NeuralNetwork, NeuronGene};
-pub use self::params::NeatParams;
-pub use self::population::Population;
+pub use self::params::{MutationRates, NeatParams, ParamsError};
+pub use self::phylogeny::{Mutation, OrganismRecord, Phylogeny, SpeciesRecord};
+pub use self::rng::seed;
+#[cfg(feature = "evolution")]
+pub use self::archipelago::{Archipelago, Topology};
+#[cfg(feature = "evolution")]
+pub use self::population::{Phase, PhaseStats, Population};
 pub use self::specie::Specie;
 
 /// Trait to define test parameter
@@ -45,6 +47,39 @@ mod genome;
 /// building block of an organism (and in many cases, the only building block).
 pub mod nn;
 mod params;
+/// Portable model format for trained networks
+pub mod model;
+/// ONNX export of acyclic networks
+pub mod onnx;
+/// Rust and C source generation from networks
+pub mod codegen;
+/// Indirect encoding of large networks with CPPNs
+pub mod hyperneat;
+/// Lineage of organisms and species
+mod phylogeny;
+/// Seedable random number generator
+mod rng;
+/// Local refinement of weights and biases between generations
+pub mod refine;
+/// Supervised training with backpropagation, also through time
+pub mod train;
+/// Fitness from supervised datasets, loaded from CSV
+pub mod dataset;
+/// Fitness evaluation on worker processes over TCP
+pub mod distributed;
+/// Environments simulated by external programs
+pub mod process;
+/// Pole balancing and mountain car, simulated natively, and sequence and
+/// memory tasks: parity, delayed recall, copy task and T-maze
+#[cfg(feature = "benchmarks")]
+pub mod benchmarks;
 /// A collection of species with champion
+#[cfg(feature = "evolution")]
 mod population;
+/// Sub-populations with migration between them
+#[cfg(feature = "evolution")]
+mod archipelago;
+/// Hyperparameter search over `NeatParams`
+#[cfg(feature = "evolution")]
+pub mod tune;
 mod specie;
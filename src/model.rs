@@ -0,0 +1,433 @@
+//! Portable file format for trained networks, and loaders for inference.
+//!
+//! A model is a JSON document that holds everything needed to run a network,
+//! without any of the evolution machinery:
+//!
+//! ```json
+//! {
+//!   "format": "rustneat-model",
+//!   "version": 1,
+//!   "neurons": [
+//!     { "id": 0, "bias": 0.0, "activation": "sigmoid", "tau": 1.0 },
+//!     { "id": 1, "bias": 0.0, "activation": "sigmoid", "tau": 1.0 },
+//!     { "id": 2, "bias": -0.5, "activation": "sigmoid", "tau": 1.0 }
+//!   ],
+//!   "connections": [
+//!     { "from": 0, "to": 2, "weight": 1.5 },
+//!     { "from": 1, "to": 2, "weight": -0.7 }
+//!   ],
+//!   "inputs": [0, 1],
+//!   "outputs": [2],
+//!   "integration": { "delta_t": 1.0, "steps": 10 }
+//! }
+//! ```
+//!
+//! * `neurons`: every neuron with a unique `id`, its `bias`, `activation`
+//!   (one of `sigmoid`, `tanh`, `relu`, `identity`, `gaussian`, `sine`,
+//!   `abs`) and time constant `tau`.
+//! * `connections`: weighted connections from neuron `from` to neuron `to`.
+//! * `inputs`: the neuron receiving the i-th input, for every input.
+//! * `outputs`: the neuron read as the j-th output, for every output.
+//! * `integration`: time step and number of steps of every activation of the
+//!   continuous-time network.
+//!
+//! The state `y` of the network evolves as
+//! `y += delta_t / tau * (W * activation(y + bias) - y + input)`, starting from
+//! `y = input`, and the outputs are the states of the output neurons. For
+//! acyclic networks, the settled state can be computed directly with a
+//! `FeedForward` runner.
+//!
+//! `version` is increased whenever the format changes in a way that older
+//! readers cannot read. Readers reject files with a newer version.
+//!
+//! This module only depends on the `nn` phenotypes, so it is available when
+//! building the crate with `default-features = false`, which leaves out the
+//! evolution machinery.
+
+use crate::nn::{Activation, Ctrnn, FeedForward, NeuralNetwork, DELTA_T, STEPS, TAU};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::{error, fmt, fs, io};
+
+/// Value of the `format` field of every model file
+pub const FORMAT: &str = "rustneat-model";
+/// The version of the format written by this crate
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A neuron of a `Model`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelNeuron {
+    /// Unique id of the neuron
+    pub id: usize,
+    /// Bias of the neuron
+    pub bias: f64,
+    /// Activation function of the neuron
+    pub activation: Activation,
+    /// Time constant of the neuron
+    pub tau: f64,
+}
+
+/// A connection of a `Model`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelConnection {
+    /// Id of the source neuron
+    pub from: usize,
+    /// Id of the target neuron
+    pub to: usize,
+    /// Weight of the connection
+    pub weight: f64,
+}
+
+/// Integration settings of the continuous-time network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Integration {
+    /// Time step
+    pub delta_t: f64,
+    /// Number of steps per activation
+    pub steps: usize,
+}
+
+/// A network in the portable model format. See the module documentation for
+/// a description of the format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Model {
+    /// Always `FORMAT`
+    pub format: String,
+    /// Version of the format
+    pub version: u32,
+    /// All neurons
+    pub neurons: Vec<ModelNeuron>,
+    /// All connections
+    pub connections: Vec<ModelConnection>,
+    /// Ids of the neurons receiving the inputs
+    pub inputs: Vec<usize>,
+    /// Ids of the neurons read as outputs
+    pub outputs: Vec<usize>,
+    /// Integration settings
+    pub integration: Integration,
+}
+
+/// Errors when reading a `Model`
+#[derive(Debug)]
+pub enum ModelError {
+    /// The file could not be read or written
+    Io(io::Error),
+    /// The file is not valid JSON, or misses fields
+    Json(serde_json::Error),
+    /// The `format` field is not `FORMAT`
+    UnknownFormat(String),
+    /// The file was written with a newer version of the format
+    UnsupportedVersion(u32),
+    /// The model is not consistent, e.g. a connection refers to a missing
+    /// neuron
+    Invalid(String),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModelError::Io(e) => write!(f, "could not access model file: {}", e),
+            ModelError::Json(e) => write!(f, "could not parse model: {}", e),
+            ModelError::UnknownFormat(format) => write!(f, "unknown model format {:?}", format),
+            ModelError::UnsupportedVersion(version) => write!(
+                f,
+                "model format version {} is newer than the supported version {}",
+                version, FORMAT_VERSION
+            ),
+            ModelError::Invalid(reason) => write!(f, "invalid model: {}", reason),
+        }
+    }
+}
+
+impl error::Error for ModelError {}
+
+impl From<io::Error> for ModelError {
+    fn from(e: io::Error) -> ModelError {
+        ModelError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ModelError {
+    fn from(e: serde_json::Error) -> ModelError {
+        ModelError::Json(e)
+    }
+}
+
+impl Model {
+    /// Parse and validate a model from JSON.
+    pub fn from_json(json: &str) -> Result<Model, ModelError> {
+        let model: Model = serde_json::from_str(json)?;
+        model.validate()?;
+        Ok(model)
+    }
+    /// Read and validate a model from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Model, ModelError> {
+        Model::from_json(&fs::read_to_string(path)?)
+    }
+    /// Serialize the model to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+    /// Write the model to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ModelError> {
+        fs::write(path, self.to_json())?;
+        Ok(())
+    }
+
+    /// Check that the model is consistent.
+    pub fn validate(&self) -> Result<(), ModelError> {
+        if self.format != FORMAT {
+            return Err(ModelError::UnknownFormat(self.format.clone()));
+        }
+        if self.version > FORMAT_VERSION {
+            return Err(ModelError::UnsupportedVersion(self.version));
+        }
+        let mut ids = HashSet::new();
+        for neuron in &self.neurons {
+            if !ids.insert(neuron.id) {
+                return Err(ModelError::Invalid(format!(
+                    "duplicate neuron {}",
+                    neuron.id
+                )));
+            }
+            if neuron.tau <= 0.0 || neuron.tau.is_nan() {
+                return Err(ModelError::Invalid(format!(
+                    "neuron {} has non-positive tau {}",
+                    neuron.id, neuron.tau
+                )));
+            }
+        }
+        for connection in &self.connections {
+            for id in &[connection.from, connection.to] {
+                if !ids.contains(id) {
+                    return Err(ModelError::Invalid(format!(
+                        "connection {} -> {} refers to missing neuron {}",
+                        connection.from, connection.to, id
+                    )));
+                }
+            }
+        }
+        let mut io = HashSet::new();
+        for id in self.inputs.iter().chain(&self.outputs) {
+            if !ids.contains(id) {
+                return Err(ModelError::Invalid(format!(
+                    "input or output refers to missing neuron {}",
+                    id
+                )));
+            }
+            if !io.insert(*id) {
+                return Err(ModelError::Invalid(format!(
+                    "neuron {} is used more than once as input or output",
+                    id
+                )));
+            }
+        }
+        if self.integration.delta_t <= 0.0
+            || self.integration.delta_t.is_nan()
+            || self.integration.steps == 0
+        {
+            return Err(ModelError::Invalid(
+                "integration needs a positive time step and number of steps".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Number of inputs of the network
+    pub fn n_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+    /// Number of outputs of the network
+    pub fn n_outputs(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Order the neurons like the runners expect them: inputs, then outputs,
+    /// then the rest. Returns the neurons in that order, and the index of
+    /// every neuron id.
+    pub(crate) fn layout(&self) -> (Vec<&ModelNeuron>, HashMap<usize, usize>) {
+        let by_id = self
+            .neurons
+            .iter()
+            .map(|neuron| (neuron.id, neuron))
+            .collect::<HashMap<_, _>>();
+        let mut neurons = self
+            .inputs
+            .iter()
+            .chain(&self.outputs)
+            .map(|id| by_id[id])
+            .collect::<Vec<_>>();
+        neurons.extend(
+            self.neurons
+                .iter()
+                .filter(|n| !self.inputs.contains(&n.id) && !self.outputs.contains(&n.id)),
+        );
+        let index = neurons
+            .iter()
+            .enumerate()
+            .map(|(i, neuron)| (neuron.id, i))
+            .collect();
+        (neurons, index)
+    }
+
+    /// Create a continuous-time network. It is activated with exactly
+    /// `n_inputs()` inputs, and writes up to `n_outputs()` outputs.
+    pub fn to_ctrnn(&self) -> Ctrnn {
+        let (neurons, index) = self.layout();
+        let n = neurons.len();
+        let mut wij = vec![0.0; n * n];
+        for connection in &self.connections {
+            wij[index[&connection.to] * n + index[&connection.from]] += connection.weight;
+        }
+        Ctrnn::new(
+            neurons.iter().map(|n| n.bias).collect(),
+            neurons.iter().map(|n| n.tau).collect(),
+            wij,
+            self.integration.delta_t,
+            self.integration.steps,
+        )
+        .with_activations(neurons.iter().map(|n| n.activation).collect())
+    }
+
+    /// Create a feed-forward network, which computes the settled state of the
+    /// continuous-time network. It is activated with exactly `n_inputs()`
+    /// inputs, and writes up to `n_outputs()` outputs. Returns `None` if the
+    /// network has recurrent connections.
+    pub fn to_feed_forward(&self) -> Option<FeedForward> {
+        let (neurons, index) = self.layout();
+        let connections = self
+            .connections
+            .iter()
+            .map(|c| (index[&c.from], index[&c.to], c.weight))
+            .collect::<Vec<_>>();
+        FeedForward::new(
+            neurons.iter().map(|n| n.bias).collect(),
+            neurons.iter().map(|n| n.activation).collect(),
+            &connections,
+        )
+    }
+}
+
+impl NeuralNetwork {
+    /// Export the network in the portable model format, with the same
+    /// behaviour as `make_network()` when it is activated with `n_inputs`
+    /// inputs and `n_outputs` outputs. Panics if the network has fewer
+    /// neurons than that. Plasticity rules and modulatory neurons are not part
+    /// of the format, so the model has the initial weights.
+    pub fn to_model(&self, n_inputs: usize, n_outputs: usize) -> Model {
+        let ids = self.sorted_neuron_ids();
+        assert!(
+            ids.len() >= n_inputs + n_outputs,
+            "to_model: the network has fewer neurons than inputs and outputs"
+        );
+        Model {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            neurons: ids
+                .iter()
+                .map(|id| ModelNeuron {
+                    id: *id,
+                    bias: self.neurons[id].bias,
+                    activation: self.neurons[id].activation,
+                    tau: TAU,
+                })
+                .collect(),
+            connections: self
+                .connections
+                .values()
+                .map(|gene| ModelConnection {
+                    from: gene.in_neuron_id(),
+                    to: gene.out_neuron_id(),
+                    weight: gene.weight,
+                })
+                .collect(),
+            inputs: ids[..n_inputs].to_vec(),
+            outputs: ids[n_inputs..n_inputs + n_outputs].to_vec(),
+            integration: Integration {
+                delta_t: DELTA_T,
+                steps: STEPS,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Model, ModelError};
+    use crate::NeuralNetwork;
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        for (a, b) in a.iter().zip(b) {
+            assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn model_should_round_trip_through_json() {
+        let mut network = NeuralNetwork::with_neurons(5);
+        network.neurons.get_mut(&4).unwrap().bias = 0.3;
+        network.add_connection(0, 4, 1.5);
+        network.add_connection(1, 4, -0.7);
+        network.add_connection(4, 2, 2.0);
+        network.add_connection(4, 3, -1.0);
+        network.add_connection(2, 4, 0.5);
+        let model = network.to_model(2, 2);
+        let loaded = Model::from_json(&model.to_json()).unwrap();
+        assert_eq!(model, loaded);
+
+        let ctrnn = network.make_network();
+        let loaded_ctrnn = loaded.to_ctrnn();
+        for input in &[[0.0, 0.0], [0.0, 1.0], [1.0, 0.5], [-2.0, 3.0]] {
+            let mut expected = vec![0.0; 2];
+            let mut output = vec![0.0; 2];
+            ctrnn.activate(input.to_vec(), &mut expected);
+            loaded_ctrnn.activate(input.to_vec(), &mut output);
+            assert_close(&expected, &output);
+        }
+        // Recurrent
+        assert!(loaded.to_feed_forward().is_none());
+    }
+
+    #[test]
+    fn feed_forward_should_match_ctrnn_for_acyclic_networks() {
+        let mut network = NeuralNetwork::with_neurons(6);
+        network.neurons.get_mut(&5).unwrap().bias = -0.2;
+        network.neurons.get_mut(&4).unwrap().bias = 0.4;
+        network.add_connection(0, 4, 1.5);
+        network.add_connection(1, 4, -0.7);
+        network.add_connection(4, 5, 2.0);
+        network.add_connection(5, 2, -1.0);
+        network.add_connection(0, 3, 0.5);
+        network.add_connection(5, 3, 1.2);
+        let model = Model::from_json(&network.to_model(2, 2).to_json()).unwrap();
+        let ctrnn = network.make_network();
+        let feed_forward = model.to_feed_forward().unwrap();
+        for input in &[[0.0, 0.0], [0.0, 1.0], [1.0, 0.5], [-2.0, 3.0]] {
+            let mut expected = vec![0.0; 2];
+            let mut output = vec![0.0; 2];
+            ctrnn.activate(input.to_vec(), &mut expected);
+            feed_forward.activate(input.to_vec(), &mut output);
+            assert_close(&expected, &output);
+        }
+    }
+
+    #[test]
+    fn model_should_reject_newer_versions_and_dangling_connections() {
+        let network = NeuralNetwork::with_neurons(2);
+        let mut model = network.to_model(1, 1);
+        model.version += 1;
+        match Model::from_json(&model.to_json()) {
+            Err(ModelError::UnsupportedVersion(_)) => {}
+            other => panic!("{:?}", other),
+        }
+        let json = network.to_model(1, 1).to_json().replace(
+            "\"connections\": []",
+            "\"connections\": [{\"from\": 0, \"to\": 7, \"weight\": 1.0}]",
+        );
+        match Model::from_json(&json) {
+            Err(ModelError::Invalid(_)) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+}
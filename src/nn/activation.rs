@@ -0,0 +1,75 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// Activation function of a neuron. It is applied to the state of the neuron
+/// plus its bias.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Activation {
+    /// Steepened logistic function `1 / (1 + exp(-5x))`, inspired by
+    /// neat-python. This is the activation used by `NeuralNetwork`.
+    #[default]
+    Sigmoid,
+    /// Hyperbolic tangent
+    Tanh,
+    /// Rectified linear unit `max(0, x)`
+    Relu,
+    /// `x`
+    Identity,
+    /// Gaussian bell `exp(-x^2)`
+    Gaussian,
+    /// `sin(x)`
+    Sine,
+    /// `|x|`
+    Abs,
+}
+
+impl Activation {
+    /// Apply the activation function.
+    pub fn apply(self, x: f64) -> f64 {
+        match self {
+            Activation::Sigmoid => {
+                // Inspired from neat-python
+                let y = (x * 5.0).clamp(-60.0, 60.0);
+                1.0 / (1.0 + (-y).exp())
+            }
+            Activation::Tanh => x.tanh(),
+            Activation::Relu => x.max(0.0),
+            Activation::Identity => x,
+            Activation::Gaussian => (-x * x).exp(),
+            Activation::Sine => x.sin(),
+            Activation::Abs => x.abs(),
+        }
+    }
+
+    /// Derivative of the activation function at `x`
+    pub fn derivative(self, x: f64) -> f64 {
+        match self {
+            Activation::Sigmoid => {
+                if (x * 5.0).abs() > 60.0 {
+                    0.0
+                } else {
+                    let y = self.apply(x);
+                    5.0 * y * (1.0 - y)
+                }
+            }
+            Activation::Tanh => 1.0 - x.tanh().powi(2),
+            Activation::Relu => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Activation::Identity => 1.0,
+            Activation::Gaussian => -2.0 * x * (-x * x).exp(),
+            Activation::Sine => x.cos(),
+            Activation::Abs => {
+                if x < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use super::{ConnectionId, NeuralNetwork, NeuronId};
+use indexmap::map::IndexMap;
+use std::collections::{HashMap, HashSet};
+
+/// Facts about the graph of a network. Neurons are identified by id, and
+/// inputs and outputs by position, like in `neuron_roles()`.
+impl NeuralNetwork {
+    /// Number of incoming connections of every neuron, ordered by id
+    pub fn fan_in(&self) -> IndexMap<NeuronId, usize> {
+        let mut fan_in = self.zero_per_neuron();
+        for (_, target) in self.connections.keys() {
+            *fan_in.get_mut(target).unwrap() += 1;
+        }
+        fan_in
+    }
+    /// Number of outgoing connections of every neuron, ordered by id
+    pub fn fan_out(&self) -> IndexMap<NeuronId, usize> {
+        let mut fan_out = self.zero_per_neuron();
+        for (source, _) in self.connections.keys() {
+            *fan_out.get_mut(source).unwrap() += 1;
+        }
+        fan_out
+    }
+
+    /// The neurons that can be reached from one of `sources` by following
+    /// connections, including the sources
+    pub fn downstream(&self, sources: &[NeuronId]) -> HashSet<NeuronId> {
+        search(sources, &self.successors())
+    }
+    /// The neurons from which one of `targets` can be reached by following
+    /// connections, including the targets
+    pub fn upstream(&self, targets: &[NeuronId]) -> HashSet<NeuronId> {
+        search(targets, &self.predecessors())
+    }
+
+    /// Whether the network has no cycles (including connections from a neuron
+    /// to itself), so that `make_feed_forward()` can evaluate it
+    pub fn is_acyclic(&self) -> bool {
+        self.recurrent_connections().is_empty()
+    }
+    /// The connections that are part of a cycle
+    pub fn recurrent_connections(&self) -> Vec<ConnectionId> {
+        let mut component = HashMap::new();
+        for (i, neurons) in self.strongly_connected_components().iter().enumerate() {
+            for id in neurons {
+                component.insert(*id, i);
+            }
+        }
+        self.connections
+            .keys()
+            .filter(|(source, target)| component[source] == component[target])
+            .cloned()
+            .collect()
+    }
+    /// The strongly connected components: the largest groups of neurons that
+    /// can all reach each other. Every neuron is in exactly one, which has
+    /// more than one neuron, or a connection to itself, if and only if the
+    /// neuron is part of a cycle. Components come in topological order,
+    /// i.e. no connection goes to an earlier one, and the neurons of each
+    /// are ordered by id.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NeuronId>> {
+        // Tarjan's algorithm, which finds the components in reverse
+        // topological order. It runs on a stack of its own rather than
+        // recursively, since paths through large networks can be deep.
+        let successors = self.successors();
+        let mut index = HashMap::new();
+        let mut low_link = HashMap::new();
+        let mut stack = Vec::new();
+        let mut on_stack = HashSet::new();
+        let mut components = Vec::new();
+        for root in self.sorted_neuron_ids() {
+            if index.contains_key(&root) {
+                continue;
+            }
+            // The neurons being visited, with the position of the next
+            // successor to look at
+            let mut visiting = vec![(root, 0)];
+            let root_index = index.len();
+            index.insert(root, root_index);
+            low_link.insert(root, root_index);
+            stack.push(root);
+            on_stack.insert(root);
+            while let Some(&mut (id, ref mut position)) = visiting.last_mut() {
+                if let Some(&next) = successors[&id].get(*position) {
+                    *position += 1;
+                    if !index.contains_key(&next) {
+                        let next_index = index.len();
+                        index.insert(next, next_index);
+                        low_link.insert(next, next_index);
+                        stack.push(next);
+                        on_stack.insert(next);
+                        visiting.push((next, 0));
+                    } else if on_stack.contains(&next) {
+                        let link = low_link[&id].min(index[&next]);
+                        low_link.insert(id, link);
+                    }
+                    continue;
+                }
+                // All successors visited
+                visiting.pop();
+                if let Some(&(parent, _)) = visiting.last() {
+                    let link = low_link[&parent].min(low_link[&id]);
+                    low_link.insert(parent, link);
+                }
+                if low_link[&id] == index[&id] {
+                    let mut component = Vec::new();
+                    loop {
+                        let member = stack.pop().unwrap();
+                        on_stack.remove(&member);
+                        component.push(member);
+                        if member == id {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+        components.reverse();
+        components
+    }
+    /// The elementary cycles, i.e. closed paths that visit no neuron twice, at
+    /// most `limit` of them, since there can be exponentially many. Every
+    /// cycle starts at its neuron with the lowest id, and does not repeat it
+    /// at the end.
+    pub fn cycles(&self, limit: usize) -> Vec<Vec<NeuronId>> {
+        let successors = self.successors();
+        let mut cycles = Vec::new();
+        for component in self.strongly_connected_components() {
+            let members = component.iter().cloned().collect::<HashSet<_>>();
+            for start in &component {
+                // Paths from `start` through neurons of the component with a
+                // higher id, so that every cycle is found once
+                let mut path = vec![*start];
+                let mut branches = vec![successors[start].iter()];
+                while let Some(branch) = branches.last_mut() {
+                    if cycles.len() >= limit {
+                        return cycles;
+                    }
+                    match branch.next() {
+                        Some(next) if next == start => cycles.push(path.clone()),
+                        Some(next)
+                            if next > start && members.contains(next) && !path.contains(next) =>
+                        {
+                            path.push(*next);
+                            branches.push(successors[next].iter());
+                        }
+                        Some(_) => {}
+                        None => {
+                            path.pop();
+                            branches.pop();
+                        }
+                    }
+                }
+            }
+        }
+        cycles
+    }
+
+    /// The depth of every neuron, ordered by id: the number of connections on
+    /// the longest path to it, from a neuron without incoming connections.
+    /// `None` if the network has cycles.
+    pub fn depths(&self) -> Option<IndexMap<NeuronId, usize>> {
+        let mut depths = self.zero_per_neuron();
+        for component in self.strongly_connected_components() {
+            let id = component[0];
+            if component.len() > 1 || self.connections.contains_key(&(id, id)) {
+                return None;
+            }
+            let depth = depths[&id];
+            for (_, target) in self.connections.keys().filter(|(source, _)| *source == id) {
+                let next = depths.get_mut(target).unwrap();
+                *next = (*next).max(depth + 1);
+            }
+        }
+        Some(depths)
+    }
+    /// The number of connections on the longest path, or `None` if the network
+    /// has cycles
+    pub fn depth(&self) -> Option<usize> {
+        self.depths()
+            .map(|depths| depths.values().cloned().max().unwrap_or(0))
+    }
+
+    /// Which inputs influence which outputs, when the network is activated
+    /// with `n_inputs` inputs and `n_outputs` outputs: `influence[i][o]` is
+    /// true if there is a path from input `i` to output `o`
+    pub fn influence(&self, n_inputs: usize, n_outputs: usize) -> Vec<Vec<bool>> {
+        let ids = self.sorted_neuron_ids();
+        let outputs = ids
+            .iter()
+            .skip(n_inputs)
+            .take(n_outputs)
+            .collect::<Vec<_>>();
+        ids.iter()
+            .take(n_inputs)
+            .map(|input| {
+                let reached = search(&[*input], &self.successors());
+                let mut row = outputs
+                    .iter()
+                    .map(|output| reached.contains(output))
+                    .collect::<Vec<_>>();
+                row.resize(n_outputs, false);
+                row
+            })
+            .collect()
+    }
+
+    fn zero_per_neuron(&self) -> IndexMap<NeuronId, usize> {
+        self.sorted_neuron_ids()
+            .into_iter()
+            .map(|id| (id, 0))
+            .collect()
+    }
+    fn successors(&self) -> HashMap<NeuronId, Vec<NeuronId>> {
+        let mut successors = self
+            .neurons
+            .keys()
+            .map(|id| (*id, Vec::new()))
+            .collect::<HashMap<_, _>>();
+        for (source, target) in self.connections.keys() {
+            successors.get_mut(source).unwrap().push(*target);
+        }
+        successors
+    }
+    fn predecessors(&self) -> HashMap<NeuronId, Vec<NeuronId>> {
+        let mut predecessors = self
+            .neurons
+            .keys()
+            .map(|id| (*id, Vec::new()))
+            .collect::<HashMap<_, _>>();
+        for (source, target) in self.connections.keys() {
+            predecessors.get_mut(target).unwrap().push(*source);
+        }
+        predecessors
+    }
+}
+
+/// The neurons reachable from `start` along `edges`, including `start`
+fn search(start: &[NeuronId], edges: &HashMap<NeuronId, Vec<NeuronId>>) -> HashSet<NeuronId> {
+    let mut reached = start.iter().cloned().collect::<HashSet<_>>();
+    let mut stack = start.to_vec();
+    while let Some(id) = stack.pop() {
+        for next in edges.get(&id).into_iter().flatten() {
+            if reached.insert(*next) {
+                stack.push(*next);
+            }
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Genome, NeatParams, NeuralNetwork};
+
+    /// Inputs 0 and 1, outputs 2 and 3, a cycle between 4 and 5 and one of 6
+    /// alone
+    fn network() -> NeuralNetwork {
+        let mut network = NeuralNetwork::with_neurons(7);
+        network.add_connection(0, 4, 1.0);
+        network.add_connection(4, 5, 1.0);
+        network.add_connection(5, 4, 1.0);
+        network.add_connection(5, 2, 1.0);
+        network.add_connection(0, 6, 1.0);
+        network.add_connection(6, 6, 1.0);
+        network.add_connection(6, 2, 1.0);
+        network.add_connection(1, 3, 1.0);
+        network
+    }
+
+    #[test]
+    fn cycles_should_be_found() {
+        let mut network = network();
+        assert!(!network.is_acyclic());
+        assert_eq!(
+            network.strongly_connected_components(),
+            vec![vec![1], vec![3], vec![0], vec![6], vec![4, 5], vec![2]]
+        );
+        assert_eq!(
+            network.recurrent_connections(),
+            vec![(4, 5), (5, 4), (6, 6)]
+        );
+        assert_eq!(network.cycles(10), vec![vec![6], vec![4, 5]]);
+        assert_eq!(network.cycles(1).len(), 1);
+        assert_eq!(network.depth(), None);
+
+        network.connections.remove(&(5, 4));
+        network.connections.remove(&(6, 6));
+        assert!(network.is_acyclic());
+        assert!(network.cycles(10).is_empty());
+        assert_eq!(network.depth(), Some(3));
+        assert_eq!(
+            network
+                .depths()
+                .unwrap()
+                .values()
+                .cloned()
+                .collect::<Vec<_>>(),
+            vec![0, 0, 3, 1, 1, 2, 1]
+        );
+    }
+
+    #[test]
+    fn long_chains_should_not_overflow_the_stack() {
+        let n = 100_000;
+        let mut network = NeuralNetwork::with_neurons(n);
+        for id in 1..n {
+            network.add_connection(id - 1, id, 1.0);
+        }
+        network.add_connection(n - 1, 1, 1.0);
+        let components = network.strongly_connected_components();
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0], vec![0]);
+        assert_eq!(components[1].len(), n - 1);
+    }
+
+    #[test]
+    fn every_elementary_cycle_should_be_found_once() {
+        let mut network = NeuralNetwork::with_neurons(3);
+        for source in 0..3 {
+            for target in 0..3 {
+                network.add_connection(source, target, 1.0);
+            }
+        }
+        // 3 connections to self, 3 cycles of 2 neurons and 2 of 3
+        let cycles = network.cycles(100);
+        assert_eq!(cycles.len(), 8);
+        assert!(cycles.contains(&vec![0, 2, 1]));
+        assert_eq!(network.recurrent_connections().len(), 9);
+    }
+
+    #[test]
+    fn acyclic_networks_should_have_a_feed_forward_evaluator() {
+        let p = NeatParams {
+            mutate_add_neuron_pr: 0.3,
+            ..NeatParams::default(2, 1)
+        };
+        for _ in 0..50 {
+            let mut network = NeuralNetwork::with_neurons(3);
+            let mut innovation_id = 3;
+            for _ in 0..10 {
+                network.mutate(&mut innovation_id, &p);
+            }
+            assert_eq!(network.is_acyclic(), network.make_feed_forward().is_some());
+        }
+    }
+
+    #[test]
+    fn reachability_should_follow_connections() {
+        let network = network();
+        assert_eq!(network.fan_in()[&2], 2);
+        assert_eq!(network.fan_out()[&0], 2);
+        assert_eq!(network.fan_in()[&0], 0);
+        let mut upstream = network.upstream(&[2]).into_iter().collect::<Vec<_>>();
+        upstream.sort();
+        assert_eq!(upstream, vec![0, 2, 4, 5, 6]);
+        assert!(network.downstream(&[1]).contains(&3));
+        assert_eq!(
+            network.influence(2, 2),
+            vec![vec![true, false], vec![false, true]]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use super::Activation;
 use rulinalg::matrix::{BaseMatrix, BaseMatrixMut, Matrix};
 
 /// Continuous Time Recurrent Neural Network implementation, which the
@@ -9,19 +10,27 @@ pub struct Ctrnn {
     delta_t_tau: Matrix<f64>,
     wij: Matrix<f64>, // weights
     steps: usize,
+    activations: Vec<Activation>,
 }
 
 impl Ctrnn {
-    /// Create a new CTRNN
+    /// Create a new CTRNN where all neurons use the sigmoid activation
     pub fn new(theta: Vec<f64>, tau: Vec<f64>, wij: Vec<f64>, delta_t: f64, steps: usize) -> Ctrnn {
         let tau = Ctrnn::vector_to_column_matrix(tau);
         Ctrnn {
+            activations: vec![Activation::Sigmoid; theta.len()],
             theta: Ctrnn::vector_to_column_matrix(theta),
             wij: Ctrnn::vector_to_matrix(wij),
             delta_t_tau: tau.apply(&(|x| 1.0 / x)) * delta_t,
             steps,
         }
     }
+    /// Use a different activation function for each neuron
+    pub fn with_activations(mut self, activations: Vec<Activation>) -> Ctrnn {
+        assert_eq!(activations.len(), self.theta.rows());
+        self.activations = activations;
+        self
+    }
     /// Activate the neural network. The output is written to `output`, the
     /// amount depending on the length of `output`.
     pub fn activate(&self, mut input: Vec<f64>, output: &mut [f64]) {
@@ -36,7 +45,7 @@ impl Ctrnn {
         let input = Ctrnn::vector_to_column_matrix(input);
         let mut y = input.clone(); // TODO: correct? Or zero-vector?
         for _ in 0..self.steps {
-            let activations = (&y + &self.theta).apply(&Ctrnn::sigmoid);
+            let activations = self.activation(&(&y + &self.theta));
             y = &y
                 + self
                     .delta_t_tau
@@ -53,17 +62,13 @@ impl Ctrnn {
         }
     }
 
-    fn sigmoid(y: f64) -> f64 {
-        // Inspired from neat-python
-        let y = y * 5.0;
-        let y = if y < -60.0 {
-            -60.0
-        } else if y > 60.0 {
-            60.0
-        } else {
-            y
-        };
-        1.0 / (1.0 + (-y).exp())
+    fn activation(&self, y: &Matrix<f64>) -> Matrix<f64> {
+        Ctrnn::vector_to_column_matrix(
+            y.iter()
+                .zip(&self.activations)
+                .map(|(y, activation)| activation.apply(*y))
+                .collect(),
+        )
     }
 
     fn vector_to_column_matrix(vector: Vec<f64>) -> Matrix<f64> {
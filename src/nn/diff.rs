@@ -0,0 +1,424 @@
+use super::{ConnectionGene, Gene, NeuralNetwork, NeuronGene, NeuronId};
+use crate::{Genome, NeatParams};
+use indexmap::map::IndexMap;
+use serde_derive::Serialize;
+use std::fmt;
+
+/// How a gene of one genome lines up with the genes of another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Alignment {
+    /// Both genomes have the gene
+    Matching,
+    /// Only one genome has the gene, which is older than the newest neuron of
+    /// the other genome
+    Disjoint,
+    /// Only one genome has the gene, which depends on a neuron newer than all
+    /// neurons of the other genome
+    Excess,
+}
+
+/// A gene of either genome, lined up with the other genome
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneDiff<G> {
+    /// How the gene lines up
+    pub alignment: Alignment,
+    /// The gene in the first genome, if it has it
+    pub first: Option<G>,
+    /// The gene in the second genome, if it has it
+    pub second: Option<G>,
+    /// For matching genes, the weight (or bias) in the second genome minus
+    /// the one in the first
+    pub delta: Option<f64>,
+    /// For matching genes, their distance (see `Gene::distance()`), 0
+    /// otherwise
+    pub distance: f64,
+}
+
+/// The connection or neuron genes of two genomes, lined up
+#[derive(Debug, Clone, Serialize)]
+pub struct GenesDiff<G> {
+    /// Number of genes in both genomes
+    pub matching: usize,
+    /// Number of disjoint genes, in either genome
+    pub disjoint: usize,
+    /// Number of excess genes, in either genome
+    pub excess: usize,
+    /// What these genes add to the distance between the genomes
+    pub distance: f64,
+    /// The genes of both genomes, ordered by id
+    pub genes: Vec<GeneDiff<G>>,
+}
+
+/// The genes of two genomes lined up by innovation id, to see where the
+/// distance used for speciation comes from, or what a child got from its
+/// parents. Printed, it lists the genes that differ; `to_json()` gives all
+/// of them.
+#[derive(Debug, Clone, Serialize)]
+pub struct GenomeDiff {
+    /// The distance between the genomes, from `Genome::distance()`. It is
+    /// the sum of the distances of the neurons and the connections.
+    pub distance: f64,
+    /// `NeatParams::compatibility_threshold`
+    pub compatibility_threshold: f64,
+    /// Whether the distance is below the threshold, so that the genomes can
+    /// be in the same species
+    pub same_species: bool,
+    /// The neuron genes
+    pub neurons: GenesDiff<NeuronGene>,
+    /// The connection genes
+    pub connections: GenesDiff<ConnectionGene>,
+}
+
+impl NeuralNetwork {
+    /// Line up the genes of this genome with those of `other`. Connections
+    /// have no innovation number of their own, so a connection is excess if
+    /// one of its neurons is newer than all neurons of the other genome.
+    /// `Genome::distance()` counts disjoint and excess genes alike.
+    pub fn diff(&self, other: &NeuralNetwork, p: &NeatParams) -> GenomeDiff {
+        let newest = |network: &NeuralNetwork| network.neurons.keys().cloned().max();
+        let newest = (newest(self), newest(other));
+        let distance = self.distance(other, p);
+        GenomeDiff {
+            distance,
+            compatibility_threshold: p.compatibility_threshold,
+            same_species: distance < p.compatibility_threshold,
+            neurons: align(&self.neurons, &other.neurons, newest, p),
+            connections: align(&self.connections, &other.connections, newest, p),
+        }
+    }
+}
+
+impl GenomeDiff {
+    /// The diff in JSON, with every gene of both genomes
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/// What `GenomeDiff` needs to know about genes besides `Gene`
+trait Aligned: Gene + serde::Serialize {
+    /// The newest neuron the gene depends on
+    fn newest_neuron(&self) -> NeuronId;
+    /// What `value()` is
+    const VALUE: &'static str;
+    /// The weight or bias
+    fn value(&self) -> f64;
+    /// How the gene is shown, e.g. "connection 0 -> 4"
+    fn label(&self) -> String;
+    /// How the gene is shown when only one genome has it
+    fn describe(&self) -> String;
+    /// The differences with the same gene in the other genome, besides the
+    /// weight or bias
+    fn changes(&self, other: &Self) -> Vec<String>;
+}
+
+impl Aligned for NeuronGene {
+    const VALUE: &'static str = "bias";
+    fn newest_neuron(&self) -> NeuronId {
+        self.innovation_id
+    }
+    fn value(&self) -> f64 {
+        self.bias
+    }
+    fn label(&self) -> String {
+        format!("neuron {}", self.innovation_id)
+    }
+    fn describe(&self) -> String {
+        format!(
+            "{} {:.4}, {:?}{}",
+            Self::VALUE,
+            self.bias,
+            self.activation,
+            if self.modulatory { ", modulatory" } else { "" }
+        )
+    }
+    fn changes(&self, other: &Self) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.activation != other.activation {
+            changes.push(format!("{:?} -> {:?}", self.activation, other.activation));
+        }
+        if self.modulatory != other.modulatory {
+            changes.push(if other.modulatory {
+                "now modulatory".to_string()
+            } else {
+                "no longer modulatory".to_string()
+            });
+        }
+        changes
+    }
+}
+
+impl Aligned for ConnectionGene {
+    const VALUE: &'static str = "weight";
+    fn newest_neuron(&self) -> NeuronId {
+        self.in_neuron_id().max(self.out_neuron_id())
+    }
+    fn value(&self) -> f64 {
+        self.weight
+    }
+    fn label(&self) -> String {
+        format!(
+            "connection {} -> {}",
+            self.in_neuron_id(),
+            self.out_neuron_id()
+        )
+    }
+    fn describe(&self) -> String {
+        format!(
+            "{} {:.4}{}",
+            Self::VALUE,
+            self.weight,
+            if self.plasticity.is_some() {
+                ", plastic"
+            } else {
+                ""
+            }
+        )
+    }
+    fn changes(&self, other: &Self) -> Vec<String> {
+        match (self.plasticity, other.plasticity) {
+            (None, Some(_)) => vec!["now plastic".to_string()],
+            (Some(_), None) => vec!["no longer plastic".to_string()],
+            (Some(a), Some(b)) if a != b => vec!["plasticity changed".to_string()],
+            _ => vec![],
+        }
+    }
+}
+
+/// Line up the genes of two genomes, whose newest neurons are `newest`
+fn align<G: Aligned>(
+    first: &IndexMap<G::Id, G>,
+    second: &IndexMap<G::Id, G>,
+    newest: (Option<NeuronId>, Option<NeuronId>),
+    p: &NeatParams,
+) -> GenesDiff<G>
+where
+    G::Id: Ord,
+{
+    let mut ids = first
+        .keys()
+        .chain(second.keys())
+        .cloned()
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+
+    let is_excess = |gene: &G, other_newest: Option<NeuronId>| {
+        other_newest.is_none_or(|newest| gene.newest_neuron() > newest)
+    };
+    let genes = ids
+        .into_iter()
+        .map(|id| {
+            let (first, second) = (first.get(&id).cloned(), second.get(&id).cloned());
+            let (alignment, delta, distance) = match (&first, &second) {
+                (Some(a), Some(b)) => (
+                    Alignment::Matching,
+                    Some(b.value() - a.value()),
+                    a.distance(b),
+                ),
+                (Some(a), None) if is_excess(a, newest.1) => (Alignment::Excess, None, 0.0),
+                (None, Some(b)) if is_excess(b, newest.0) => (Alignment::Excess, None, 0.0),
+                _ => (Alignment::Disjoint, None, 0.0),
+            };
+            GeneDiff {
+                alignment,
+                first,
+                second,
+                delta,
+                distance,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let count = |alignment| {
+        genes
+            .iter()
+            .filter(|gene| gene.alignment == alignment)
+            .count()
+    };
+    let (matching, disjoint, excess) = (
+        count(Alignment::Matching),
+        count(Alignment::Disjoint),
+        count(Alignment::Excess),
+    );
+    let distance = super::compatibility(
+        disjoint + excess,
+        genes.iter().map(|gene| gene.distance).sum(),
+        first.len().max(second.len()),
+        p,
+    );
+    GenesDiff {
+        matching,
+        disjoint,
+        excess,
+        distance,
+        genes,
+    }
+}
+
+fn fmt_summary<G>(genes: &GenesDiff<G>, f: &mut fmt::Formatter, name: &str) -> fmt::Result {
+    writeln!(
+        f,
+        "{:<13} {} matching, {} disjoint, {} excess, distance {:.4}",
+        format!("{}:", name),
+        genes.matching,
+        genes.disjoint,
+        genes.excess,
+        genes.distance
+    )
+}
+
+/// One line per gene that is not the same in both genomes
+fn fmt_genes<G: Aligned>(genes: &GenesDiff<G>, f: &mut fmt::Formatter) -> fmt::Result {
+    for gene in &genes.genes {
+        match (&gene.first, &gene.second) {
+            (Some(a), Some(b)) => {
+                let mut changes = a.changes(b);
+                if a.value() != b.value() {
+                    changes.insert(
+                        0,
+                        format!(
+                            "{} {:.4} -> {:.4} ({:+.4})",
+                            G::VALUE,
+                            a.value(),
+                            b.value(),
+                            b.value() - a.value()
+                        ),
+                    );
+                }
+                if !changes.is_empty() {
+                    writeln!(f, "  {:<22} {}", a.label(), changes.join(", "))?;
+                }
+            }
+            (Some(only), None) | (None, Some(only)) => {
+                let alignment = match gene.alignment {
+                    Alignment::Excess => "excess",
+                    _ => "disjoint",
+                };
+                let genome = if gene.first.is_some() {
+                    "first"
+                } else {
+                    "second"
+                };
+                writeln!(
+                    f,
+                    "  {:<22} {}, only in {}: {}",
+                    only.label(),
+                    alignment,
+                    genome,
+                    only.describe()
+                )?;
+            }
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for GenomeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "distance {:.4}, {} species (threshold {:.4})",
+            self.distance,
+            if self.same_species {
+                "same"
+            } else {
+                "different"
+            },
+            self.compatibility_threshold
+        )?;
+        fmt_summary(&self.neurons, f, "neurons")?;
+        fmt_summary(&self.connections, f, "connections")?;
+        fmt_genes(&self.neurons, f)?;
+        fmt_genes(&self.connections, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Alignment;
+    use crate::nn::{Activation, NeuralNetwork, NeuronGene};
+    use crate::{Genome, NeatParams};
+
+    #[test]
+    fn genes_should_be_lined_up_by_innovation_id() {
+        let p = NeatParams::default(2, 1);
+        let mut first = NeuralNetwork::with_neurons(4);
+        first.add_connection(0, 2, 1.0);
+        first.add_connection(1, 3, 0.5);
+        first.add_connection(3, 2, 0.5);
+        let mut second = NeuralNetwork::with_neurons(3);
+        second.add_connection(0, 2, 0.25);
+        second.add_connection(1, 2, 1.0);
+        let mut gene = NeuronGene::new(0.0, 5);
+        gene.activation = Activation::Relu;
+        second.neurons.insert(5, gene);
+        second.add_connection(5, 2, 1.0);
+
+        let diff = first.diff(&second, &p);
+        assert_eq!(diff.neurons.matching, 3);
+        assert_eq!(diff.neurons.disjoint, 1);
+        assert_eq!(diff.neurons.excess, 1);
+        assert_eq!(
+            diff.connections
+                .genes
+                .iter()
+                .map(|gene| gene.alignment)
+                .collect::<Vec<_>>(),
+            vec![
+                Alignment::Matching,
+                Alignment::Disjoint,
+                Alignment::Disjoint,
+                Alignment::Disjoint,
+                Alignment::Excess
+            ]
+        );
+        assert_eq!(diff.connections.genes[0].delta, Some(-0.75));
+        assert!((diff.distance - first.distance(&second, &p)).abs() < 1e-12);
+        assert!((diff.distance - diff.neurons.distance - diff.connections.distance).abs() < 1e-12);
+        assert_eq!(diff.same_species, first.is_same_specie(&second, &p));
+
+        let text = diff.to_string();
+        assert!(text.contains("connection 0 -> 2"));
+        assert!(text.contains("weight 1.0000 -> 0.2500 (-0.7500)"));
+        assert!(text.contains("excess, only in second"));
+        assert!(!text.contains("neuron 0 "));
+
+        let json = serde_json::from_str::<serde_json::Value>(&diff.to_json()).unwrap();
+        assert_eq!(json["connections"]["genes"].as_array().unwrap().len(), 5);
+        assert_eq!(json["connections"]["genes"][4]["alignment"], "excess");
+    }
+
+    #[test]
+    fn diff_should_match_the_distance_of_random_genomes() {
+        let p = NeatParams {
+            mutate_add_neuron_pr: 0.3,
+            mutate_del_conn_pr: 0.1,
+            activation_mutate_pr: 0.1,
+            activation_options: vec![Activation::Sigmoid, Activation::Tanh],
+            plastic_connection_pr: 0.1,
+            ..NeatParams::default(2, 1)
+        };
+        for _ in 0..20 {
+            let mut first = NeuralNetwork::with_neurons(3);
+            let mut innovation_id = 3;
+            for _ in 0..10 {
+                first.mutate(&mut innovation_id, &p);
+            }
+            let mut second = first.clone();
+            for _ in 0..5 {
+                second.mutate(&mut innovation_id, &p);
+            }
+            let diff = first.diff(&second, &p);
+            assert!((diff.distance - first.distance(&second, &p)).abs() < 1e-9);
+            assert!(
+                (diff.distance - diff.neurons.distance - diff.connections.distance).abs() < 1e-9
+            );
+            let reverse = second.diff(&first, &p);
+            assert_eq!(diff.connections.excess, reverse.connections.excess);
+            assert_eq!(diff.neurons.disjoint, reverse.neurons.disjoint);
+        }
+    }
+}
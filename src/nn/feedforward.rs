@@ -0,0 +1,94 @@
+use super::Activation;
+
+/// Feed-forward runner for acyclic networks. Each neuron is evaluated once, in
+/// topological order, which gives the same result as running a `Ctrnn` of the
+/// same network until it settles: the state of neuron `i` is
+/// `input_i + sum_j(w_ji * activation_j(state_j + bias_j))`.
+///
+/// Like `Ctrnn`, the first neurons receive the inputs, and the outputs are
+/// read from the neurons directly following the inputs.
+#[derive(Debug, Clone)]
+pub struct FeedForward {
+    pub(crate) bias: Vec<f64>,
+    pub(crate) activations: Vec<Activation>,
+    /// Incoming connections `(source, weight)` of every neuron
+    pub(crate) incoming: Vec<Vec<(usize, f64)>>,
+    /// Topological order of the neurons
+    pub(crate) order: Vec<usize>,
+}
+
+impl FeedForward {
+    /// Create a new feed-forward network from the bias and activation of each
+    /// neuron and the connections `(source, target, weight)` between them
+    /// (given as indices). Returns `None` if the connections form a cycle.
+    pub fn new(
+        bias: Vec<f64>,
+        activations: Vec<Activation>,
+        connections: &[(usize, usize, f64)],
+    ) -> Option<FeedForward> {
+        assert_eq!(bias.len(), activations.len());
+        let n = bias.len();
+        let mut incoming = vec![Vec::new(); n];
+        let mut n_unresolved = vec![0; n];
+        let mut outgoing = vec![Vec::new(); n];
+        for &(source, target, weight) in connections {
+            incoming[target].push((source, weight));
+            outgoing[source].push(target);
+            n_unresolved[target] += 1;
+        }
+
+        // Kahn's algorithm
+        let mut order = Vec::with_capacity(n);
+        let mut ready = (0..n)
+            .rev()
+            .filter(|i| n_unresolved[*i] == 0)
+            .collect::<Vec<_>>();
+        while let Some(neuron) = ready.pop() {
+            order.push(neuron);
+            for target in &outgoing[neuron] {
+                n_unresolved[*target] -= 1;
+                if n_unresolved[*target] == 0 {
+                    ready.push(*target);
+                }
+            }
+        }
+        if order.len() < n {
+            return None;
+        }
+
+        Some(FeedForward {
+            bias,
+            activations,
+            incoming,
+            order,
+        })
+    }
+
+    /// Activate the neural network. The output is written to `output`, the
+    /// amount depending on the length of `output`.
+    pub fn activate(&self, input: Vec<f64>, output: &mut [f64]) {
+        let state = self.evaluate(&input);
+        let n_inputs = input.len();
+        for (o, y) in output.iter_mut().zip(state.iter().skip(n_inputs)) {
+            *o = *y;
+        }
+    }
+
+    /// The state of every neuron after evaluating the network on `input`.
+    pub fn evaluate(&self, input: &[f64]) -> Vec<f64> {
+        let mut state = vec![0.0; self.bias.len()];
+        for &neuron in &self.order {
+            let mut y = input.get(neuron).cloned().unwrap_or(0.0);
+            for &(source, weight) in &self.incoming[neuron] {
+                y += weight * self.activations[source].apply(state[source] + self.bias[source]);
+            }
+            state[neuron] = y;
+        }
+        state
+    }
+
+    /// Number of neurons
+    pub fn n_neurons(&self) -> usize {
+        self.bias.len()
+    }
+}
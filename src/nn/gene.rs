@@ -1,3 +1,4 @@
+use super::{Activation, HebbianRule};
 use serde_derive::{Deserialize, Serialize};
 use std::hash::Hash;
 
@@ -24,14 +25,24 @@ pub struct NeuronGene {
     pub bias: f64,
     /// Innovation number of the neuron.
     pub innovation_id: usize,
+    /// Activation function of the neuron. Always `Sigmoid` in plain NEAT;
+    /// CPPNs (see `hyperneat`) mix several.
+    #[serde(default)]
+    pub activation: Activation,
+    /// Modulatory neurons only modulate the plasticity of the connections
+    /// into the neurons they connect to (see `StatefulCtrnn`)
+    #[serde(default)]
+    pub modulatory: bool,
 }
 
 impl NeuronGene {
-    ///
+    /// Create a neuron with the sigmoid activation
     pub fn new(bias: f64, innovation_id: usize) -> NeuronGene {
         NeuronGene {
             bias,
             innovation_id,
+            activation: Activation::Sigmoid,
+            modulatory: false,
         }
     }
 }
@@ -41,7 +52,17 @@ impl Gene for NeuronGene {
         self.innovation_id
     }
     fn distance(&self, other: &Self) -> f64 {
-        (self.bias - other.bias).abs()
+        let activation = if self.activation == other.activation {
+            0.0
+        } else {
+            1.0
+        };
+        let modulatory = if self.modulatory == other.modulatory {
+            0.0
+        } else {
+            1.0
+        };
+        (self.bias - other.bias).abs() + activation + modulatory
     }
 }
 
@@ -55,6 +76,9 @@ pub struct ConnectionGene {
     out_neuron_id: usize,
     /// Weight of the connection
     pub weight: f64,
+    /// Rule to change the weight during activation, for plastic connections
+    #[serde(default)]
+    pub plasticity: Option<HebbianRule>,
 }
 
 impl ConnectionGene {
@@ -64,6 +88,7 @@ impl ConnectionGene {
             in_neuron_id: in_neuron_id,
             out_neuron_id: out_neuron_id,
             weight: weight,
+            plasticity: None,
         }
     }
     /// The neuron that acts as the input of this connection
@@ -81,7 +106,12 @@ impl Gene for ConnectionGene {
         (self.in_neuron_id, self.out_neuron_id)
     }
     fn distance(&self, other: &Self) -> f64 {
-        (self.weight - other.weight).abs()
+        let plasticity = match (self.plasticity, other.plasticity) {
+            (Some(a), Some(b)) => a.distance(&b),
+            (None, None) => 0.0,
+            _ => 1.0,
+        };
+        (self.weight - other.weight).abs() + plasticity
     }
 }
 
@@ -92,6 +122,7 @@ impl Default for ConnectionGene {
             in_neuron_id: 1,
             out_neuron_id: 1,
             weight: 0.0,
+            plasticity: None,
         }
     }
 }
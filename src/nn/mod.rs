@@ -1,12 +1,32 @@
-use crate::{Genome, NeatParams};
+use crate::{Genome, Mutation, MutationRates, NeatParams};
 use indexmap::map::IndexMap;
 use serde_derive::{Deserialize, Serialize};
 use std::cmp;
 
+mod activation;
+mod analysis;
 mod ctrnn;
+mod diff;
+mod feedforward;
 mod gene;
+mod plastic;
+mod render;
+mod simplify;
+pub use self::activation::*;
 pub use self::ctrnn::*;
+pub use self::diff::*;
+pub use self::feedforward::*;
 pub use self::gene::*;
+pub use self::plastic::*;
+pub use self::render::*;
+
+/// Time constant of every neuron in the `Ctrnn` made by `make_network()`
+pub const TAU: f64 = 1.0;
+/// Time step of the `Ctrnn` made by `make_network()`
+pub const DELTA_T: f64 = 1.0;
+/// Number of integration steps per activation of the `Ctrnn` made by
+/// `make_network()`
+pub const STEPS: usize = 10;
 
 /// Genome representing a neural network.
 /// There is one gene for every connection and one gene for every neuron.
@@ -15,10 +35,75 @@ pub struct NeuralNetwork {
     /// Connections between neurons. Sorted at all times. Use `add_connection()`
     /// to add a connection!
     // TODO :should it be private with a getter?
+    #[serde(with = "connection_list")]
     pub connections: IndexMap<ConnectionId, ConnectionGene>,
     /// Neurons with bias. Can simple be pushed to.
     pub neurons: IndexMap<NeuronId, NeuronGene>,
+    /// Mutation rates of this genome, if they evolve
+    /// (`NeatParams::rate_adaptation` above 0). Set by its first mutation,
+    /// and inherited from the fittest parent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rates: Option<MutationRates>,
 }
+/// (De)serializes the connections as a list of genes in human-readable
+/// formats, since formats like JSON only allow strings as keys. Other formats,
+/// like bincode, keep the map of earlier versions, so that their data still
+/// loads; human-readable formats accept both.
+mod connection_list {
+    use super::{ConnectionGene, ConnectionId, Gene};
+    use indexmap::map::IndexMap;
+    use serde::de::{MapAccess, SeqAccess, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(
+        connections: &IndexMap<ConnectionId, ConnectionGene>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_seq(connections.values())
+        } else {
+            connections.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<IndexMap<ConnectionId, ConnectionGene>, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(Connections)
+        } else {
+            IndexMap::deserialize(deserializer)
+        }
+    }
+
+    struct Connections;
+
+    impl<'de> Visitor<'de> for Connections {
+        type Value = IndexMap<ConnectionId, ConnectionGene>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a list of connection genes, or a map of them by id")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut connections = IndexMap::new();
+            while let Some(gene) = seq.next_element::<ConnectionGene>()? {
+                connections.insert(gene.id(), gene);
+            }
+            Ok(connections)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut connections = IndexMap::new();
+            while let Some((id, gene)) = map.next_entry::<ConnectionId, ConnectionGene>()? {
+                connections.insert(id, gene);
+            }
+            Ok(connections)
+        }
+    }
+}
+
 impl Default for NeuralNetwork {
     fn default() -> NeuralNetwork {
         let mut neurons = IndexMap::new();
@@ -26,6 +111,7 @@ impl Default for NeuralNetwork {
         NeuralNetwork {
             connections: IndexMap::new(),
             neurons,
+            rates: None,
         }
     }
 }
@@ -45,7 +131,7 @@ fn distance<T: Gene>(
         .collect::<Vec<(T, T)>>();
 
     // Disjoint / excess genes
-    let disjoint_genes = (genome1.len() + genome2.len() - (2 * common_genes.len())) as f64;
+    let disjoint_genes = genome1.len() + genome2.len() - (2 * common_genes.len());
 
     // Get the distance between common genes and neurons
     let genes_distance = common_genes
@@ -53,13 +139,25 @@ fn distance<T: Gene>(
         .map(|(gene1, gene2)| gene1.distance(gene2))
         .sum::<f64>();
 
-    let max_genes = std::cmp::max(genome1.len(), genome2.len()) as f64;
+    let max_genes = std::cmp::max(genome1.len(), genome2.len());
+    compatibility(disjoint_genes, genes_distance, max_genes, p)
+}
 
-    if max_genes == 0.0 {
+/// Distance between two sets of genes, the larger of which has `max_genes`
+/// genes, from the number of disjoint and excess genes and the sum of the
+/// distances of the matching ones
+fn compatibility(
+    disjoint_genes: usize,
+    genes_distance: f64,
+    max_genes: usize,
+    p: &NeatParams,
+) -> f64 {
+    if max_genes == 0 {
         0.0
     } else {
-        (disjoint_genes * p.distance_disjoint_coef + genes_distance * p.distance_weight_coef)
-            / max_genes
+        (disjoint_genes as f64 * p.distance_disjoint_coef
+            + genes_distance * p.distance_weight_coef)
+            / max_genes as f64
     }
 }
 
@@ -69,45 +167,138 @@ impl Genome for NeuralNetwork {
         distance(&self.connections, &other.connections, p)
             + distance(&self.neurons, &other.neurons, p)
     }
+    fn complexity(&self) -> usize {
+        self.n_neurons() + self.n_connections()
+    }
     /// May add a connection &| neuron &| mutat connection weight &|
     /// enable/disable connection
     fn mutate(&mut self, innovation_id: &mut usize, p: &NeatParams) {
+        self.mutate_tracked(innovation_id, p);
+    }
+
+    fn mutate_tracked(&mut self, innovation_id: &mut usize, p: &NeatParams) -> Vec<Mutation> {
         use rand::distributions::{Distribution, Normal};
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::rng::thread_rng();
+        let mut mutations = Vec::new();
+
+        // The rates of the genome, if they evolve, are mutated first
+        let adapted;
+        let p = if p.rate_adaptation > 0.0 {
+            let rates = self.rates.get_or_insert_with(|| MutationRates::new(p));
+            rates.perturb(p.rate_adaptation);
+            adapted = rates.apply(p);
+            &adapted
+        } else {
+            p
+        };
 
         // Topological mutations
-        if rand::random::<f64>() < p.mutate_add_conn_pr || self.connections.is_empty() {
-            self.mutate_add_connection(p);
+        if crate::rng::random::<f64>() < p.mutate_add_conn_pr || self.connections.is_empty() {
+            if let Some((in_neuron_id, out_neuron_id)) = self.mutate_add_connection(p) {
+                mutations.push(Mutation::AddConnection {
+                    in_neuron_id,
+                    out_neuron_id,
+                });
+            }
         }
-        if rand::random::<f64>() < p.mutate_add_neuron_pr {
-            self.mutate_add_neuron(*innovation_id);
+        if crate::rng::random::<f64>() < p.mutate_add_neuron_pr {
+            let neuron_id = self.mutate_add_neuron(*innovation_id, p);
+            mutations.push(Mutation::AddNeuron { neuron_id });
             *innovation_id += 1;
         }
-        if rand::random::<f64>() < p.mutate_del_neuron_pr {
-            self.mutate_del_neuron(p);
+        if crate::rng::random::<f64>() < p.mutate_del_neuron_pr {
+            if let Some(neuron_id) = self.mutate_del_neuron(p) {
+                mutations.push(Mutation::DeleteNeuron { neuron_id });
+            }
         }
-        if rand::random::<f64>() < p.mutate_del_conn_pr {
-            self.mutate_del_conn();
+        if crate::rng::random::<f64>() < p.mutate_del_conn_pr {
+            if let Some((in_neuron_id, out_neuron_id)) = self.mutate_del_conn() {
+                mutations.push(Mutation::DeleteConnection {
+                    in_neuron_id,
+                    out_neuron_id,
+                });
+            }
         }
 
         // For each connection and neuron, there is some probability to mutate it
 
         let bias_distr = Normal::new(0.0, p.bias_mutate_var);
         let weight_distr = Normal::new(0.0, p.weight_mutate_var);
+        let mut n_biases = 0;
         for gene in self.neurons.values_mut() {
-            if rand::random::<f64>() < p.bias_mutate_pr {
+            if crate::rng::random::<f64>() < p.bias_mutate_pr {
                 gene.bias += bias_distr.sample(&mut rng);
-            } else if rand::random::<f64>() < p.bias_replace_pr {
+                n_biases += 1;
+            } else if crate::rng::random::<f64>() < p.bias_replace_pr {
                 gene.bias = bias_distr.sample(&mut rng);
+                n_biases += 1;
             }
         }
+        let mut n_activations = 0;
+        if p.activation_mutate_pr > 0.0 {
+            // The inputs pass the observations on with the activation they
+            // were given
+            let inputs = self
+                .sorted_neuron_ids()
+                .into_iter()
+                .take(p.n_inputs)
+                .collect::<Vec<_>>();
+            for (id, gene) in self.neurons.iter_mut() {
+                if inputs.contains(id) {
+                    continue;
+                }
+                if crate::rng::random::<f64>() < p.activation_mutate_pr {
+                    gene.activation = random_activation(p);
+                    n_activations += 1;
+                }
+            }
+        }
+        let mut n_plastic = 0;
+        if crate::rng::random::<f64>() < p.plastic_connection_pr && !self.connections.is_empty() {
+            let plasticity_distr = Normal::new(0.0, p.plasticity_mutate_var);
+            let id = get_random_key(&self.connections);
+            let gene = self.connections.get_mut(&id).unwrap();
+            if gene.plasticity.is_none() {
+                gene.plasticity = Some(HebbianRule::random(&plasticity_distr, &mut rng));
+                n_plastic += 1;
+            }
+        }
+        if p.plasticity_mutate_pr > 0.0 {
+            let plasticity_distr = Normal::new(0.0, p.plasticity_mutate_var);
+            for gene in self.connections.values_mut() {
+                if let Some(rule) = &mut gene.plasticity {
+                    if crate::rng::random::<f64>() < p.plasticity_mutate_pr {
+                        rule.perturb(&plasticity_distr, &mut rng);
+                        n_plastic += 1;
+                    }
+                }
+            }
+        }
+        let mut n_weights = 0;
         for gene in self.connections.values_mut() {
-            if rand::random::<f64>() < p.weight_mutate_pr {
+            if crate::rng::random::<f64>() < p.weight_mutate_pr {
                 gene.weight += weight_distr.sample(&mut rng);
-            } else if rand::random::<f64>() < p.weight_replace_pr {
+                n_weights += 1;
+            } else if crate::rng::random::<f64>() < p.weight_replace_pr {
                 gene.weight = weight_distr.sample(&mut rng);
+                n_weights += 1;
             }
         }
+        if n_biases > 0 {
+            mutations.push(Mutation::Biases { count: n_biases });
+        }
+        if n_weights > 0 {
+            mutations.push(Mutation::Weights { count: n_weights });
+        }
+        if n_activations > 0 {
+            mutations.push(Mutation::Activations {
+                count: n_activations,
+            });
+        }
+        if n_plastic > 0 {
+            mutations.push(Mutation::Plasticity { count: n_plastic });
+        }
+        mutations
     }
 
     /// Mate two genes. `fittest` is true if `self` is the fittest one
@@ -120,6 +311,7 @@ impl Genome for NeuralNetwork {
         let mut genome = NeuralNetwork::default();
         genome.neurons = NeuralNetwork::reproduce(&best.neurons, &worst.neurons);
         genome.connections = NeuralNetwork::reproduce(&best.connections, &worst.connections);
+        genome.rates = best.rates.clone();
         genome
     }
 }
@@ -127,14 +319,59 @@ impl Genome for NeuralNetwork {
 impl NeuralNetwork {
     /// Create an activatable neural network from this genome.
     pub fn make_network(&self) -> Ctrnn {
-        let mut neurons = self.neurons.clone();
-        neurons.sort_keys();
-        let theta = neurons.values().map(|x| x.bias).collect();
-        let tau = vec![1.0; self.n_neurons()];
+        let theta = self.get_bias();
+        let tau = vec![TAU; self.n_neurons()];
         let wij = self.get_weights();
-        let delta_t = 1.0;
 
-        Ctrnn::new(theta, tau, wij, delta_t, 10)
+        Ctrnn::new(theta, tau, wij, DELTA_T, STEPS).with_activations(self.get_activations())
+    }
+    /// Create a network that keeps its state between activations and applies
+    /// the plasticity rules of the connections and the modulatory neurons,
+    /// which `make_network()` ignores. Without plasticity, each episode
+    /// behaves like `make_network()` activated with the state carried over.
+    pub fn make_stateful(&self) -> StatefulCtrnn {
+        let ids = self.sorted_neuron_ids();
+        let index = |id: &NeuronId| ids.binary_search(id).unwrap();
+        let connections = self
+            .connections
+            .values()
+            .map(|gene| {
+                (
+                    index(&gene.in_neuron_id()),
+                    index(&gene.out_neuron_id()),
+                    gene.weight,
+                    gene.plasticity,
+                )
+            })
+            .collect::<Vec<_>>();
+        StatefulCtrnn::new(
+            self.get_bias(),
+            vec![TAU; ids.len()],
+            self.get_activations(),
+            ids.iter().map(|id| self.neurons[id].modulatory).collect(),
+            &connections,
+            DELTA_T,
+            STEPS,
+        )
+    }
+    /// Create a feed-forward network from this genome, which is equivalent to
+    /// the settled state of `make_network()`. Returns `None` if the network
+    /// has recurrent connections.
+    pub fn make_feed_forward(&self) -> Option<FeedForward> {
+        let ids = self.sorted_neuron_ids();
+        let index = |id: &NeuronId| ids.binary_search(id).unwrap();
+        let connections = self
+            .connections
+            .values()
+            .map(|gene| {
+                (
+                    index(&gene.in_neuron_id()),
+                    index(&gene.out_neuron_id()),
+                    gene.weight,
+                )
+            })
+            .collect::<Vec<_>>();
+        FeedForward::new(self.get_bias(), self.get_activations(), &connections)
     }
     /// Creates a network that with no connections, but enough neurons to cover
     /// all inputs and outputs.
@@ -146,24 +383,60 @@ impl NeuralNetwork {
         NeuralNetwork {
             neurons,
             connections: IndexMap::new(),
+            rates: None,
         }
     }
 
     /// Helper function for `activate()`. Get weights of connections (as a
-    /// matrix represented linearly)
+    /// matrix represented linearly). Neurons are ordered by id.
     pub fn get_weights(&self) -> Vec<f64> {
-        let n_neurons = self.neurons.len();
+        let ids = self.sorted_neuron_ids();
+        let n_neurons = ids.len();
         let mut matrix = vec![0.0; n_neurons * n_neurons];
         for gene in self.connections.values() {
-            let (out_neuron_idx, _, _) = self.neurons.get_full(&gene.out_neuron_id()).unwrap();
-            let (in_neuron_idx, _, _) = self.neurons.get_full(&gene.in_neuron_id()).unwrap();
+            let out_neuron_idx = ids.binary_search(&gene.out_neuron_id()).unwrap();
+            let in_neuron_idx = ids.binary_search(&gene.in_neuron_id()).unwrap();
             matrix[(out_neuron_idx * n_neurons) + in_neuron_idx] = gene.weight;
         }
         matrix
     }
-    /// Helper function for `activate()`. Get bias of neurons.
+    /// Helper function for `activate()`. Get bias of neurons, ordered by id.
     pub fn get_bias(&self) -> Vec<f64> {
-        self.neurons.values().map(|x| x.bias).collect()
+        self.sorted_neuron_ids()
+            .iter()
+            .map(|id| self.neurons[id].bias)
+            .collect()
+    }
+
+    /// Get activation functions of neurons, ordered by id.
+    pub fn get_activations(&self) -> Vec<Activation> {
+        self.sorted_neuron_ids()
+            .iter()
+            .map(|id| self.neurons[id].activation)
+            .collect()
+    }
+
+    /// The weights of all connections followed by the biases of all neurons,
+    /// in the order of `connections` and `neurons`. Used by optimizers that
+    /// tune the network without changing its structure.
+    pub fn parameters(&self) -> Vec<f64> {
+        self.connections
+            .values()
+            .map(|gene| gene.weight)
+            .chain(self.neurons.values().map(|gene| gene.bias))
+            .collect()
+    }
+    /// Set the weights and biases from a vector laid out like `parameters()`.
+    /// Panics if it has the wrong length.
+    pub fn set_parameters(&mut self, parameters: &[f64]) {
+        assert_eq!(parameters.len(), self.n_connections() + self.n_neurons());
+        let (weights, biases) = parameters.split_at(self.n_connections());
+        for (gene, weight) in self.connections.values_mut().zip(weights) {
+            gene.weight = *weight;
+        }
+        for (gene, bias) in self.neurons.values_mut().zip(biases) {
+            gene.bias = *bias;
+        }
     }
 
     /// Get number of neurons
@@ -175,35 +448,47 @@ impl NeuralNetwork {
         self.connections.len()
     }
 
-    fn mutate_add_connection(&mut self, p: &NeatParams) {
+    fn mutate_add_connection(&mut self, p: &NeatParams) -> Option<ConnectionId> {
         if self.neurons.len() == 0 {
-            return;
+            return None;
         }
         // TODO: function to pick multiple random unique values from a range?
         let in_neuron_id = get_random_key(&self.neurons);
         let out_neuron_id = get_random_key(&self.neurons);
+        if p.feed_forward && self.downstream(&[out_neuron_id]).contains(&in_neuron_id) {
+            return None;
+        }
 
         self.add_connection(in_neuron_id, out_neuron_id, 0.0);
+        Some((in_neuron_id, out_neuron_id))
     }
 
-    fn mutate_del_conn(&mut self) {
+    fn mutate_del_conn(&mut self) -> Option<ConnectionId> {
         if self.connections.len() > 0 {
             let selected_gene = get_random_key(&self.connections);
             self.connections.remove(&selected_gene);
+            Some(selected_gene)
+        } else {
+            None
         }
     }
 
-    fn mutate_add_neuron(&mut self, innovation_id: usize) {
+    fn mutate_add_neuron(&mut self, innovation_id: usize, p: &NeatParams) -> NeuronId {
         if self.connections.len() == 0 {
-            let gene = NeuronGene::new(0.0, innovation_id);
+            let mut gene = NeuronGene::new(0.0, innovation_id);
+            gene.activation = random_activation(p);
+            gene.modulatory = crate::rng::random::<f64>() < p.modulatory_neuron_pr;
             self.neurons.insert(gene.id(), gene);
+            gene.id()
         } else {
             // Select a random connections along which to add neuron.. and remove it
             let old_connection_id = get_random_key(&mut self.connections);
             let old_connection = *self.connections.get_mut(&old_connection_id).unwrap();
             self.connections.remove(&old_connection_id);
             // Create new neuron
-            let new_neuron = NeuronGene::new(0.0, innovation_id);
+            let mut new_neuron = NeuronGene::new(0.0, innovation_id);
+            new_neuron.activation = random_activation(p);
+            new_neuron.modulatory = crate::rng::random::<f64>() < p.modulatory_neuron_pr;
             self.neurons.insert(new_neuron.id(), new_neuron);
             // ... and make two new connections that go through the new neuron
             self.add_connection(old_connection.in_neuron_id(), new_neuron.id(), 1.0);
@@ -212,16 +497,17 @@ impl NeuralNetwork {
                 old_connection.out_neuron_id(),
                 old_connection.weight,
             );
+            new_neuron.id()
         }
     }
-    fn mutate_del_neuron(&mut self, p: &NeatParams) {
+    fn mutate_del_neuron(&mut self, p: &NeatParams) -> Option<NeuronId> {
         let sacred_neurons = p.n_inputs + p.n_outputs;
         if self.neurons.len() <= sacred_neurons {
-            return;
+            return None;
         }
 
-        let idx =
-            (rand::random::<usize>() % (self.neurons.len() - sacred_neurons)) + sacred_neurons;
+        let idx = (crate::rng::random::<usize>() % (self.neurons.len() - sacred_neurons))
+            + sacred_neurons;
         let id = *self.neurons.get_index(idx).unwrap().0;
         // Delete it
         self.neurons.remove(&id);
@@ -235,6 +521,7 @@ impl NeuralNetwork {
         for conn in &to_remove {
             self.connections.remove(conn);
         }
+        Some(id)
     }
 
     fn reproduce<T: Gene + Copy>(
@@ -248,7 +535,7 @@ impl NeuralNetwork {
             genes.insert(
                 *id,
                 if let Some(worst) = worst.get(id) {
-                    if rand::random::<f64>() < 0.5 {
+                    if crate::rng::random::<f64>() < 0.5 {
                         *best
                     } else {
                         *worst
@@ -290,14 +577,22 @@ impl NeuralNetwork {
     }
 }
 
+fn random_activation(p: &NeatParams) -> Activation {
+    if p.activation_options.is_empty() {
+        Activation::Sigmoid
+    } else {
+        p.activation_options[crate::rng::random::<usize>() % p.activation_options.len()]
+    }
+}
+
 fn get_random_key<K: Clone, V>(map: &IndexMap<K, V>) -> K {
-    let idx = rand::random::<usize>() % map.len();
+    let idx = crate::rng::random::<usize>() % map.len();
     map.get_index(idx).unwrap().0.clone()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{nn::ConnectionGene, nn::NeuralNetwork, Genome, NeatParams};
+    use crate::{nn::Activation, nn::NeuralNetwork, Genome, NeatParams};
     use std::f64::EPSILON;
 
     #[test]
@@ -318,6 +613,91 @@ mod tests {
         assert!(gene.weight.abs() > EPSILON);
     }
 
+    #[test]
+    fn mutation_activation_should_choose_from_options() {
+        let p = NeatParams {
+            mutate_add_conn_pr: 0.0,
+            mutate_add_neuron_pr: 0.0,
+            mutate_del_neuron_pr: 0.0,
+            mutate_del_conn_pr: 0.0,
+            activation_mutate_pr: 1.0,
+            activation_options: vec![Activation::Gaussian],
+            ..NeatParams::default(1, 1)
+        };
+        let mut genome = NeuralNetwork::with_neurons(3);
+        genome.add_connection(0, 1, 1.0);
+        genome.mutate(&mut 3, &p);
+        // The input keeps its activation
+        assert_eq!(
+            genome.get_activations(),
+            vec![
+                Activation::Sigmoid,
+                Activation::Gaussian,
+                Activation::Gaussian
+            ]
+        );
+    }
+
+    #[test]
+    fn mutation_should_make_connections_plastic() {
+        let p = NeatParams {
+            mutate_add_conn_pr: 0.0,
+            mutate_add_neuron_pr: 0.0,
+            mutate_del_neuron_pr: 0.0,
+            mutate_del_conn_pr: 0.0,
+            plastic_connection_pr: 1.0,
+            ..NeatParams::default(1, 1)
+        };
+        let mut genome = NeuralNetwork::with_neurons(2);
+        genome.add_connection(0, 1, 1.0);
+        let mutations = genome.mutate_tracked(&mut 2, &p);
+        assert!(genome.connections[&(0, 1)].plasticity.is_some());
+        assert!(mutations.contains(&crate::Mutation::Plasticity { count: 1 }));
+        let mut static_genome = NeuralNetwork::with_neurons(2);
+        static_genome.add_connection(0, 1, 1.0);
+        assert!(genome.distance(&static_genome, &p) > 0.0);
+    }
+
+    #[test]
+    fn mutation_rates_should_evolve_with_the_genome_and_be_inherited() {
+        let mut genome = NeuralNetwork::with_neurons(2);
+        genome.mutate(&mut 0, &NeatParams::default(1, 1));
+        assert!(genome.rates.is_none());
+
+        let p = NeatParams {
+            mutate_del_conn_pr: 0.0,
+            weight_mutate_pr: 0.9,
+            rate_adaptation: 0.5,
+            ..NeatParams::default(1, 1)
+        };
+        let mut fittest = NeuralNetwork::with_neurons(2);
+        for _ in 0..20 {
+            fittest.mutate(&mut 0, &p);
+        }
+        let rates = fittest.rates.clone().unwrap();
+        assert!(rates.weight_mutate_var != p.weight_mutate_var);
+        assert!(rates.weight_mutate_pr <= 1.0);
+        assert_eq!(rates.mutate_del_conn_pr, 0.0);
+
+        let other = NeuralNetwork::with_neurons(2);
+        assert_eq!(fittest.mate(&other, true, &p).rates, Some(rates.clone()));
+        assert_eq!(other.mate(&fittest, false, &p).rates, Some(rates));
+    }
+
+    #[test]
+    fn feed_forward_mutations_should_not_add_cycles() {
+        let p = NeatParams {
+            mutate_add_neuron_pr: 0.3,
+            feed_forward: true,
+            ..NeatParams::default(2, 1)
+        };
+        let mut genome = NeuralNetwork::with_neurons(3);
+        for i in 0..100 {
+            genome.mutate(&mut (i + 3), &p);
+            assert!(genome.is_acyclic());
+        }
+    }
+
     #[test]
     fn mutation_add_connection() {
         let mut genome = NeuralNetwork::with_neurons(3);
@@ -332,7 +712,7 @@ mod tests {
         let p = NeatParams::default(1, 1);
         let mut genome = NeuralNetwork::with_neurons(2);
         genome.add_connection(0, 1, 1.0);
-        genome.mutate_add_neuron(2);
+        genome.mutate_add_neuron(2, &p);
         let connections = genome.connections.values().collect::<Vec<_>>();
         assert_eq!(connections.len(), 2);
         assert!(connections[0].in_neuron_id() == 0);
@@ -524,11 +904,25 @@ mod tests {
         organism.add_connection(2, 3, -0.5);
         let mut output1 = vec![0.0; 1];
         organism.make_network().activate(vec![INPUT], &mut output1);
-        organism.mutate_add_neuron(4);
+        organism.mutate_add_neuron(4, &NeatParams::default(1, 1));
         let mut output2 = vec![0.0; 1];
         organism.make_network().activate(vec![INPUT], &mut output2);
         assert!((output1[0] - output2[0]).abs() < 0.01);
         // ^ due to the ctrnn implementation only approximating a DE, the output is not
         // always exactly the same
     }
+    #[test]
+    fn genome_should_survive_json_round_trip() {
+        let mut organism = NeuralNetwork::with_neurons(3);
+        organism.neurons.get_mut(&2).unwrap().bias = 0.7;
+        organism.add_connection(0, 2, 0.5);
+        organism.add_connection(2, 1, -1.5);
+        let json = serde_json::to_string(&organism).unwrap();
+        let copy: NeuralNetwork = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.parameters(), organism.parameters());
+        assert_eq!(
+            copy.connections.keys().collect::<Vec<_>>(),
+            organism.connections.keys().collect::<Vec<_>>()
+        );
+    }
 }
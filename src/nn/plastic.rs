@@ -0,0 +1,331 @@
+use super::Activation;
+use rand::distributions::{Distribution, Normal};
+use serde_derive::{Deserialize, Serialize};
+
+/// Generalized Hebbian ("ABCD") rule of a plastic connection. After every
+/// integration step, the weight changes by
+/// `eta * m * (a * pre * post + b * pre + c * post + d)`, where `pre` and
+/// `post` are the activations of the source and target neurons, and `m` is
+/// the modulation of the target neuron: `tanh` of the sum of its inputs from
+/// modulatory neurons, or 1 if it has none. The absolute value of the weight
+/// is kept at most `HebbianRule::MAX_WEIGHT`, or the absolute value of the
+/// evolved weight if that is larger.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HebbianRule {
+    /// Correlation term
+    pub a: f64,
+    /// Presynaptic term
+    pub b: f64,
+    /// Postsynaptic term
+    pub c: f64,
+    /// Constant term
+    pub d: f64,
+    /// Learning rate
+    pub eta: f64,
+}
+
+impl HebbianRule {
+    /// Bound of the absolute value of plastic weights, which would otherwise
+    /// grow without limit under most rules over a long episode.
+    pub const MAX_WEIGHT: f64 = 10.0;
+
+    /// Weight change for the given activations and modulation
+    pub fn delta(&self, pre: f64, post: f64, modulation: f64) -> f64 {
+        self.eta * modulation * (self.a * pre * post + self.b * pre + self.c * post + self.d)
+    }
+
+    /// Sum of the absolute differences of the coefficients
+    pub fn distance(&self, other: &HebbianRule) -> f64 {
+        (self.a - other.a).abs()
+            + (self.b - other.b).abs()
+            + (self.c - other.c).abs()
+            + (self.d - other.d).abs()
+            + (self.eta - other.eta).abs()
+    }
+
+    pub(crate) fn random<R: rand::Rng>(distribution: &Normal, rng: &mut R) -> HebbianRule {
+        HebbianRule {
+            a: distribution.sample(rng),
+            b: distribution.sample(rng),
+            c: distribution.sample(rng),
+            d: distribution.sample(rng),
+            eta: distribution.sample(rng).abs(),
+        }
+    }
+
+    pub(crate) fn perturb<R: rand::Rng>(&mut self, distribution: &Normal, rng: &mut R) {
+        self.a += distribution.sample(rng);
+        self.b += distribution.sample(rng);
+        self.c += distribution.sample(rng);
+        self.d += distribution.sample(rng);
+        self.eta = (self.eta + distribution.sample(rng)).abs();
+    }
+}
+
+/// A connection of a `StatefulCtrnn`
+#[derive(Debug, Clone)]
+struct Synapse {
+    source: usize,
+    target: usize,
+    initial_weight: f64,
+    weight: f64,
+    rule: Option<HebbianRule>,
+}
+
+/// Continuous Time Recurrent Neural Network that keeps its state between
+/// activations, and whose plastic connections change their weights while it
+/// runs. Call `reset()` at the start of every episode to restore the initial
+/// state and weights.
+///
+/// The first `step()` after a reset gives the same result as
+/// `Ctrnn::activate()` (if no weights change), later ones continue from the
+/// state the previous one left.
+///
+/// Modulatory neurons do not feed the state of the neurons they connect to;
+/// their weighted activations only set the modulation of the plasticity of
+/// the target neuron's incoming connections (see `HebbianRule`).
+#[derive(Debug, Clone)]
+pub struct StatefulCtrnn {
+    theta: Vec<f64>,
+    delta_t_tau: Vec<f64>,
+    activations: Vec<Activation>,
+    modulatory: Vec<bool>,
+    synapses: Vec<Synapse>,
+    steps: usize,
+    state: Option<Vec<f64>>,
+}
+
+impl StatefulCtrnn {
+    /// Create a network from the bias, time constant, activation and
+    /// modulatory flag of every neuron, and the connections
+    /// `(source, target, weight, rule)` between them (given as indices).
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        theta: Vec<f64>,
+        tau: Vec<f64>,
+        activations: Vec<Activation>,
+        modulatory: Vec<bool>,
+        connections: &[(usize, usize, f64, Option<HebbianRule>)],
+        delta_t: f64,
+        steps: usize,
+    ) -> StatefulCtrnn {
+        assert_eq!(theta.len(), tau.len());
+        assert_eq!(theta.len(), activations.len());
+        assert_eq!(theta.len(), modulatory.len());
+        StatefulCtrnn {
+            delta_t_tau: tau.iter().map(|tau| delta_t / tau).collect(),
+            theta,
+            activations,
+            modulatory,
+            synapses: connections
+                .iter()
+                .map(|&(source, target, weight, rule)| Synapse {
+                    source,
+                    target,
+                    initial_weight: weight,
+                    weight,
+                    rule,
+                })
+                .collect(),
+            steps,
+            state: None,
+        }
+    }
+
+    /// Restore the initial state and weights, for a new episode
+    pub fn reset(&mut self) {
+        self.state = None;
+        for synapse in &mut self.synapses {
+            synapse.weight = synapse.initial_weight;
+        }
+    }
+
+    /// Activate the network, continuing from the current state. The output
+    /// is written to `output`, the amount depending on the length of
+    /// `output`.
+    pub fn step(&mut self, input: &[f64], output: &mut [f64]) {
+        let n = self.theta.len();
+        let n_inputs = input.len();
+        let input = (0..n)
+            .map(|i| input.get(i).cloned().unwrap_or(0.0))
+            .collect::<Vec<_>>();
+        let mut y = self.state.take().unwrap_or_else(|| input.clone());
+        let mut activation = vec![0.0; n];
+        let mut sum = vec![0.0; n];
+        let mut modulation = vec![0.0; n];
+        let mut modulated = vec![false; n];
+        for _ in 0..self.steps {
+            for i in 0..n {
+                activation[i] = self.activations[i].apply(y[i] + self.theta[i]);
+                sum[i] = 0.0;
+                modulation[i] = 0.0;
+                modulated[i] = false;
+            }
+            for synapse in &self.synapses {
+                let signal = synapse.weight * activation[synapse.source];
+                if self.modulatory[synapse.source] {
+                    modulation[synapse.target] += signal;
+                    modulated[synapse.target] = true;
+                } else {
+                    sum[synapse.target] += signal;
+                }
+            }
+            for i in 0..n {
+                y[i] += self.delta_t_tau[i] * (sum[i] - y[i] + input[i]);
+            }
+            for synapse in &mut self.synapses {
+                if let Some(rule) = synapse.rule {
+                    if self.modulatory[synapse.source] {
+                        continue;
+                    }
+                    let m = if modulated[synapse.target] {
+                        modulation[synapse.target].tanh()
+                    } else {
+                        1.0
+                    };
+                    let delta =
+                        rule.delta(activation[synapse.source], activation[synapse.target], m);
+                    let bound = synapse.initial_weight.abs().max(HebbianRule::MAX_WEIGHT);
+                    synapse.weight = (synapse.weight + delta).clamp(-bound, bound);
+                }
+            }
+        }
+        for (o, y) in output.iter_mut().zip(y.iter().skip(n_inputs)) {
+            *o = *y;
+        }
+        self.state = Some(y);
+    }
+
+    /// Current weights of the connections, in the order they were given
+    pub fn weights(&self) -> Vec<f64> {
+        self.synapses.iter().map(|s| s.weight).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HebbianRule;
+    use crate::NeuralNetwork;
+
+    fn network() -> NeuralNetwork {
+        let mut network = NeuralNetwork::with_neurons(4);
+        network.neurons.get_mut(&3).unwrap().bias = 0.3;
+        network.add_connection(0, 3, 1.5);
+        network.add_connection(3, 1, 2.0);
+        network.add_connection(1, 3, -0.5);
+        network.add_connection(3, 2, -1.0);
+        network
+    }
+
+    #[test]
+    fn first_step_should_match_ctrnn_without_plasticity() {
+        let network = network();
+        let ctrnn = network.make_network();
+        let mut stateful = network.make_stateful();
+        for input in &[[0.0], [1.0], [-2.0]] {
+            let mut expected = vec![0.0; 2];
+            let mut output = vec![0.0; 2];
+            ctrnn.activate(input.to_vec(), &mut expected);
+            stateful.reset();
+            stateful.step(input, &mut output);
+            for (e, o) in expected.iter().zip(&output) {
+                assert!((e - o).abs() < 1e-9, "{} != {}", e, o);
+            }
+        }
+    }
+
+    #[test]
+    fn plastic_weights_should_change_within_an_episode_and_reset() {
+        let mut network = network();
+        network.connections.get_mut(&(0, 3)).unwrap().plasticity = Some(HebbianRule {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 0.1,
+            eta: 0.5,
+        });
+        let mut stateful = network.make_stateful();
+        let initial = stateful.weights();
+        let mut first_episode = Vec::new();
+        for _ in 0..3 {
+            let mut output = vec![0.0; 2];
+            stateful.step(&[1.0], &mut output);
+            first_episode.push(output);
+        }
+        let weights = stateful.weights();
+        assert!(weights[0] > initial[0]);
+        assert_eq!(&weights[1..], &initial[1..]);
+
+        stateful.reset();
+        assert_eq!(stateful.weights(), initial);
+        for expected in &first_episode {
+            let mut output = vec![0.0; 2];
+            stateful.step(&[1.0], &mut output);
+            assert_eq!(&output, expected);
+        }
+    }
+
+    #[test]
+    fn modulatory_neurons_should_gate_plasticity() {
+        let rule = HebbianRule {
+            a: 0.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            eta: 0.1,
+        };
+        let mut network = NeuralNetwork::with_neurons(4);
+        network.add_connection(0, 2, 1.0);
+        network.connections.get_mut(&(0, 2)).unwrap().plasticity = Some(rule);
+        network.neurons.get_mut(&3).unwrap().modulatory = true;
+        network.neurons.get_mut(&3).unwrap().bias = -100.0;
+        // The modulatory neuron is silent: no learning
+        network.add_connection(3, 2, 1.0);
+        let mut stateful = network.make_stateful();
+        stateful.step(&[1.0], &mut [0.0]);
+        assert!((stateful.weights()[0] - 1.0).abs() < 1e-12);
+
+        // Active modulatory neuron: learning, but no effect on the state
+        network.neurons.get_mut(&3).unwrap().bias = 100.0;
+        let mut stateful = network.make_stateful();
+        stateful.step(&[1.0], &mut [0.0]);
+        let expected = 1.0 + 10.0 * 0.1 * 1f64.tanh();
+        assert!((stateful.weights()[0] - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn plastic_weights_should_stay_bounded() {
+        let mut network = network();
+        network.connections.get_mut(&(3, 1)).unwrap().plasticity = Some(HebbianRule {
+            a: 5.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            eta: 1.0,
+        });
+        let mut stateful = network.make_stateful();
+        let mut output = vec![0.0; 2];
+        for _ in 0..100 {
+            stateful.step(&[1.0], &mut output);
+        }
+        assert_eq!(stateful.weights()[1], HebbianRule::MAX_WEIGHT);
+        assert!(output.iter().all(|o| o.is_finite()));
+    }
+
+    #[test]
+    fn plastic_weights_beyond_the_bound_should_be_kept() {
+        let mut network = network();
+        let connection = network.connections.get_mut(&(3, 1)).unwrap();
+        connection.weight = 15.0;
+        connection.plasticity = Some(HebbianRule {
+            a: 0.0,
+            b: 0.0,
+            c: 0.0,
+            d: 0.0,
+            eta: 1.0,
+        });
+        let mut stateful = network.make_stateful();
+        stateful.step(&[1.0], &mut [0.0; 2]);
+        assert_eq!(stateful.weights()[1], 15.0);
+    }
+}
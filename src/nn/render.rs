@@ -0,0 +1,400 @@
+use super::{NeuralNetwork, NeuronId};
+use std::fmt::Write;
+
+/// The role of a neuron in the network, given how many inputs and outputs it
+/// is used with. Inputs are the first neurons (ordered by id), then outputs,
+/// and the rest are hidden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeuronRole {
+    /// Receives external input
+    Input,
+    /// Read as output of the network
+    Output,
+    /// Everything else
+    Hidden,
+}
+
+impl NeuronRole {
+    fn color(self) -> &'static str {
+        match self {
+            NeuronRole::Input => "#8ecae6",
+            NeuronRole::Output => "#ffb703",
+            NeuronRole::Hidden => "#d9d9d9",
+        }
+    }
+}
+
+impl NeuralNetwork {
+    /// Ids of the neurons, in the order they are laid out in the phenotype.
+    pub fn sorted_neuron_ids(&self) -> Vec<NeuronId> {
+        let mut ids = self.neurons.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    /// Role of each neuron (in the order of `sorted_neuron_ids()`) when the
+    /// network is activated with `n_inputs` inputs and `n_outputs` outputs.
+    pub fn neuron_roles(&self, n_inputs: usize, n_outputs: usize) -> Vec<NeuronRole> {
+        (0..self.n_neurons())
+            .map(|i| {
+                if i < n_inputs {
+                    NeuronRole::Input
+                } else if i < n_inputs + n_outputs {
+                    NeuronRole::Output
+                } else {
+                    NeuronRole::Hidden
+                }
+            })
+            .collect()
+    }
+
+    /// Render the network in the Graphviz DOT language. Neurons are coloured by
+    /// role, and connections are coloured by the sign of the weight (green for
+    /// positive, red for negative) with a thickness relative to its magnitude.
+    /// Connections with a weight of zero have no effect, and are drawn dashed.
+    pub fn to_dot(&self, n_inputs: usize, n_outputs: usize) -> String {
+        let ids = self.sorted_neuron_ids();
+        let roles = self.neuron_roles(n_inputs, n_outputs);
+        let max_weight = self.max_abs_weight();
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph neural_network {{").unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    node [shape=circle, style=filled, fontsize=10];").unwrap();
+        for (id, role) in ids.iter().zip(&roles) {
+            writeln!(
+                dot,
+                "    n{} [label=\"{}\\nb={:.2}\", fillcolor=\"{}\"];",
+                id,
+                id,
+                self.neurons[id].bias,
+                role.color()
+            )
+            .unwrap();
+        }
+        for (rank, role) in &[("source", NeuronRole::Input), ("sink", NeuronRole::Output)] {
+            let members = ids
+                .iter()
+                .zip(&roles)
+                .filter(|(_, r)| *r == role)
+                .map(|(id, _)| format!("n{};", id))
+                .collect::<Vec<_>>();
+            if !members.is_empty() {
+                writeln!(dot, "    {{ rank={}; {} }}", rank, members.join(" ")).unwrap();
+            }
+        }
+        for gene in self.connections.values() {
+            let style = if gene.weight == 0.0 {
+                "dashed"
+            } else {
+                "solid"
+            };
+            writeln!(
+                dot,
+                "    n{} -> n{} [label=\"{:.2}\", color=\"{}\", penwidth={:.2}, style={}];",
+                gene.in_neuron_id(),
+                gene.out_neuron_id(),
+                gene.weight,
+                weight_color(gene.weight, max_weight),
+                0.5 + 3.5 * gene.weight.abs() / max_weight,
+                style
+            )
+            .unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+
+    /// Render the network as a standalone SVG image, using a layered layout:
+    /// inputs in the leftmost column, outputs in the rightmost, and hidden
+    /// neurons in between according to their distance from the inputs.
+    /// Colours and line widths follow the same conventions as `to_dot()`.
+    pub fn to_svg(&self, n_inputs: usize, n_outputs: usize) -> String {
+        const RADIUS: f64 = 14.0;
+        const LAYER_SPACING: f64 = 120.0;
+        const NODE_SPACING: f64 = 50.0;
+        const MARGIN: f64 = 40.0;
+
+        let ids = self.sorted_neuron_ids();
+        let roles = self.neuron_roles(n_inputs, n_outputs);
+        let layers = self.layers(&ids, &roles);
+        let max_weight = self.max_abs_weight();
+
+        // Order neurons within each layer, and compute positions
+        let n_layers = layers.iter().cloned().max().map_or(0, |x| x + 1);
+        let mut columns = vec![Vec::new(); n_layers];
+        for (idx, layer) in layers.iter().enumerate() {
+            columns[*layer].push(idx);
+        }
+        self.order_columns(&ids, &mut columns);
+        let max_column = columns.iter().map(|c| c.len()).max().unwrap_or(0);
+        let height = MARGIN * 2.0 + (max_column.max(1) - 1) as f64 * NODE_SPACING;
+        let width = MARGIN * 2.0 + (n_layers.max(1) - 1) as f64 * LAYER_SPACING;
+        let mut positions = vec![(0.0, 0.0); ids.len()];
+        for (layer, column) in columns.iter().enumerate() {
+            let offset = (height - (column.len().max(1) - 1) as f64 * NODE_SPACING) / 2.0;
+            for (i, idx) in column.iter().enumerate() {
+                positions[*idx] = (
+                    MARGIN + layer as f64 * LAYER_SPACING,
+                    offset + i as f64 * NODE_SPACING,
+                );
+            }
+        }
+        let position = |id: &NeuronId| positions[ids.binary_search(id).unwrap()];
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" \
+             viewBox=\"0 0 {:.0} {:.0}\" font-family=\"sans-serif\" font-size=\"10\">",
+            width, height, width, height
+        )
+        .unwrap();
+        for gene in self.connections.values() {
+            let (x1, y1) = position(&gene.in_neuron_id());
+            let (x2, y2) = position(&gene.out_neuron_id());
+            let stroke = format!(
+                "stroke=\"{}\" stroke-width=\"{:.2}\" fill=\"none\"{}",
+                weight_color(gene.weight, max_weight),
+                0.5 + 3.5 * gene.weight.abs() / max_weight,
+                if gene.weight == 0.0 {
+                    " stroke-dasharray=\"4,3\""
+                } else {
+                    ""
+                }
+            );
+            if gene.in_neuron_id() == gene.out_neuron_id() {
+                // Self-loop above the neuron
+                writeln!(
+                    svg,
+                    "  <path d=\"M {:.1} {:.1} C {:.1} {:.1} {:.1} {:.1} {:.1} {:.1}\" {}/>",
+                    x1 - RADIUS * 0.6,
+                    y1 - RADIUS * 0.8,
+                    x1 - RADIUS * 2.0,
+                    y1 - RADIUS * 3.5,
+                    x1 + RADIUS * 2.0,
+                    y1 - RADIUS * 3.5,
+                    x1 + RADIUS * 0.6,
+                    y1 - RADIUS * 0.8,
+                    stroke
+                )
+                .unwrap();
+            } else if x2 <= x1 {
+                // Recurrent connection: curve around, so it doesn't overlap
+                // forward connections
+                writeln!(
+                    svg,
+                    "  <path d=\"M {:.1} {:.1} Q {:.1} {:.1} {:.1} {:.1}\" {}/>",
+                    x1,
+                    y1,
+                    (x1 + x2) / 2.0,
+                    y1.min(y2) - NODE_SPACING,
+                    x2,
+                    y2,
+                    stroke
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    svg,
+                    "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" {}/>",
+                    x1, y1, x2, y2, stroke
+                )
+                .unwrap();
+            }
+        }
+        for (idx, id) in ids.iter().enumerate() {
+            let (x, y) = positions[idx];
+            writeln!(
+                svg,
+                "  <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\" stroke=\"#333333\">\
+                 <title>neuron {} (bias {:.3})</title></circle>",
+                x,
+                y,
+                RADIUS,
+                roles[idx].color(),
+                id,
+                self.neurons[id].bias
+            )
+            .unwrap();
+            writeln!(
+                svg,
+                "  <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                x,
+                y + 3.5,
+                id
+            )
+            .unwrap();
+        }
+        writeln!(svg, "</svg>").unwrap();
+        svg
+    }
+
+    fn max_abs_weight(&self) -> f64 {
+        self.connections
+            .values()
+            .map(|gene| gene.weight.abs())
+            .fold(0.0, f64::max)
+            .max(f64::EPSILON)
+    }
+
+    /// Helper of `to_svg`. Assign each neuron (given by index into `ids`) a
+    /// layer: inputs are in layer 0, outputs in the last layer, and hidden
+    /// neurons are placed by their longest distance from a neuron without
+    /// incoming connections. Recurrent connections are ignored.
+    fn layers(&self, ids: &[NeuronId], roles: &[NeuronRole]) -> Vec<usize> {
+        let n = ids.len();
+        let index = |id: &NeuronId| ids.binary_search(id).unwrap();
+        let mut incoming = vec![Vec::new(); n];
+        for gene in self.connections.values() {
+            incoming[index(&gene.out_neuron_id())].push(index(&gene.in_neuron_id()));
+        }
+
+        // Longest path by depth-first search, where edges to neurons on the
+        // current path (i.e. recurrent connections) are ignored
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            New,
+            Visiting,
+            Done,
+        }
+        let mut depth = vec![0; n];
+        let mut state = vec![State::New; n];
+        for start in 0..n {
+            if state[start] != State::New {
+                continue;
+            }
+            let mut stack = vec![(start, 0)];
+            state[start] = State::Visiting;
+            while let Some((node, next)) = stack.pop() {
+                if next < incoming[node].len() {
+                    stack.push((node, next + 1));
+                    let source = incoming[node][next];
+                    if state[source] == State::New && roles[source] != NeuronRole::Input {
+                        state[source] = State::Visiting;
+                        stack.push((source, 0));
+                    }
+                } else {
+                    depth[node] = incoming[node]
+                        .iter()
+                        .filter(|source| state[**source] == State::Done)
+                        .map(|source| depth[*source] + 1)
+                        .max()
+                        .unwrap_or(0);
+                    state[node] = State::Done;
+                }
+            }
+        }
+
+        let max_hidden = (0..n)
+            .filter(|i| roles[*i] == NeuronRole::Hidden)
+            .map(|i| depth[i].max(1))
+            .max()
+            .unwrap_or(0);
+        (0..n)
+            .map(|i| match roles[i] {
+                NeuronRole::Input => 0,
+                NeuronRole::Hidden => depth[i].max(1),
+                NeuronRole::Output => max_hidden + 1,
+            })
+            .collect()
+    }
+
+    /// Helper of `to_svg`. Reduce crossings by ordering each column by the
+    /// average position of the neurons connected to it in the previous column
+    /// (barycenter heuristic). Inputs and outputs keep their order.
+    fn order_columns(&self, ids: &[NeuronId], columns: &mut [Vec<usize>]) {
+        let index = |id: &NeuronId| ids.binary_search(id).unwrap();
+        let last = columns.len().saturating_sub(1);
+        for layer in 1..last {
+            let mut row = vec![0.0; ids.len()];
+            for (i, idx) in columns[layer - 1].iter().enumerate() {
+                row[*idx] = i as f64;
+            }
+            let previous = &columns[layer - 1];
+            let barycenter = |idx: usize| {
+                let sources = self
+                    .connections
+                    .values()
+                    .filter(|gene| index(&gene.out_neuron_id()) == idx)
+                    .map(|gene| index(&gene.in_neuron_id()))
+                    .filter(|source| previous.contains(source))
+                    .map(|source| row[source])
+                    .collect::<Vec<_>>();
+                if sources.is_empty() {
+                    f64::MAX
+                } else {
+                    sources.iter().sum::<f64>() / sources.len() as f64
+                }
+            };
+            let mut column = columns[layer]
+                .iter()
+                .map(|idx| (barycenter(*idx), *idx))
+                .collect::<Vec<_>>();
+            column.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            columns[layer] = column.into_iter().map(|(_, idx)| idx).collect();
+        }
+    }
+}
+
+fn weight_color(weight: f64, max_weight: f64) -> String {
+    // Fade towards grey for small weights
+    let strength = (weight.abs() / max_weight).min(1.0);
+    let fade = |full: u8| (f64::from(0xbb) + (f64::from(full) - f64::from(0xbb)) * strength) as u8;
+    if weight >= 0.0 {
+        format!("#{:02x}{:02x}{:02x}", fade(0x1a), fade(0x96), fade(0x41))
+    } else {
+        format!("#{:02x}{:02x}{:02x}", fade(0xd7), fade(0x19), fade(0x1c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NeuronRole;
+    use crate::NeuralNetwork;
+
+    fn network() -> NeuralNetwork {
+        let mut network = NeuralNetwork::with_neurons(4);
+        network.add_connection(0, 3, 1.5);
+        network.add_connection(3, 2, -0.5);
+        network.add_connection(1, 2, 0.0);
+        network.add_connection(2, 3, 0.25);
+        network
+    }
+
+    #[test]
+    fn dot_should_contain_all_neurons_and_connections() {
+        let dot = network().to_dot(2, 1);
+        assert!(dot.starts_with("digraph"));
+        for id in 0..4 {
+            assert!(dot.contains(&format!("n{} [", id)));
+        }
+        assert!(dot.contains("n0 -> n3"));
+        assert!(dot.contains("n3 -> n2"));
+        assert!(dot.contains("{ rank=source; n0; n1; }"));
+        assert!(dot.contains("{ rank=sink; n2; }"));
+        assert_eq!(dot.matches("style=dashed").count(), 1);
+    }
+
+    #[test]
+    fn svg_should_place_hidden_neurons_between_inputs_and_outputs() {
+        let network = network();
+        let roles = network.neuron_roles(2, 1);
+        assert_eq!(
+            roles,
+            vec![
+                NeuronRole::Input,
+                NeuronRole::Input,
+                NeuronRole::Output,
+                NeuronRole::Hidden
+            ]
+        );
+        assert_eq!(network.layers(&[0, 1, 2, 3], &roles), vec![0, 0, 2, 1]);
+
+        let svg = network.to_svg(2, 1);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert_eq!(svg.matches("stroke-dasharray").count(), 1);
+    }
+}
@@ -0,0 +1,191 @@
+use super::{NeuralNetwork, NeuronId};
+use std::collections::HashSet;
+
+impl NeuralNetwork {
+    /// Remove the structure that does not affect the outputs when the network
+    /// is activated with `n_inputs` inputs and `n_outputs` outputs, e.g. left
+    /// behind by deletion mutations, to shrink a champion before export.
+    /// Removes, until nothing more can be removed:
+    /// * connections with a weight of at most `weight_tolerance` (in absolute
+    ///   value), unless they are plastic or come from a modulatory neuron. With
+    ///   a tolerance of 0, only connections without effect are removed, and
+    ///   the outputs do not change.
+    /// * hidden neurons without a path to an output, and all connections into
+    ///   neurons without one
+    /// * hidden neurons that are silent: no input reaches them, and their
+    ///   activation is 0 (e.g. `Relu` with a negative bias)
+    ///
+    /// Hidden neurons that no input reaches are kept otherwise, since they
+    /// send a constant signal, like a bias. Inputs and outputs are never
+    /// removed.
+    pub fn simplify(&mut self, n_inputs: usize, n_outputs: usize, weight_tolerance: f64) {
+        let ids = self.sorted_neuron_ids();
+        let outputs = ids
+            .iter()
+            .skip(n_inputs)
+            .take(n_outputs)
+            .cloned()
+            .collect::<Vec<_>>();
+        let hidden = ids
+            .iter()
+            .skip(n_inputs + n_outputs)
+            .cloned()
+            .collect::<HashSet<_>>();
+
+        loop {
+            let size = (self.n_neurons(), self.n_connections());
+
+            let neurons = &self.neurons;
+            self.connections.retain(|_, gene| {
+                gene.weight.abs() > weight_tolerance
+                    || gene.plasticity.is_some()
+                    || neurons[&gene.in_neuron_id()].modulatory
+            });
+
+            let useful = self.upstream(&outputs);
+            self.connections
+                .retain(|(_, target), _| useful.contains(target));
+            self.neurons
+                .retain(|id, _| !hidden.contains(id) || useful.contains(id));
+
+            let silent = self.silent(&hidden);
+            self.connections
+                .retain(|(source, target), _| !silent.contains(source) && !silent.contains(target));
+            self.neurons.retain(|id, _| !silent.contains(id));
+
+            if (self.n_neurons(), self.n_connections()) == size {
+                break;
+            }
+        }
+    }
+
+    /// Helper of `simplify()`. The neurons of `hidden` whose activation is
+    /// always 0: not modulatory, 0 at a state of 0, and only connected from
+    /// other silent neurons, so that their state stays 0.
+    fn silent(&self, hidden: &HashSet<NeuronId>) -> HashSet<NeuronId> {
+        let mut silent = self
+            .neurons
+            .iter()
+            .filter(|(id, gene)| {
+                hidden.contains(id) && !gene.modulatory && gene.activation.apply(gene.bias) == 0.0
+            })
+            .map(|(id, _)| *id)
+            .collect::<HashSet<_>>();
+        loop {
+            let fed = self
+                .connections
+                .keys()
+                .filter(|(source, target)| !silent.contains(source) && silent.contains(target))
+                .map(|(_, target)| *target)
+                .collect::<Vec<_>>();
+            if fed.is_empty() {
+                return silent;
+            }
+            for id in fed {
+                silent.remove(&id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nn::{Activation, NeuralNetwork, NeuronGene};
+    use crate::{Genome, NeatParams};
+
+    /// Check the outputs of the networks, activated with 2 inputs and 2
+    /// outputs
+    fn assert_same_outputs(original: &NeuralNetwork, simplified: &NeuralNetwork) {
+        for input in &[[0.0, 0.0], [1.0, -0.5], [-2.0, 3.0]] {
+            let mut expected = vec![0.0; 2];
+            let mut output = vec![0.0; 2];
+            original
+                .make_network()
+                .activate(input.to_vec(), &mut expected);
+            simplified
+                .make_network()
+                .activate(input.to_vec(), &mut output);
+            for (a, b) in expected.iter().zip(&output) {
+                assert!((a - b).abs() < 1e-9, "{:?} != {:?}", expected, output);
+            }
+
+            let mut original = original.make_stateful();
+            let mut simplified = simplified.make_stateful();
+            for _ in 0..3 {
+                original.step(input, &mut expected);
+                simplified.step(input, &mut output);
+                for (a, b) in expected.iter().zip(&output) {
+                    assert!((a - b).abs() < 1e-9, "{:?} != {:?}", expected, output);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn simplify_should_remove_structure_that_cannot_affect_outputs() {
+        // Neurons 0 and 1 are the inputs, 2 and 3 the outputs
+        let mut network = NeuralNetwork::with_neurons(9);
+        network.add_connection(0, 4, 1.0);
+        network.add_connection(4, 2, 2.0);
+        network.add_connection(1, 3, -1.0);
+        // Dead end
+        network.add_connection(0, 5, 1.0);
+        network.add_connection(5, 5, 1.0);
+        network.add_connection(2, 5, 1.0);
+        // Silent neuron
+        let mut gene = NeuronGene::new(-1.0, 6);
+        gene.activation = Activation::Relu;
+        network.neurons.insert(6, gene);
+        network.add_connection(6, 3, 1.0);
+        // Constant signal
+        network.add_connection(7, 3, 0.5);
+        // Weak connection, to a neuron that is then useless
+        network.add_connection(8, 4, 0.0);
+        network.add_connection(1, 8, 1.0);
+
+        let mut simplified = network.clone();
+        simplified.simplify(2, 2, 0.0);
+        assert_eq!(simplified.sorted_neuron_ids(), vec![0, 1, 2, 3, 4, 7]);
+        assert_eq!(
+            simplified.connections.keys().cloned().collect::<Vec<_>>(),
+            vec![(0, 4), (4, 2), (1, 3), (7, 3)]
+        );
+        assert_same_outputs(&network, &simplified);
+
+        simplified.simplify(2, 2, 0.6);
+        assert_eq!(simplified.sorted_neuron_ids(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn simplified_networks_should_have_the_same_outputs() {
+        let p = NeatParams {
+            mutate_add_neuron_pr: 0.5,
+            mutate_del_conn_pr: 0.3,
+            mutate_del_neuron_pr: 0.2,
+            weight_replace_pr: 0.2,
+            activation_mutate_pr: 0.1,
+            activation_options: vec![Activation::Sigmoid, Activation::Relu, Activation::Tanh],
+            plastic_connection_pr: 0.2,
+            modulatory_neuron_pr: 0.2,
+            ..NeatParams::default(2, 2)
+        };
+        let mut removed = 0;
+        for _ in 0..50 {
+            let mut network = NeuralNetwork::with_neurons(4);
+            let mut innovation_id = 4;
+            for _ in 0..30 {
+                network.mutate(&mut innovation_id, &p);
+                if crate::rng::random::<f64>() < 0.2 {
+                    if let Some(gene) = network.connections.values_mut().next() {
+                        gene.weight = 0.0;
+                    }
+                }
+            }
+            let mut simplified = network.clone();
+            simplified.simplify(2, 2, 0.0);
+            removed += network.complexity() - simplified.complexity();
+            assert_same_outputs(&network, &simplified);
+        }
+        assert!(removed > 0);
+    }
+}
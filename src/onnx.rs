@@ -0,0 +1,676 @@
+//! Export of acyclic networks to [ONNX](https://onnx.ai), so that champions can
+//! run in any ONNX runtime.
+//!
+//! The exported graph has one input `input` of shape `[N, n_inputs]` and one
+//! output `output` of shape `[N, n_outputs]`, both `float`. It computes the
+//! same function as `FeedForward` (i.e. the settled state of the `Ctrnn`):
+//! neurons are grouped by topological depth and activation function, and each
+//! group is computed with one `MatMul` per group it receives connections from,
+//! followed by `Add` of the bias and the activation ops.
+//!
+//! The protobuf encoding is written by hand to avoid depending on a protobuf
+//! library.
+
+use crate::model::Model;
+use crate::nn::{Activation, NeuralNetwork};
+use std::collections::HashMap;
+
+/// IR version of the emitted models
+const IR_VERSION: u64 = 7;
+/// Version of the default operator set used by the emitted models
+const OPSET_VERSION: u64 = 13;
+
+/// `TensorProto.DataType`
+const FLOAT: u64 = 1;
+const INT64: u64 = 7;
+/// `AttributeProto.AttributeType`
+const ATTRIBUTE_INT: u64 = 2;
+
+impl NeuralNetwork {
+    /// Export the network as a serialized ONNX model. See `Model::to_onnx()`.
+    /// Returns `None` if the network has recurrent connections.
+    pub fn to_onnx(&self, n_inputs: usize, n_outputs: usize) -> Option<Vec<u8>> {
+        self.to_model(n_inputs, n_outputs).to_onnx()
+    }
+}
+
+impl Model {
+    /// Export the model as a serialized ONNX model, computing the same as
+    /// `to_feed_forward()`. Returns `None` if the network has recurrent
+    /// connections.
+    pub fn to_onnx(&self) -> Option<Vec<u8>> {
+        let (neurons, index) = self.layout();
+        let n = neurons.len();
+        let n_inputs = self.n_inputs();
+
+        // Depth of every neuron, using Kahn's algorithm
+        let mut incoming = vec![Vec::new(); n];
+        let mut outgoing = vec![Vec::new(); n];
+        for c in &self.connections {
+            incoming[index[&c.to]].push((index[&c.from], c.weight));
+            outgoing[index[&c.from]].push(index[&c.to]);
+        }
+        let mut n_unresolved = incoming.iter().map(|x| x.len()).collect::<Vec<_>>();
+        let mut depth = vec![0; n];
+        let mut ready = (0..n).filter(|i| n_unresolved[*i] == 0).collect::<Vec<_>>();
+        let mut n_resolved = 0;
+        while let Some(neuron) = ready.pop() {
+            n_resolved += 1;
+            for target in &outgoing[neuron] {
+                depth[*target] = depth[*target].max(depth[neuron] + 1);
+                n_unresolved[*target] -= 1;
+                if n_unresolved[*target] == 0 {
+                    ready.push(*target);
+                }
+            }
+        }
+        if n_resolved < n {
+            return None;
+        }
+
+        // Group neurons by depth and activation
+        let mut groups: Vec<(usize, Activation, Vec<usize>)> = Vec::new();
+        for i in 0..n {
+            let key = (depth[i], neurons[i].activation);
+            match groups.iter_mut().find(|g| (g.0, g.1) == key) {
+                Some(group) => group.2.push(i),
+                None => groups.push((key.0, key.1, vec![i])),
+            }
+        }
+        groups.sort_by_key(|g| g.0);
+        let mut position = vec![(0, 0); n]; // (group, index in group)
+        for (g, group) in groups.iter().enumerate() {
+            for (k, i) in group.2.iter().enumerate() {
+                position[*i] = (g, k);
+            }
+        }
+
+        let mut graph = Graph::default();
+        for (g, (_, activation, members)) in groups.iter().enumerate() {
+            // External input
+            let mut selection = vec![0.0; n_inputs * members.len()];
+            for (k, i) in members.iter().enumerate() {
+                if *i < n_inputs {
+                    selection[i * members.len() + k] = 1.0;
+                }
+            }
+            let selection = graph.float_initializer(
+                &format!("g{}_input_weights", g),
+                &[n_inputs, members.len()],
+                &selection,
+            );
+            let mut sum = graph.node("MatMul", &["input", &selection], &[]);
+
+            // Connections from earlier groups
+            let mut weights = HashMap::new();
+            for (k, i) in members.iter().enumerate() {
+                for &(source, weight) in &incoming[*i] {
+                    let (h, l) = position[source];
+                    let matrix = weights
+                        .entry(h)
+                        .or_insert_with(|| vec![0.0; groups[h].2.len() * members.len()]);
+                    matrix[l * members.len() + k] += weight;
+                }
+            }
+            let mut sources = weights.into_iter().collect::<Vec<_>>();
+            sources.sort_by_key(|x| x.0);
+            for (h, matrix) in sources {
+                let matrix = graph.float_initializer(
+                    &format!("g{}_weights_from_g{}", g, h),
+                    &[groups[h].2.len(), members.len()],
+                    &matrix,
+                );
+                let product = graph.node("MatMul", &[&format!("g{}_activation", h), &matrix], &[]);
+                sum = graph.node("Add", &[&sum, &product], &[]);
+            }
+            graph.rename(&sum, &format!("g{}_state", g));
+
+            // Bias and activation
+            let bias = members.iter().map(|i| neurons[*i].bias).collect::<Vec<_>>();
+            let bias = graph.float_initializer(&format!("g{}_bias", g), &[members.len()], &bias);
+            let x = graph.node("Add", &[&format!("g{}_state", g), &bias], &[]);
+            let activated = match activation {
+                Activation::Sigmoid => {
+                    let five = graph.float_initializer(&format!("g{}_five", g), &[], &[5.0]);
+                    let x = graph.node("Mul", &[&x, &five], &[]);
+                    graph.node("Sigmoid", &[&x], &[])
+                }
+                Activation::Tanh => graph.node("Tanh", &[&x], &[]),
+                Activation::Relu => graph.node("Relu", &[&x], &[]),
+                Activation::Identity => graph.node("Identity", &[&x], &[]),
+                Activation::Sine => graph.node("Sin", &[&x], &[]),
+                Activation::Abs => graph.node("Abs", &[&x], &[]),
+                Activation::Gaussian => {
+                    let square = graph.node("Mul", &[&x, &x], &[]);
+                    let negative = graph.node("Neg", &[&square], &[]);
+                    graph.node("Exp", &[&negative], &[])
+                }
+            };
+            graph.rename(&activated, &format!("g{}_activation", g));
+        }
+
+        // Gather the outputs from the states of all groups
+        let states = (0..groups.len())
+            .map(|g| format!("g{}_state", g))
+            .collect::<Vec<_>>();
+        let states = states.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+        let all_states = graph.node("Concat", &states, &[("axis", 1)]);
+        let offsets = groups
+            .iter()
+            .scan(0, |offset, group| {
+                let current = *offset;
+                *offset += group.2.len();
+                Some(current)
+            })
+            .collect::<Vec<_>>();
+        let output_indices = (n_inputs..n_inputs + self.n_outputs())
+            .map(|i| (offsets[position[i].0] + position[i].1) as i64)
+            .collect::<Vec<_>>();
+        let output_indices = graph.int64_initializer("output_indices", &output_indices);
+        let output = graph.node("Gather", &[&all_states, &output_indices], &[("axis", 1)]);
+        graph.rename(&output, "output");
+
+        Some(graph.into_model(n_inputs, self.n_outputs()))
+    }
+}
+
+/// Graph under construction. Nodes are kept in topological order.
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Node>,
+    initializers: Vec<Vec<u8>>,
+    n_values: usize,
+}
+
+struct Node {
+    op_type: &'static str,
+    inputs: Vec<String>,
+    output: String,
+    attributes: Vec<(&'static str, i64)>,
+}
+
+impl Graph {
+    /// Add a node with a single output, and return the name of the output.
+    fn node(
+        &mut self,
+        op_type: &'static str,
+        inputs: &[&str],
+        attributes: &[(&'static str, i64)],
+    ) -> String {
+        self.n_values += 1;
+        let output = format!("v{}", self.n_values);
+        self.nodes.push(Node {
+            op_type,
+            inputs: inputs.iter().map(|x| x.to_string()).collect(),
+            output: output.clone(),
+            attributes: attributes.to_vec(),
+        });
+        output
+    }
+
+    /// Give the output `value` of a node a new name.
+    fn rename(&mut self, value: &str, name: &str) {
+        for node in &mut self.nodes {
+            if node.output == value {
+                node.output = name.to_string();
+            }
+            for input in &mut node.inputs {
+                if input == value {
+                    *input = name.to_string();
+                }
+            }
+        }
+    }
+
+    fn float_initializer(&mut self, name: &str, dims: &[usize], values: &[f64]) -> String {
+        let mut tensor = Message::default();
+        for dim in dims {
+            tensor.varint(1, *dim as u64);
+        }
+        tensor.varint(2, FLOAT);
+        tensor.packed_floats(4, values);
+        tensor.string(8, name);
+        self.initializers.push(tensor.0);
+        name.to_string()
+    }
+
+    fn int64_initializer(&mut self, name: &str, values: &[i64]) -> String {
+        let mut tensor = Message::default();
+        tensor.varint(1, values.len() as u64);
+        tensor.varint(2, INT64);
+        tensor.packed_int64(7, values);
+        tensor.string(8, name);
+        self.initializers.push(tensor.0);
+        name.to_string()
+    }
+
+    fn into_model(self, n_inputs: usize, n_outputs: usize) -> Vec<u8> {
+        let mut graph = Message::default();
+        for (i, node) in self.nodes.iter().enumerate() {
+            let mut message = Message::default();
+            for input in &node.inputs {
+                message.string(1, input);
+            }
+            message.string(2, &node.output);
+            message.string(3, &format!("{}_{}", node.op_type, i));
+            message.string(4, node.op_type);
+            for (name, value) in &node.attributes {
+                let mut attribute = Message::default();
+                attribute.string(1, name);
+                attribute.varint(3, *value as u64);
+                attribute.varint(20, ATTRIBUTE_INT);
+                message.bytes(5, &attribute.0);
+            }
+            graph.bytes(1, &message.0);
+        }
+        graph.string(2, "rustneat");
+        for initializer in &self.initializers {
+            graph.bytes(5, initializer);
+        }
+        graph.bytes(11, &value_info("input", n_inputs));
+        graph.bytes(12, &value_info("output", n_outputs));
+
+        let mut opset = Message::default();
+        opset.string(1, "");
+        opset.varint(2, OPSET_VERSION);
+
+        let mut model = Message::default();
+        model.varint(1, IR_VERSION);
+        model.string(2, "rustneat");
+        model.string(3, env!("CARGO_PKG_VERSION"));
+        model.bytes(7, &graph.0);
+        model.bytes(8, &opset.0);
+        model.0
+    }
+}
+
+/// `ValueInfoProto` of a float tensor of shape `[N, width]`
+fn value_info(name: &str, width: usize) -> Vec<u8> {
+    let mut batch = Message::default();
+    batch.string(2, "N");
+    let mut features = Message::default();
+    features.varint(1, width as u64);
+    let mut shape = Message::default();
+    shape.bytes(1, &batch.0);
+    shape.bytes(1, &features.0);
+    let mut tensor_type = Message::default();
+    tensor_type.varint(1, FLOAT);
+    tensor_type.bytes(2, &shape.0);
+    let mut type_proto = Message::default();
+    type_proto.bytes(1, &tensor_type.0);
+    let mut value_info = Message::default();
+    value_info.string(1, name);
+    value_info.bytes(2, &type_proto.0);
+    value_info.0
+}
+
+/// Minimal protobuf encoder
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn raw_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+    fn varint(&mut self, field: u64, value: u64) {
+        self.raw_varint(field << 3);
+        self.raw_varint(value);
+    }
+    fn bytes(&mut self, field: u64, bytes: &[u8]) {
+        self.raw_varint((field << 3) | 2);
+        self.raw_varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+    fn string(&mut self, field: u64, string: &str) {
+        self.bytes(field, string.as_bytes());
+    }
+    fn packed_floats(&mut self, field: u64, values: &[f64]) {
+        let bytes = values
+            .iter()
+            .flat_map(|x| (*x as f32).to_bits().to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        self.bytes(field, &bytes);
+    }
+    fn packed_int64(&mut self, field: u64, values: &[i64]) {
+        let mut packed = Message::default();
+        for value in values {
+            packed.raw_varint(*value as u64);
+        }
+        self.bytes(field, &packed.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! A small ONNX interpreter, covering the ops emitted by the exporter.
+
+    use crate::nn::Activation;
+    use crate::NeuralNetwork;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Clone)]
+    enum Field {
+        Varint(u64),
+        Bytes(Vec<u8>),
+    }
+
+    fn decode(mut bytes: &[u8]) -> Vec<(u64, Field)> {
+        fn varint(bytes: &mut &[u8]) -> u64 {
+            let mut value = 0;
+            let mut shift = 0;
+            loop {
+                let byte = bytes[0];
+                *bytes = &bytes[1..];
+                value |= u64::from(byte & 0x7f) << shift;
+                shift += 7;
+                if byte < 0x80 {
+                    return value;
+                }
+            }
+        }
+        let mut fields = Vec::new();
+        while !bytes.is_empty() {
+            let key = varint(&mut bytes);
+            match key & 7 {
+                0 => fields.push((key >> 3, Field::Varint(varint(&mut bytes)))),
+                2 => {
+                    let len = varint(&mut bytes) as usize;
+                    fields.push((key >> 3, Field::Bytes(bytes[..len].to_vec())));
+                    bytes = &bytes[len..];
+                }
+                wire_type => panic!("unexpected wire type {}", wire_type),
+            }
+        }
+        fields
+    }
+
+    fn bytes(fields: &[(u64, Field)], field: u64) -> Vec<Vec<u8>> {
+        fields
+            .iter()
+            .filter(|f| f.0 == field)
+            .filter_map(|f| match &f.1 {
+                Field::Bytes(b) => Some(b.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+    fn string(fields: &[(u64, Field)], field: u64) -> Vec<String> {
+        bytes(fields, field)
+            .into_iter()
+            .map(|b| String::from_utf8(b).unwrap())
+            .collect()
+    }
+    fn varints(fields: &[(u64, Field)], field: u64) -> Vec<u64> {
+        fields
+            .iter()
+            .filter(|f| f.0 == field)
+            .filter_map(|f| match f.1 {
+                Field::Varint(v) => Some(v),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A 2D (or lower) tensor
+    #[derive(Debug, Clone)]
+    struct Tensor {
+        shape: Vec<usize>,
+        data: Vec<f64>,
+    }
+
+    impl Tensor {
+        fn rows_cols(&self) -> (usize, usize) {
+            match self.shape.len() {
+                0 => (1, 1),
+                1 => (1, self.shape[0]),
+                _ => (self.shape[0], self.shape[1]),
+            }
+        }
+        fn get(&self, row: usize, col: usize) -> f64 {
+            // Broadcasting of scalars and rows
+            let (rows, cols) = self.rows_cols();
+            self.data[(row % rows) * cols + col % cols]
+        }
+        fn map(&self, f: impl Fn(f64) -> f64) -> Tensor {
+            Tensor {
+                shape: self.shape.clone(),
+                data: self.data.iter().map(|x| f(*x)).collect(),
+            }
+        }
+        fn zip(&self, other: &Tensor, f: impl Fn(f64, f64) -> f64) -> Tensor {
+            let (r1, c1) = self.rows_cols();
+            let (r2, c2) = other.rows_cols();
+            let (rows, cols) = (r1.max(r2), c1.max(c2));
+            let mut data = Vec::new();
+            for row in 0..rows {
+                for col in 0..cols {
+                    data.push(f(self.get(row, col), other.get(row, col)));
+                }
+            }
+            Tensor {
+                shape: vec![rows, cols],
+                data,
+            }
+        }
+    }
+
+    fn run(model: &[u8], input: &[f64]) -> Vec<f64> {
+        let model = decode(model);
+        let graph = decode(&bytes(&model, 7)[0]);
+        let mut values = HashMap::new();
+        values.insert(
+            "input".to_string(),
+            Tensor {
+                shape: vec![1, input.len()],
+                data: input.to_vec(),
+            },
+        );
+        for initializer in bytes(&graph, 5) {
+            let tensor = decode(&initializer);
+            let shape = decode_packed_or_plain(&tensor, 1);
+            let data_type = varints(&tensor, 2)[0];
+            let data = if data_type == super::FLOAT {
+                bytes(&tensor, 4)
+                    .concat()
+                    .chunks(4)
+                    .map(|c| {
+                        f64::from(f32::from_bits(u32::from_le_bytes([c[0], c[1], c[2], c[3]])))
+                    })
+                    .collect()
+            } else {
+                decode_packed_or_plain(&tensor, 7)
+                    .into_iter()
+                    .map(|x| x as f64)
+                    .collect()
+            };
+            values.insert(
+                string(&tensor, 8)[0].clone(),
+                Tensor {
+                    shape: shape.into_iter().map(|x| x as usize).collect(),
+                    data,
+                },
+            );
+        }
+        for node in bytes(&graph, 1) {
+            let node = decode(&node);
+            let inputs = string(&node, 1)
+                .iter()
+                .map(|name| values[name].clone())
+                .collect::<Vec<_>>();
+            let attributes = bytes(&node, 5)
+                .iter()
+                .map(|a| {
+                    let a = decode(a);
+                    (string(&a, 1)[0].clone(), varints(&a, 3)[0] as i64)
+                })
+                .collect::<HashMap<_, _>>();
+            let result = match string(&node, 4)[0].as_str() {
+                "MatMul" => {
+                    let (a, b) = (&inputs[0], &inputs[1]);
+                    let (rows, inner) = a.rows_cols();
+                    let cols = b.rows_cols().1;
+                    let mut data = vec![0.0; rows * cols];
+                    for r in 0..rows {
+                        for c in 0..cols {
+                            for k in 0..inner {
+                                data[r * cols + c] += a.get(r, k) * b.get(k, c);
+                            }
+                        }
+                    }
+                    Tensor {
+                        shape: vec![rows, cols],
+                        data,
+                    }
+                }
+                "Add" => inputs[0].zip(&inputs[1], |a, b| a + b),
+                "Mul" => inputs[0].zip(&inputs[1], |a, b| a * b),
+                "Sigmoid" => inputs[0].map(|x| 1.0 / (1.0 + (-x).exp())),
+                "Tanh" => inputs[0].map(f64::tanh),
+                "Relu" => inputs[0].map(|x| x.max(0.0)),
+                "Identity" => inputs[0].clone(),
+                "Sin" => inputs[0].map(f64::sin),
+                "Abs" => inputs[0].map(f64::abs),
+                "Neg" => inputs[0].map(|x| -x),
+                "Exp" => inputs[0].map(f64::exp),
+                "Concat" => {
+                    assert_eq!(attributes["axis"], 1);
+                    let rows = inputs[0].rows_cols().0;
+                    let cols = inputs.iter().map(|t| t.rows_cols().1).sum::<usize>();
+                    let mut data = Vec::new();
+                    for r in 0..rows {
+                        for t in &inputs {
+                            for c in 0..t.rows_cols().1 {
+                                data.push(t.get(r, c));
+                            }
+                        }
+                    }
+                    Tensor {
+                        shape: vec![rows, cols],
+                        data,
+                    }
+                }
+                "Gather" => {
+                    assert_eq!(attributes["axis"], 1);
+                    let rows = inputs[0].rows_cols().0;
+                    let mut data = Vec::new();
+                    for r in 0..rows {
+                        for index in &inputs[1].data {
+                            data.push(inputs[0].get(r, *index as usize));
+                        }
+                    }
+                    Tensor {
+                        shape: vec![rows, inputs[1].data.len()],
+                        data,
+                    }
+                }
+                op => panic!("unsupported op {}", op),
+            };
+            values.insert(string(&node, 2)[0].clone(), result);
+        }
+        values["output"].data.clone()
+    }
+
+    fn decode_packed_or_plain(fields: &[(u64, Field)], field: u64) -> Vec<i64> {
+        let mut values = varints(fields, field)
+            .into_iter()
+            .map(|x| x as i64)
+            .collect::<Vec<_>>();
+        for packed in bytes(fields, field) {
+            let mut rest = &packed[..];
+            while !rest.is_empty() {
+                let mut value = 0u64;
+                let mut shift = 0;
+                loop {
+                    let byte = rest[0];
+                    rest = &rest[1..];
+                    value |= u64::from(byte & 0x7f) << shift;
+                    shift += 7;
+                    if byte < 0x80 {
+                        break;
+                    }
+                }
+                values.push(value as i64);
+            }
+        }
+        values
+    }
+
+    #[test]
+    fn onnx_graph_should_compute_the_same_as_the_network() {
+        let mut network = NeuralNetwork::with_neurons(7);
+        network.neurons.get_mut(&4).unwrap().bias = 0.3;
+        network.neurons.get_mut(&6).unwrap().bias = -0.4;
+        network.add_connection(0, 4, 1.5);
+        network.add_connection(1, 4, -0.7);
+        network.add_connection(4, 5, 0.9);
+        network.add_connection(5, 2, 2.0);
+        network.add_connection(0, 2, -0.3);
+        network.add_connection(4, 3, 1.1);
+        network.add_connection(6, 3, 0.8);
+        network.add_connection(6, 0, 0.5); // connection into an input
+
+        let onnx = network.to_onnx(2, 2).unwrap();
+        let ctrnn = network.make_network();
+        for input in &[[0.0, 0.0], [0.0, 1.0], [1.0, 0.5], [-2.0, 3.0]] {
+            let mut expected = vec![0.0; 2];
+            ctrnn.activate(input.to_vec(), &mut expected);
+            let output = run(&onnx, input);
+            assert_eq!(output.len(), 2);
+            for (a, b) in expected.iter().zip(&output) {
+                assert!((a - b).abs() < 1e-4, "{:?} != {:?}", expected, output);
+            }
+        }
+    }
+
+    #[test]
+    fn onnx_graph_should_compute_every_activation_like_the_network() {
+        let activations = [
+            Activation::Sigmoid,
+            Activation::Tanh,
+            Activation::Relu,
+            Activation::Identity,
+            Activation::Gaussian,
+            Activation::Sine,
+            Activation::Abs,
+        ];
+        let mut network = NeuralNetwork::with_neurons(4 + activations.len());
+        for (i, activation) in activations.iter().enumerate() {
+            let id = 4 + i;
+            let gene = network.neurons.get_mut(&id).unwrap();
+            gene.activation = *activation;
+            gene.bias = 0.1 * i as f64 - 0.3;
+            network.add_connection(0, id, 0.5 + 0.1 * i as f64);
+            network.add_connection(1, id, -0.4 + 0.05 * i as f64);
+            network.add_connection(id, 2 + i % 2, 0.7 - 0.2 * i as f64);
+        }
+        network.add_connection(4, 5, 0.9);
+        network.add_connection(5, 6, -1.1);
+        network.add_connection(0, 2, 0.3);
+        network.neurons.get_mut(&2).unwrap().activation = Activation::Gaussian;
+        network.neurons.get_mut(&3).unwrap().activation = Activation::Tanh;
+
+        let onnx = network.to_onnx(2, 2).unwrap();
+        let ctrnn = network.make_network();
+        for input in &[[0.0, 0.0], [0.0, 1.0], [1.0, 0.5], [-2.0, 3.0]] {
+            let mut expected = vec![0.0; 2];
+            ctrnn.activate(input.to_vec(), &mut expected);
+            let output = run(&onnx, input);
+            for (a, b) in expected.iter().zip(&output) {
+                assert!((a - b).abs() < 1e-4, "{:?} != {:?}", expected, output);
+            }
+        }
+    }
+
+    #[test]
+    fn recurrent_networks_should_not_be_exported() {
+        let mut network = NeuralNetwork::with_neurons(3);
+        network.add_connection(0, 1, 1.0);
+        network.add_connection(1, 2, 1.0);
+        network.add_connection(2, 1, 1.0);
+        assert!(network.to_onnx(1, 1).is_none());
+    }
+}
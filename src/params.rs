@@ -1,4 +1,8 @@
+use crate::nn::Activation;
 use serde_derive::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::{error, fmt, fs, io};
 
 /// Contains all parameters for the NEAT algorithm. A reference to `NeatParams`
 /// will be passed around internally. Usually you only need to give it to
@@ -39,6 +43,9 @@ pub struct NeatParams {
     pub mutate_add_neuron_pr: f64,
     /// The probability of deleting a neuron during mutation
     pub mutate_del_neuron_pr: f64,
+    /// Only add connections that keep the network acyclic, so that it can be
+    /// evaluated by `make_feed_forward()`
+    pub feed_forward: bool,
 
     /// The mean (normal distribution) of the weight of a new connection
     pub weight_init_mean: f64,
@@ -68,10 +75,48 @@ pub struct NeatParams {
     /// each connection individually) when mutating
     pub bias_replace_pr: f64,
 
+    /// The probability to replace the activation function of a neuron
+    /// (simulated for each neuron individually, except the inputs) when
+    /// mutating
+    pub activation_mutate_pr: f64,
+    /// The activation functions that new neurons and activation mutations
+    /// choose from. Plain NEAT only uses `Sigmoid`, while CPPNs for HyperNEAT
+    /// use several.
+    pub activation_options: Vec<Activation>,
+
+    /// The probability of making a random static connection plastic, with a
+    /// random `HebbianRule`, during mutation
+    pub plastic_connection_pr: f64,
+    /// The probability to perturb the rule of a plastic connection (simulated
+    /// for each connection individually) when mutating
+    pub plasticity_mutate_pr: f64,
+    /// The variance (normal distribution) of new rule coefficients and of
+    /// their mutations
+    pub plasticity_mutate_var: f64,
+    /// The probability that a neuron added during mutation is modulatory
+    pub modulatory_neuron_pr: f64,
+
     /// The probability, during mating, of including a gene that is disjoint or
     /// excess, from the organisms that is least fit
     pub include_weak_disjoint_gene: f64,
 
+    /// How fast the `MutationRates` of each genome evolve: the learning rate
+    /// of their log-normal self-adaptation. With 0, every genome mutates at
+    /// the rates given here.
+    pub rate_adaptation: f64,
+
+    // Complexity control, in `Population`
+    /// Fitness penalty per unit of `Genome::complexity()` (neurons and
+    /// connections), during selection only
+    pub parsimony_coef: f64,
+    /// How much the mean complexity of the population may grow in a
+    /// complexifying phase, before a simplifying phase in which neurons and
+    /// connections are only deleted. 0 never simplifies.
+    pub pruning_threshold: f64,
+    /// Number of generations without a new low of the mean complexity, after
+    /// which a simplifying phase ends
+    pub pruning_generations: usize,
+
     // (TODO: tau and n_steps in `activate()`
 
     // Other
@@ -104,6 +149,7 @@ impl NeatParams {
             mutate_del_conn_pr: 0.5,
             mutate_add_neuron_pr: 0.1,
             mutate_del_neuron_pr: 0.1,
+            feed_forward: false,
 
             weight_init_mean: 0.0,
             weight_init_var: 1.0,
@@ -117,8 +163,22 @@ impl NeatParams {
             bias_mutate_pr: 0.7,
             bias_replace_pr: 0.1,
 
+            activation_mutate_pr: 0.0,
+            activation_options: vec![Activation::Sigmoid],
+
+            plastic_connection_pr: 0.0,
+            plasticity_mutate_pr: 0.0,
+            plasticity_mutate_var: 0.1,
+            modulatory_neuron_pr: 0.0,
+
             include_weak_disjoint_gene: 0.2,
 
+            rate_adaptation: 0.0,
+
+            parsimony_coef: 0.0,
+            pruning_threshold: 0.0,
+            pruning_generations: 10,
+
             // other
             compatibility_threshold: 3.0,
             distance_weight_coef: 0.5,
@@ -143,6 +203,7 @@ impl NeatParams {
             mutate_del_conn_pr: 0.12487973179523451,
             mutate_add_neuron_pr: 0.018564851821478344,
             mutate_del_neuron_pr: 0.03263771379940423,
+            feed_forward: false,
             weight_init_mean: 0.0,
             weight_init_var: 0.9413042884798473,
             weight_mutate_var: 0.8539035934199557,
@@ -153,10 +214,562 @@ impl NeatParams {
             bias_mutate_var: 0.25153760530420227,
             bias_mutate_pr: 0.2568246658042563,
             bias_replace_pr: 0.13720985010407194,
+            activation_mutate_pr: 0.0,
+            activation_options: vec![Activation::Sigmoid],
+            plastic_connection_pr: 0.0,
+            plasticity_mutate_pr: 0.0,
+            plasticity_mutate_var: 0.1,
+            modulatory_neuron_pr: 0.0,
             include_weak_disjoint_gene: 0.2922982738026929,
+            rate_adaptation: 0.0,
+            parsimony_coef: 0.0,
+            pruning_threshold: 0.0,
+            pruning_generations: 10,
             compatibility_threshold: 3.0772944943236347,
             distance_weight_coef: 0.32272770736662426,
             distance_disjoint_coef: 0.7457289806719729,
         }
     }
 }
+
+/// The mutation probabilities and variances of one genome, when they evolve
+/// with it (see `NeatParams::rate_adaptation`). They start at the values of
+/// the `NeatParams`, and replace them when that genome is mutated.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MutationRates {
+    /// See `NeatParams::mutate_add_conn_pr`
+    pub mutate_add_conn_pr: f64,
+    /// See `NeatParams::mutate_del_conn_pr`
+    pub mutate_del_conn_pr: f64,
+    /// See `NeatParams::mutate_add_neuron_pr`
+    pub mutate_add_neuron_pr: f64,
+    /// See `NeatParams::mutate_del_neuron_pr`
+    pub mutate_del_neuron_pr: f64,
+    /// See `NeatParams::weight_mutate_var`
+    pub weight_mutate_var: f64,
+    /// See `NeatParams::weight_mutate_pr`
+    pub weight_mutate_pr: f64,
+    /// See `NeatParams::weight_replace_pr`
+    pub weight_replace_pr: f64,
+    /// See `NeatParams::bias_mutate_var`
+    pub bias_mutate_var: f64,
+    /// See `NeatParams::bias_mutate_pr`
+    pub bias_mutate_pr: f64,
+    /// See `NeatParams::bias_replace_pr`
+    pub bias_replace_pr: f64,
+}
+
+impl MutationRates {
+    /// The rates given by `p`
+    pub fn new(p: &NeatParams) -> MutationRates {
+        MutationRates {
+            mutate_add_conn_pr: p.mutate_add_conn_pr,
+            mutate_del_conn_pr: p.mutate_del_conn_pr,
+            mutate_add_neuron_pr: p.mutate_add_neuron_pr,
+            mutate_del_neuron_pr: p.mutate_del_neuron_pr,
+            weight_mutate_var: p.weight_mutate_var,
+            weight_mutate_pr: p.weight_mutate_pr,
+            weight_replace_pr: p.weight_replace_pr,
+            bias_mutate_var: p.bias_mutate_var,
+            bias_mutate_pr: p.bias_mutate_pr,
+            bias_replace_pr: p.bias_replace_pr,
+        }
+    }
+
+    /// Log-normal self-adaptation, as in evolution strategies: multiply
+    /// every rate by `exp(N(0, learning_rate))`. Probabilities stay at most
+    /// 1, and a rate of 0 stays 0, so a mutation disabled in the
+    /// `NeatParams` is never enabled.
+    pub fn perturb(&mut self, learning_rate: f64) {
+        use rand::distributions::{Distribution, Normal};
+        let mut rng = crate::rng::thread_rng();
+        let distr = Normal::new(0.0, learning_rate);
+        let mut factor = || distr.sample(&mut rng).exp();
+        for pr in &mut [
+            &mut self.mutate_add_conn_pr,
+            &mut self.mutate_del_conn_pr,
+            &mut self.mutate_add_neuron_pr,
+            &mut self.mutate_del_neuron_pr,
+            &mut self.weight_mutate_pr,
+            &mut self.weight_replace_pr,
+            &mut self.bias_mutate_pr,
+            &mut self.bias_replace_pr,
+        ] {
+            **pr = (**pr * factor()).min(1.0);
+        }
+        self.weight_mutate_var *= factor();
+        self.bias_mutate_var *= factor();
+    }
+
+    /// A copy of `p` with these rates
+    pub fn apply(&self, p: &NeatParams) -> NeatParams {
+        NeatParams {
+            mutate_add_conn_pr: self.mutate_add_conn_pr,
+            mutate_del_conn_pr: self.mutate_del_conn_pr,
+            mutate_add_neuron_pr: self.mutate_add_neuron_pr,
+            mutate_del_neuron_pr: self.mutate_del_neuron_pr,
+            weight_mutate_var: self.weight_mutate_var,
+            weight_mutate_pr: self.weight_mutate_pr,
+            weight_replace_pr: self.weight_replace_pr,
+            bias_mutate_var: self.bias_mutate_var,
+            bias_mutate_pr: self.bias_mutate_pr,
+            bias_replace_pr: self.bias_replace_pr,
+            ..p.clone()
+        }
+    }
+}
+
+/// Errors when loading or validating `NeatParams`
+#[derive(Debug)]
+pub enum ParamsError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file is not valid TOML, JSON or INI, or a parameter has the wrong
+    /// type
+    Parse(String),
+    /// A parameter that `NeatParams` does not have
+    UnknownParameter(String),
+    /// A parameter without default value is missing
+    MissingParameter(String),
+    /// Parameters with values out of their range, one description per
+    /// parameter
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamsError::Io(e) => write!(f, "could not read parameters: {}", e),
+            ParamsError::Parse(message) => write!(f, "could not parse parameters: {}", message),
+            ParamsError::UnknownParameter(name) => write!(f, "unknown parameter `{}`", name),
+            ParamsError::MissingParameter(name) => write!(f, "missing parameter `{}`", name),
+            ParamsError::Invalid(problems) => {
+                write!(f, "invalid parameters: {}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl error::Error for ParamsError {}
+
+impl From<io::Error> for ParamsError {
+    fn from(e: io::Error) -> ParamsError {
+        ParamsError::Io(e)
+    }
+}
+
+impl NeatParams {
+    /// Load parameters from a TOML file, or a JSON file if the name ends in
+    /// `.json`. `n_inputs` and `n_outputs` are required, other missing
+    /// parameters take their value from `NeatParams::default()`. The
+    /// parameters are validated.
+    #[cfg(feature = "evolution")]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<NeatParams, ParamsError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        if path.extension().is_some_and(|e| e == "json") {
+            NeatParams::from_json(&text)
+        } else {
+            NeatParams::from_toml(&text)
+        }
+    }
+
+    /// Parse parameters from TOML, see `from_file()`
+    #[cfg(feature = "evolution")]
+    pub fn from_toml(text: &str) -> Result<NeatParams, ParamsError> {
+        let value = toml::from_str(text).map_err(|e| ParamsError::Parse(e.to_string()))?;
+        NeatParams::from_value(value)
+    }
+
+    /// Parse parameters from JSON, see `from_file()`
+    pub fn from_json(text: &str) -> Result<NeatParams, ParamsError> {
+        let value = serde_json::from_str(text).map_err(|e| ParamsError::Parse(e.to_string()))?;
+        NeatParams::from_value(value)
+    }
+
+    /// The default parameters, with the ones in the table `value` replaced
+    fn from_value(value: Value) -> Result<NeatParams, ParamsError> {
+        let overrides = match value {
+            Value::Object(overrides) => overrides,
+            _ => {
+                return Err(ParamsError::Parse(
+                    "expected a table of parameters".to_string(),
+                ))
+            }
+        };
+        let size = |name: &str| {
+            overrides
+                .get(name)
+                .and_then(Value::as_u64)
+                .map(|n| n as usize)
+                .ok_or_else(|| ParamsError::MissingParameter(name.to_string()))
+        };
+        NeatParams::default(size("n_inputs")?, size("n_outputs")?).with_overrides(&overrides)
+    }
+
+    /// A copy with the parameters named in `overrides` replaced, e.g. from
+    /// `{"mutation_pr": 0.8}`, and validated
+    pub fn with_overrides(
+        &self,
+        overrides: &Map<String, Value>,
+    ) -> Result<NeatParams, ParamsError> {
+        let mut params = serde_json::to_value(self).unwrap();
+        for (name, value) in overrides {
+            match params.get_mut(name) {
+                Some(param) => *param = value.clone(),
+                None => return Err(ParamsError::UnknownParameter(name.clone())),
+            }
+        }
+        let params: NeatParams =
+            serde_json::from_value(params).map_err(|e| ParamsError::Parse(e.to_string()))?;
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Check that every parameter is within its range: probabilities and
+    /// fractions between 0 and 1, variances and coefficients not negative,
+    /// and at least one activation function. `species_elite` may be 0, in
+    /// which case every species can be removed for stagnating.
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        let mut problems = Vec::new();
+        let probabilities = [
+            ("mutation_pr", self.mutation_pr),
+            ("interspecie_mate_pr", self.interspecie_mate_pr),
+            ("cull_fraction", self.cull_fraction),
+            ("mutate_add_conn_pr", self.mutate_add_conn_pr),
+            ("mutate_del_conn_pr", self.mutate_del_conn_pr),
+            ("mutate_add_neuron_pr", self.mutate_add_neuron_pr),
+            ("mutate_del_neuron_pr", self.mutate_del_neuron_pr),
+            ("weight_mutate_pr", self.weight_mutate_pr),
+            ("weight_replace_pr", self.weight_replace_pr),
+            ("bias_mutate_pr", self.bias_mutate_pr),
+            ("bias_replace_pr", self.bias_replace_pr),
+            ("activation_mutate_pr", self.activation_mutate_pr),
+            ("plastic_connection_pr", self.plastic_connection_pr),
+            ("plasticity_mutate_pr", self.plasticity_mutate_pr),
+            ("modulatory_neuron_pr", self.modulatory_neuron_pr),
+            (
+                "include_weak_disjoint_gene",
+                self.include_weak_disjoint_gene,
+            ),
+        ];
+        for (name, value) in probabilities.iter() {
+            if !(0.0..=1.0).contains(value) {
+                problems.push(format!(
+                    "{} is {}, but must be between 0 and 1",
+                    name, value
+                ));
+            }
+        }
+        let non_negative = [
+            ("weight_init_var", self.weight_init_var),
+            ("weight_mutate_var", self.weight_mutate_var),
+            ("bias_init_var", self.bias_init_var),
+            ("bias_mutate_var", self.bias_mutate_var),
+            ("plasticity_mutate_var", self.plasticity_mutate_var),
+            ("rate_adaptation", self.rate_adaptation),
+            ("parsimony_coef", self.parsimony_coef),
+            ("pruning_threshold", self.pruning_threshold),
+            ("compatibility_threshold", self.compatibility_threshold),
+            ("distance_weight_coef", self.distance_weight_coef),
+            ("distance_disjoint_coef", self.distance_disjoint_coef),
+        ];
+        for (name, value) in non_negative.iter() {
+            if value.is_nan() || *value < 0.0 || value.is_infinite() {
+                problems.push(format!("{} is {}, but must not be negative", name, value));
+            }
+        }
+        for (name, value) in [
+            ("weight_init_mean", self.weight_init_mean),
+            ("bias_init_mean", self.bias_init_mean),
+        ]
+        .iter()
+        {
+            if !value.is_finite() {
+                problems.push(format!("{} is {}, but must be finite", name, value));
+            }
+        }
+        if self.activation_options.is_empty() {
+            problems.push("activation_options is empty".to_string());
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ParamsError::Invalid(problems))
+        }
+    }
+
+    /// Load parameters from a neat-python config file, see
+    /// `from_neat_python()`
+    pub fn from_neat_python_file<P: AsRef<Path>>(path: P) -> Result<NeatParams, ParamsError> {
+        NeatParams::from_neat_python(&fs::read_to_string(path)?)
+    }
+
+    /// Convert a neat-python config (INI format) to parameters, to port
+    /// experiments. The mapped settings are:
+    ///
+    /// * `num_inputs`, `num_outputs`
+    /// * `conn_add_prob`, `conn_delete_prob`, `node_add_prob`,
+    ///   `node_delete_prob`, `feed_forward`
+    /// * `weight_*` and `bias_*`: `init_mean`, `init_stdev`, `mutate_power`,
+    ///   `mutate_rate` and `replace_rate`
+    /// * `activation_default`, `activation_options`, `activation_mutate_rate`
+    /// * `compatibility_threshold`, `compatibility_weight_coefficient`,
+    ///   `compatibility_disjoint_coefficient`
+    /// * `max_stagnation`, `species_elitism`, and `survival_threshold`,
+    ///   which keeps the fraction not culled
+    ///
+    /// The rest, like the population size, the fitness threshold or the value
+    /// bounds, has no counterpart and is ignored. Distances are not
+    /// normalized by genome size like in neat-python, so the compatibility
+    /// threshold may need tuning. Other parameters are the defaults.
+    pub fn from_neat_python(text: &str) -> Result<NeatParams, ParamsError> {
+        let mut settings = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with(';')
+                || line.starts_with('[')
+            {
+                continue;
+            }
+            let separator = line.find(['=', ':']).ok_or_else(|| {
+                ParamsError::Parse(format!("line {}: expected `key = value`", i + 1))
+            })?;
+            let key = line[..separator].trim();
+            let value = line[separator + 1..].trim();
+            settings.push((i + 1, key, value));
+        }
+
+        let mut params = Map::new();
+        let mut activation_default = None;
+        let mut activation_options = None;
+        for (line, key, value) in settings {
+            let number = || {
+                value.parse::<f64>().map_err(|_| {
+                    ParamsError::Parse(format!("line {}: `{}` is not a number", line, value))
+                })
+            };
+            let name = match key {
+                "num_inputs" => "n_inputs",
+                "num_outputs" => "n_outputs",
+                "max_stagnation" => "remove_after_n_generations",
+                "species_elitism" => "species_elite",
+                "conn_add_prob" => "mutate_add_conn_pr",
+                "conn_delete_prob" => "mutate_del_conn_pr",
+                "node_add_prob" => "mutate_add_neuron_pr",
+                "node_delete_prob" => "mutate_del_neuron_pr",
+                "weight_init_mean" => "weight_init_mean",
+                "weight_init_stdev" => "weight_init_var",
+                "weight_mutate_power" => "weight_mutate_var",
+                "weight_mutate_rate" => "weight_mutate_pr",
+                "weight_replace_rate" => "weight_replace_pr",
+                "bias_init_mean" => "bias_init_mean",
+                "bias_init_stdev" => "bias_init_var",
+                "bias_mutate_power" => "bias_mutate_var",
+                "bias_mutate_rate" => "bias_mutate_pr",
+                "bias_replace_rate" => "bias_replace_pr",
+                "activation_mutate_rate" => "activation_mutate_pr",
+                "compatibility_threshold" => "compatibility_threshold",
+                "compatibility_weight_coefficient" => "distance_weight_coef",
+                "compatibility_disjoint_coefficient" => "distance_disjoint_coef",
+                "survival_threshold" => {
+                    params.insert("cull_fraction".to_string(), Value::from(1.0 - number()?));
+                    continue;
+                }
+                "feed_forward" => {
+                    let feed_forward = match value.to_lowercase().as_str() {
+                        "true" | "1" => true,
+                        "false" | "0" => false,
+                        _ => {
+                            return Err(ParamsError::Parse(format!(
+                                "line {}: `{}` is not a boolean",
+                                line, value
+                            )))
+                        }
+                    };
+                    params.insert("feed_forward".to_string(), Value::from(feed_forward));
+                    continue;
+                }
+                "activation_default" => {
+                    activation_default = Some((line, value));
+                    continue;
+                }
+                "activation_options" => {
+                    activation_options = Some((line, value));
+                    continue;
+                }
+                _ => continue,
+            };
+            let value = match name {
+                "n_inputs" | "n_outputs" | "remove_after_n_generations" | "species_elite" => {
+                    Value::from(value.parse::<u64>().map_err(|_| {
+                        ParamsError::Parse(format!("line {}: `{}` is not a count", line, value))
+                    })?)
+                }
+                _ => Value::from(number()?),
+            };
+            params.insert(name.to_string(), value);
+        }
+
+        // New neurons get the default activation, unless it is "random", and
+        // mutations pick from the options. Both use `activation_options` here.
+        let mutate_activations = params
+            .get("activation_mutate_pr")
+            .and_then(Value::as_f64)
+            .is_some_and(|pr| pr > 0.0);
+        let activations = match (activation_default, activation_options) {
+            (_, Some(options)) if mutate_activations => Some(options),
+            (Some((_, "random")), options) => options,
+            (Some(default), _) => Some(default),
+            (None, options) => options,
+        };
+        if let Some((line, names)) = activations {
+            let activations = names
+                .split_whitespace()
+                .map(|name| {
+                    neat_python_activation(name).ok_or_else(|| {
+                        ParamsError::Parse(format!(
+                            "line {}: unsupported activation `{}`",
+                            line, name
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            params.insert(
+                "activation_options".to_string(),
+                serde_json::to_value(activations).unwrap(),
+            );
+        }
+        NeatParams::from_value(Value::Object(params))
+    }
+}
+
+/// The activation called `name` in neat-python
+fn neat_python_activation(name: &str) -> Option<Activation> {
+    Some(match name {
+        "sigmoid" => Activation::Sigmoid,
+        "tanh" => Activation::Tanh,
+        "relu" => Activation::Relu,
+        "identity" => Activation::Identity,
+        "gauss" => Activation::Gaussian,
+        "sin" => Activation::Sine,
+        "abs" => Activation::Abs,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "evolution")]
+    fn missing_parameters_should_take_default_values() {
+        let params =
+            NeatParams::from_toml("n_inputs = 3\nn_outputs = 2\nmutation_pr = 0.9").unwrap();
+        assert_eq!(params.n_inputs, 3);
+        assert_eq!(params.mutation_pr, 0.9);
+        assert_eq!(
+            params.cull_fraction,
+            NeatParams::default(3, 2).cull_fraction
+        );
+
+        let params = NeatParams::from_json(r#"{"n_inputs": 1, "n_outputs": 1}"#).unwrap();
+        assert_eq!(
+            params.species_elite,
+            NeatParams::default(1, 1).species_elite
+        );
+
+        match NeatParams::from_toml("n_inputs = 3") {
+            Err(ParamsError::MissingParameter(name)) => assert_eq!(name, "n_outputs"),
+            other => panic!("{:?}", other),
+        }
+        match NeatParams::from_toml("n_inputs = 1\nn_outputs = 1\nmutation_p = 0.9") {
+            Err(ParamsError::UnknownParameter(name)) => assert_eq!(name, "mutation_p"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate_should_report_every_parameter_out_of_range() {
+        assert!(NeatParams::default(2, 1).validate().is_ok());
+        let mut params = NeatParams::default(2, 1);
+        params.weight_mutate_var = -0.5;
+        params.mutation_pr = 1.5;
+        params.activation_options.clear();
+        match params.validate() {
+            Err(ParamsError::Invalid(problems)) => {
+                assert_eq!(problems.len(), 3);
+                assert!(problems[0].starts_with("mutation_pr is 1.5"));
+                assert!(problems[1].starts_with("weight_mutate_var is -0.5"));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn neat_python_config_should_be_imported() {
+        let config = "
+# neat-python XOR example
+[NEAT]
+fitness_criterion     = max
+fitness_threshold     = 3.9
+pop_size              = 150
+
+[DefaultGenome]
+feed_forward            = True
+activation_default      = sigmoid
+activation_mutate_rate  = 0.0
+activation_options      = sigmoid
+bias_init_mean          = 0.0
+bias_init_stdev         = 1.0
+bias_mutate_power       = 0.5
+bias_mutate_rate        = 0.7
+bias_replace_rate       = 0.1
+compatibility_disjoint_coefficient = 1.0
+compatibility_weight_coefficient   = 0.5
+conn_add_prob           = 0.5
+conn_delete_prob        = 0.5
+node_add_prob           = 0.2
+node_delete_prob        = 0.2
+num_inputs              = 2
+num_outputs             = 1
+weight_mutate_power     = 0.5
+weight_mutate_rate      = 0.8
+
+[DefaultSpeciesSet]
+compatibility_threshold = 3.0
+
+[DefaultStagnation]
+max_stagnation       = 20
+species_elitism      = 0
+
+[DefaultReproduction]
+survival_threshold = 0.2
+";
+        let params = NeatParams::from_neat_python(config).unwrap();
+        assert_eq!((params.n_inputs, params.n_outputs), (2, 1));
+        assert_eq!(params.mutate_add_neuron_pr, 0.2);
+        assert_eq!(params.species_elite, 0);
+        assert!(params.feed_forward);
+        assert!((params.cull_fraction - 0.8).abs() < 1e-12);
+        assert_eq!(params.activation_options, vec![Activation::Sigmoid]);
+
+        let config = config
+            .replace(
+                "activation_mutate_rate  = 0.0",
+                "activation_mutate_rate = 0.1",
+            )
+            .replace(
+                "activation_options      = sigmoid",
+                "activation_options = tanh gauss",
+            );
+        let params = NeatParams::from_neat_python(&config).unwrap();
+        assert_eq!(
+            params.activation_options,
+            vec![Activation::Tanh, Activation::Gaussian]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{Genome, Organism, Specie};
+use indexmap::map::IndexMap;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// A mutation operation applied to a genome. Recorded in `Organism::mutations`
+/// so that the structure of an organism can be traced back through its
+/// ancestors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Mutation {
+    /// A connection was added (or had its weight reset, if it already existed)
+    AddConnection {
+        /// Input neuron of the connection
+        in_neuron_id: usize,
+        /// Output neuron of the connection
+        out_neuron_id: usize,
+    },
+    /// A connection was removed
+    DeleteConnection {
+        /// Input neuron of the connection
+        in_neuron_id: usize,
+        /// Output neuron of the connection
+        out_neuron_id: usize,
+    },
+    /// A neuron was added, splitting an existing connection if there was any
+    AddNeuron {
+        /// Id of the new neuron
+        neuron_id: usize,
+    },
+    /// A neuron was removed together with its connections
+    DeleteNeuron {
+        /// Id of the removed neuron
+        neuron_id: usize,
+    },
+    /// Weights of `count` connections were perturbed or replaced
+    Weights {
+        /// Number of connections affected
+        count: usize,
+    },
+    /// Biases of `count` neurons were perturbed or replaced
+    Biases {
+        /// Number of neurons affected
+        count: usize,
+    },
+    /// Activation functions of `count` neurons were replaced
+    Activations {
+        /// Number of neurons affected
+        count: usize,
+    },
+    /// Connections were made plastic, or had their plasticity rule perturbed
+    Plasticity {
+        /// Number of connections affected
+        count: usize,
+    },
+    /// A mutation of a genome that does not report its mutations (see
+    /// `Genome::mutate_tracked`)
+    Unspecified,
+}
+
+/// What is known about an organism that has been part of the population.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrganismRecord {
+    /// Id of the organism
+    pub id: usize,
+    /// Parents of the organism, fittest first
+    pub parents: Vec<usize>,
+    /// Mutations applied after mating
+    pub mutations: Vec<Mutation>,
+    /// Generation in which the organism was born
+    pub generation: usize,
+    /// Species the organism was evaluated in
+    pub species: usize,
+    /// Fitness of the organism, the last time it was evaluated. `None` until
+    /// the organism is first evaluated.
+    pub fitness: Option<f64>,
+}
+
+/// What is known about a species that has been part of the population.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeciesRecord {
+    /// Id of the species
+    pub id: usize,
+    /// The species that the founding organism of this species was bred in
+    pub parent: Option<usize>,
+    /// Generation in which the species appeared
+    pub birth_generation: usize,
+    /// Generation in which the species disappeared, if it has
+    pub extinction_generation: Option<usize>,
+}
+
+/// Archive of every organism and species that has existed in a `Population`
+/// since phylogeny tracking was enabled with `Population::enable_phylogeny`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Phylogeny {
+    /// All organisms, indexed by id
+    pub organisms: IndexMap<usize, OrganismRecord>,
+    /// All species, indexed by id
+    pub species: IndexMap<usize, SpeciesRecord>,
+}
+
+impl Phylogeny {
+    /// Create an empty phylogeny.
+    pub fn new() -> Phylogeny {
+        Phylogeny::default()
+    }
+
+    /// Record (or update the fitness of) an organism evaluated in `specie`.
+    pub fn record_organism<G: Genome>(&mut self, organism: &Organism<G>, specie: usize) {
+        self.record(organism, specie, Some(organism.fitness));
+    }
+    /// Record an organism that has not been evaluated yet
+    #[cfg(feature = "evolution")]
+    pub(crate) fn record_unevaluated<G: Genome>(&mut self, organism: &Organism<G>, specie: usize) {
+        self.record(organism, specie, None);
+    }
+    fn record<G: Genome>(&mut self, organism: &Organism<G>, specie: usize, fitness: Option<f64>) {
+        self.organisms.insert(
+            organism.id,
+            OrganismRecord {
+                id: organism.id,
+                parents: organism.parents.clone(),
+                mutations: organism.mutations.clone(),
+                generation: organism.generation,
+                species: specie,
+                fitness,
+            },
+        );
+    }
+
+    /// Record a species, or update its extinction generation.
+    pub fn record_species<G: Genome>(&mut self, specie: &Specie<G>) {
+        self.species.insert(
+            specie.id,
+            SpeciesRecord {
+                id: specie.id,
+                parent: specie.parent_id,
+                birth_generation: specie.birth_generation,
+                extinction_generation: specie.extinction_generation,
+            },
+        );
+    }
+
+    /// The ancestry of an organism following the fittest parent, starting with
+    /// the organism itself and ending with an organism of the initial
+    /// population (or the oldest one recorded).
+    pub fn lineage(&self, id: usize) -> Vec<&OrganismRecord> {
+        let mut lineage = Vec::new();
+        let mut current = self.organisms.get(&id);
+        while let Some(record) = current {
+            lineage.push(record);
+            current = record
+                .parents
+                .first()
+                .filter(|parent| **parent != record.id)
+                .and_then(|parent| self.organisms.get(parent));
+        }
+        lineage
+    }
+
+    /// Export the organism tree as JSON. Contains every recorded organism and
+    /// species, including both parents of each organism.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Export the organism tree in Newick format. Since organisms have two
+    /// parents, only the fittest parent is used as the parent in the tree.
+    /// Nodes are labelled with the organism id, and branch lengths are the
+    /// number of generations between parent and child.
+    pub fn to_newick(&self) -> String {
+        let edges = self
+            .organisms
+            .values()
+            .map(|o| {
+                let parent = o
+                    .parents
+                    .first()
+                    .and_then(|parent| self.organisms.get(parent))
+                    .filter(|parent| parent.id != o.id);
+                (
+                    o.id,
+                    parent.map(|p| p.id),
+                    parent.map_or(0, |p| o.generation - p.generation),
+                )
+            })
+            .collect::<Vec<_>>();
+        newick(&edges)
+    }
+
+    /// Export the species tree in Newick format. Nodes are labelled with the
+    /// species id, and branch lengths are the number of generations between
+    /// the birth of the parent species and the birth of the child species.
+    pub fn species_to_newick(&self) -> String {
+        let edges = self
+            .species
+            .values()
+            .map(|s| {
+                let parent = s
+                    .parent
+                    .and_then(|parent| self.species.get(&parent))
+                    .filter(|parent| parent.id != s.id);
+                (
+                    s.id,
+                    parent.map(|p| p.id),
+                    parent.map_or(0, |p| s.birth_generation - p.birth_generation),
+                )
+            })
+            .collect::<Vec<_>>();
+        newick(&edges)
+    }
+}
+
+/// Write a forest given as `(id, parent, branch length)` in Newick format.
+/// Several roots are joined under an unlabelled root. Iterative, since
+/// lineages can be as deep as the number of generations.
+fn newick(edges: &[(usize, Option<usize>, usize)]) -> String {
+    let mut children: IndexMap<usize, Vec<usize>> = IndexMap::new();
+    let mut lengths = IndexMap::new();
+    let mut roots = Vec::new();
+    for &(id, parent, length) in edges {
+        lengths.insert(id, length);
+        children.entry(id).or_default();
+        match parent {
+            Some(parent) => children.entry(parent).or_default().push(id),
+            None => roots.push(id),
+        }
+    }
+
+    enum Visit {
+        Enter(usize),
+        Exit(usize),
+        Separator,
+    }
+    let mut out = String::new();
+    let mut stack = Vec::new();
+    for (i, root) in roots.iter().enumerate().rev() {
+        stack.push(Visit::Enter(*root));
+        if i > 0 {
+            stack.push(Visit::Separator);
+        }
+    }
+    if roots.len() > 1 {
+        out.push('(');
+    }
+    while let Some(visit) = stack.pop() {
+        match visit {
+            Visit::Enter(id) => {
+                let node_children = &children[&id];
+                stack.push(Visit::Exit(id));
+                if !node_children.is_empty() {
+                    out.push('(');
+                    for (i, child) in node_children.iter().enumerate().rev() {
+                        stack.push(Visit::Enter(*child));
+                        if i > 0 {
+                            stack.push(Visit::Separator);
+                        }
+                    }
+                }
+            }
+            Visit::Exit(id) => {
+                if !children[&id].is_empty() {
+                    out.push(')');
+                }
+                write!(out, "{}:{}", id, lengths[&id]).unwrap();
+            }
+            Visit::Separator => out.push(','),
+        }
+    }
+    if roots.len() > 1 {
+        out.push(')');
+    }
+    out.push(';');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::newick;
+
+    #[test]
+    fn newick_should_nest_children_under_parents() {
+        let edges = vec![
+            (0, None, 0),
+            (1, Some(0), 1),
+            (2, Some(0), 2),
+            (3, Some(1), 1),
+        ];
+        assert_eq!(newick(&edges), "((3:1)1:1,2:2)0:0;");
+    }
+
+    #[test]
+    fn newick_should_join_several_roots() {
+        let edges = vec![(0, None, 0), (1, None, 0), (2, Some(1), 1)];
+        assert_eq!(newick(&edges), "(0:0,(2:1)1:0);");
+    }
+}
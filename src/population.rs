@@ -1,7 +1,8 @@
-use crate::{Environment, Genome, NeatParams, NeuralNetwork, Organism, Specie};
+use crate::{Environment, Genome, NeatParams, NeuralNetwork, Organism, Phylogeny, Specie};
 use rayon::prelude::*;
 // use std::cmp::Ordering::*;
 use rand::distributions::{Distribution, Uniform};
+use serde_derive::{Deserialize, Serialize};
 use std::f64;
 
 #[cfg(feature = "telemetry")]
@@ -10,8 +11,70 @@ use rusty_dashed;
 #[cfg(feature = "telemetry")]
 use serde_json;
 
+/// Whether a population is growing or shrinking its genomes, with phased
+/// pruning (see `NeatParams::pruning_threshold`)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Phase {
+    /// Genomes mutate with the given `NeatParams`
+    Complexifying,
+    /// Neurons and connections are deleted but never added
+    Simplifying,
+}
+
+/// The mean complexity of the genomes during one phase of a population. With
+/// phased pruning disabled, a run is a single complexifying phase.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseStats {
+    /// Whether the population complexified or simplified
+    pub phase: Phase,
+    /// The first generation of the phase
+    pub first_generation: usize,
+    /// The last generation of the phase so far
+    pub last_generation: usize,
+    /// Mean `Genome::complexity()` at the first generation
+    pub start_complexity: f64,
+    /// Mean complexity at the last generation
+    pub end_complexity: f64,
+    /// Lowest mean complexity in the phase
+    pub min_complexity: f64,
+    /// The generation with the lowest mean complexity
+    pub min_generation: usize,
+    /// Highest mean complexity in the phase
+    pub max_complexity: f64,
+    /// Best fitness in the phase
+    pub best_fitness: f64,
+}
+
+impl PhaseStats {
+    fn new(phase: Phase, generation: usize, complexity: f64, fitness: f64) -> PhaseStats {
+        PhaseStats {
+            phase,
+            first_generation: generation,
+            last_generation: generation,
+            start_complexity: complexity,
+            end_complexity: complexity,
+            min_complexity: complexity,
+            min_generation: generation,
+            max_complexity: complexity,
+            best_fitness: fitness,
+        }
+    }
+
+    fn update(&mut self, generation: usize, complexity: f64, fitness: f64) {
+        self.last_generation = generation;
+        self.end_complexity = complexity;
+        if complexity < self.min_complexity {
+            self.min_complexity = complexity;
+            self.min_generation = generation;
+        }
+        self.max_complexity = self.max_complexity.max(complexity);
+        self.best_fitness = self.best_fitness.max(fitness);
+    }
+}
+
 /// Contains several species, and a way to evolve these to the next generation.
-#[derive(Debug)]
+/// It can be serialized, to save a run and continue it later.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Population<G: Genome = NeuralNetwork> {
     /// container of species
     pub species: Vec<Specie<G>>,
@@ -23,6 +86,15 @@ pub struct Population<G: Genome = NeuralNetwork> {
     /// To give each species a unique id. Useful for for example visualizing or
     /// processing the species.
     species_id: usize,
+    /// To give each organism a unique id, for lineage tracking.
+    organism_id: usize,
+    /// Number of calls to `evolve()` so far
+    generation: usize,
+    /// Record of all organisms and species, if enabled
+    phylogeny: Option<Phylogeny>,
+    /// Complexity of the genomes in each phase so far, the current one last
+    #[serde(default)]
+    phases: Vec<PhaseStats>,
 }
 
 impl<G: Genome> Population<G> {
@@ -36,7 +108,9 @@ impl<G: Genome> Population<G> {
     pub fn create_population_from(genome: G, population_size: usize) -> Population<G> {
         let mut organisms = Vec::new();
         while organisms.len() < population_size {
-            organisms.push(Organism::new(genome.clone()));
+            let mut organism = Organism::new(genome.clone());
+            organism.id = organisms.len();
+            organisms.push(organism);
         }
 
         let mut specie = Specie::new(organisms.first().unwrap().clone(), 0);
@@ -48,6 +122,10 @@ impl<G: Genome> Population<G> {
             generations_without_improvements: 0,
             innovation_id: 0,
             species_id: 1,
+            organism_id: population_size,
+            generation: 0,
+            phylogeny: None,
+            phases: Vec::new(),
         }
     }
 
@@ -86,6 +164,55 @@ impl<G: Genome> Population<G> {
     pub fn generations_without_improvements(&self) -> usize {
         self.generations_without_improvements
     }
+    /// The current generation, i.e. the number of calls to `evolve()`
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Mean `Genome::complexity()` of the organisms
+    pub fn mean_complexity(&self) -> f64 {
+        let total = self
+            .get_organisms()
+            .map(|organism| organism.genome.complexity())
+            .sum::<usize>();
+        total as f64 / self.size().max(1) as f64
+    }
+    /// The current phase of phased pruning
+    pub fn phase(&self) -> Phase {
+        self.phases
+            .last()
+            .map_or(Phase::Complexifying, |stats| stats.phase)
+    }
+    /// Complexity of the genomes in every phase so far, the current one last.
+    /// Updated at the start of every generation, so it does not include the
+    /// last one yet.
+    pub fn phase_stats(&self) -> &[PhaseStats] {
+        &self.phases
+    }
+
+    /// Start recording every organism and species in a `Phylogeny`, starting
+    /// with the current ones. Disabled by default, since the record grows with
+    /// every generation.
+    pub fn enable_phylogeny(&mut self) {
+        let mut phylogeny = Phylogeny::new();
+        for specie in &self.species {
+            phylogeny.record_species(specie);
+            for organism in &specie.organisms {
+                // The initial organisms are bred from before being evaluated
+                if self.generation == 0 {
+                    phylogeny.record_unevaluated(organism, specie.id);
+                } else {
+                    phylogeny.record_organism(organism, specie.id);
+                }
+            }
+        }
+        self.phylogeny = Some(phylogeny);
+    }
+    /// The record of organisms and species, if enabled with
+    /// `enable_phylogeny()`
+    pub fn phylogeny(&self) -> Option<&Phylogeny> {
+        self.phylogeny.as_ref()
+    }
 
     /// Evolve to the next generation. This includes, in order:
     /// * Collecting all organisms and dividing them into (new) species
@@ -97,8 +224,35 @@ impl<G: Genome> Population<G> {
     /// Because of the last step, organisms will always have an up-to-date
     /// fitness value.
     pub fn evolve(&mut self, env: &mut Environment<G>, p: &NeatParams, in_parallel: bool) {
+        self.reproduce(p);
+        self.evaluate(env, in_parallel);
+    }
+
+    /// First half of `evolve()`: speciate and replace the organisms with
+    /// their offspring, which are not evaluated yet.
+    pub(crate) fn reproduce(&mut self, p: &NeatParams) {
+        self.update_phase(p);
+        let simplifying;
+        let p = match self.phase() {
+            Phase::Complexifying => p,
+            Phase::Simplifying => {
+                simplifying = Self::simplifying(p);
+                &simplifying
+            }
+        };
+        self.generation += 1;
+
         // Collect all organisms
-        let organisms = self.get_organisms().cloned().collect::<Vec<_>>();
+        let mut organisms = self.get_organisms().cloned().collect::<Vec<_>>();
+
+        // Selection sees the fitness with the parsimony penalty, until the
+        // offspring are evaluated
+        if p.parsimony_coef > 0.0 {
+            for organism in &mut organisms {
+                let penalty = p.parsimony_coef * organism.genome.complexity() as f64;
+                organism.fitness = (organism.fitness - penalty).max(0.0);
+            }
+        }
 
         // Divide into species
         self.speciate(&organisms, p);
@@ -144,25 +298,43 @@ impl<G: Genome> Population<G> {
         );
 
         for (species, n_offspring) in self.species.iter_mut().zip(n_offspring) {
-            species.generate_offspring(n_offspring, &organisms, &mut self.innovation_id, p);
+            species.generate_offspring(
+                n_offspring,
+                &organisms,
+                &mut self.innovation_id,
+                &mut self.organism_id,
+                self.generation,
+                p,
+            );
         }
+    }
 
+    /// Second half of `evolve()`: evaluate the fitness of all organisms, and
+    /// record them in the phylogeny, updating the fitness of those that were
+    /// already there.
+    pub(crate) fn evaluate(&mut self, env: &dyn Environment<G>, in_parallel: bool) {
         if in_parallel {
             // Evaluate the fitness of all organisms, in parallel
-            self.species.par_iter_mut().for_each(|species| {
-                species.organisms.par_iter_mut().for_each(|organism| {
-                    organism.fitness = env.test(&mut organism.genome);
+            let streams = crate::rng::streams();
+            self.species
+                .iter_mut()
+                .flat_map(|species| species.organisms.iter_mut())
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .enumerate()
+                .for_each(|(i, organism)| {
+                    organism.fitness =
+                        streams.run(i, || crate::environment::fitness(env, &mut organism.genome));
                     if organism.fitness < 0.0 {
                         eprintln!("Fitness {} < 0.0", organism.fitness);
                         std::process::exit(1);
                     }
                 })
-            })
         } else {
             // Evaluate the fitness of all organisms
             self.species.iter_mut().for_each(|species| {
                 species.organisms.iter_mut().for_each(|organism| {
-                    organism.fitness = env.test(&mut organism.genome);
+                    organism.fitness = crate::environment::fitness(env, &mut organism.genome);
                     if organism.fitness < 0.0 {
                         eprintln!("Fitness {} < 0.0", organism.fitness);
                         std::process::exit(1);
@@ -170,6 +342,89 @@ impl<G: Genome> Population<G> {
                 })
             })
         }
+
+        if let Some(ref mut phylogeny) = self.phylogeny {
+            for specie in &self.species {
+                for organism in &specie.organisms {
+                    phylogeny.record_organism(organism, specie.id);
+                }
+            }
+        }
+    }
+
+    /// Record the organisms with the given ids in the phylogeny again, after
+    /// their fitness or genome changed outside of `evaluate()`
+    pub(crate) fn record_organisms(&mut self, ids: &std::collections::HashSet<usize>) {
+        if let Some(ref mut phylogeny) = self.phylogeny {
+            for specie in &self.species {
+                for organism in &specie.organisms {
+                    if ids.contains(&organism.id) {
+                        phylogeny.record_organism(organism, specie.id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Helper of `reproduce`. Record the complexity of the organisms of the
+    /// current generation, and switch phase: to simplifying when the mean
+    /// complexity grew more than `pruning_threshold` in the complexifying
+    /// phase, and back when it has not reached a new low for
+    /// `pruning_generations`.
+    fn update_phase(&mut self, p: &NeatParams) {
+        let generation = self.generation;
+        let complexity = self.mean_complexity();
+        let fitness = self
+            .get_organisms()
+            .map(|organism| organism.fitness)
+            .fold(0.0, f64::max);
+        let stats = match self.phases.last_mut() {
+            Some(stats) => {
+                stats.update(generation, complexity, fitness);
+                stats
+            }
+            None => {
+                self.phases.push(PhaseStats::new(
+                    Phase::Complexifying,
+                    generation,
+                    complexity,
+                    fitness,
+                ));
+                return;
+            }
+        };
+        let next = match stats.phase {
+            Phase::Complexifying
+                if p.pruning_threshold > 0.0
+                    && complexity > stats.start_complexity + p.pruning_threshold =>
+            {
+                Phase::Simplifying
+            }
+            Phase::Simplifying
+                if p.pruning_threshold == 0.0
+                    || generation - stats.min_generation >= p.pruning_generations =>
+            {
+                Phase::Complexifying
+            }
+            _ => return,
+        };
+        self.phases
+            .push(PhaseStats::new(next, generation, complexity, fitness));
+    }
+
+    /// The parameters of a simplifying phase: connections and neurons are
+    /// deleted as often as they were added or deleted when complexifying, and
+    /// never added. The mutation rates of genomes, if they evolve, are left
+    /// alone.
+    fn simplifying(p: &NeatParams) -> NeatParams {
+        NeatParams {
+            mutate_add_conn_pr: 0.0,
+            mutate_del_conn_pr: p.mutate_add_conn_pr.max(p.mutate_del_conn_pr),
+            mutate_add_neuron_pr: 0.0,
+            mutate_del_neuron_pr: p.mutate_add_neuron_pr.max(p.mutate_del_neuron_pr),
+            rate_adaptation: 0.0,
+            ..p.clone()
+        }
     }
 
     // fn determine_new_species_sizes()
@@ -180,7 +435,7 @@ impl<G: Genome> Population<G> {
     // ensured one spot
     fn partition(total: usize, fractions: &[f64], elite: usize) -> Vec<usize> {
         assert!(fractions.len() > 0);
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::rng::thread_rng();
         let mut partitions: Vec<usize> = fractions
             .iter()
             .map(|x| ((total as f64 * x) as usize))
@@ -204,6 +459,7 @@ impl<G: Genome> Population<G> {
         while partitions[elite] == 0 {
             let selected = range.sample(&mut rng);
             if partitions[selected] > 0 {
+                partitions[selected] -= 1;
                 partitions[elite] = 1;
             }
         }
@@ -212,10 +468,17 @@ impl<G: Genome> Population<G> {
 
     /// Helper of `evolve`
     fn speciate(&mut self, organisms: &[Organism<G>], p: &NeatParams) {
+        // The species each organism was bred in, to find the parent of new species
+        let origin = self
+            .species
+            .iter()
+            .flat_map(|s| s.organisms.iter().map(move |o| (o.id, s.id)))
+            .collect::<std::collections::HashMap<_, _>>();
         for s in &mut self.species {
             if s.organisms.len() > 0 {
                 // Pick random representative from the previous generation
-                s.representative = s.organisms[rand::random::<usize>() % s.organisms.len()].clone();
+                s.representative =
+                    s.organisms[crate::rng::random::<usize>() % s.organisms.len()].clone();
                 s.organisms = Vec::new();
             }
         }
@@ -229,13 +492,18 @@ impl<G: Genome> Population<G> {
                     specie.organisms.push(organism.clone());
                 }
                 None => {
-                    self.species
-                        .push(Specie::new(organism.clone(), self.species_id));
+                    let mut specie = Specie::new(organism.clone(), self.species_id);
+                    specie.parent_id = origin.get(&organism.id).cloned();
+                    specie.birth_generation = self.generation;
+                    if let Some(ref mut phylogeny) = self.phylogeny {
+                        phylogeny.record_species(&specie);
+                    }
+                    self.species.push(specie);
                     self.species_id += 1;
                 }
             }
         }
-        self.species.retain(|s| s.organisms.len() > 0);
+        self.remove_extinct_species();
 
         // Update champion
         self.species
@@ -247,20 +515,97 @@ impl<G: Genome> Population<G> {
                 .partial_cmp(&a.champion_fitness())
                 .unwrap()
         });
-        // Fitness above which a species will not be removed.
-        let safe_fitness = self.species[usize::min(p.species_elite - 1, self.species.len() - 1)]
-            .champion_fitness();
-        // Kill species that haven't improved in a awhile
-        self.species.retain(|s| {
-            (s.age - s.age_last_improvement < p.remove_after_n_generations
-                || s.champion_fitness() >= safe_fitness)
-        });
+        // Fitness above which a species will not be removed. Without elite
+        // species, any species can be removed.
+        let safe_fitness = match p.species_elite {
+            0 => f64::INFINITY,
+            n => self.species[usize::min(n - 1, self.species.len() - 1)].champion_fitness(),
+        };
+        // Kill species that haven't improved in a awhile, but never all of them
+        let stagnating = |s: &Specie<G>| {
+            s.age - s.age_last_improvement >= p.remove_after_n_generations
+                && s.champion_fitness() < safe_fitness
+        };
+        let keep_best = self.species.iter().all(&stagnating);
+        for s in self.species.iter_mut().skip(keep_best as usize) {
+            if stagnating(s) {
+                s.organisms.clear();
+            }
+        }
+        self.remove_extinct_species();
+    }
+
+    /// Helper of `speciate`. Remove species without organisms, recording their
+    /// extinction.
+    fn remove_extinct_species(&mut self) {
+        let generation = self.generation;
+        for s in &mut self.species {
+            if s.organisms.is_empty() {
+                s.extinction_generation = Some(generation);
+                if let Some(ref mut phylogeny) = self.phylogeny {
+                    phylogeny.record_species(s);
+                }
+            }
+        }
+        self.species.retain(|s| !s.organisms.is_empty());
+    }
+
+    /// The next innovation id this population will hand out
+    pub(crate) fn innovation_id(&self) -> usize {
+        self.innovation_id
+    }
+    /// Continue handing out innovation ids from `innovation_id`, to share
+    /// them with other populations
+    pub(crate) fn set_innovation_id(&mut self, innovation_id: usize) {
+        self.innovation_id = innovation_id;
+    }
+
+    /// The `n` fittest organisms, fittest first
+    pub(crate) fn fittest(&self, n: usize) -> Vec<Organism<G>> {
+        let mut organisms = self.get_organisms().cloned().collect::<Vec<_>>();
+        organisms.sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap());
+        organisms.truncate(n);
+        organisms
+    }
+
+    /// Replace the least fit organisms with `migrants` from another
+    /// population, keeping the size of the population. The migrants get new
+    /// ids, and lose their lineage since their parents are not in this
+    /// population.
+    pub(crate) fn immigrate(&mut self, migrants: Vec<Organism<G>>) {
+        for mut migrant in migrants {
+            let worst = self
+                .species
+                .iter()
+                .enumerate()
+                .flat_map(|(s, specie)| {
+                    specie
+                        .organisms
+                        .iter()
+                        .enumerate()
+                        .map(move |(o, organism)| (s, o, organism.fitness))
+                })
+                .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+            let (s, o) = match worst {
+                Some((s, o, _)) => (s, o),
+                None => return,
+            };
+            migrant.id = self.organism_id;
+            migrant.parents = Vec::new();
+            migrant.mutations = Vec::new();
+            migrant.generation = self.generation;
+            self.organism_id += 1;
+            if let Some(ref mut phylogeny) = self.phylogeny {
+                phylogeny.record_organism(&migrant, self.species[s].id);
+            }
+            self.species[s].organisms[o] = migrant;
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Environment, NeatParams, NeuralNetwork, Organism, Population, Specie};
+    use crate::{Environment, NeatParams, NeuralNetwork, Organism, Phase, Population, Specie};
 
     #[test]
     fn population_should_be_able_to_speciate_genomes() {
@@ -288,6 +633,30 @@ mod tests {
         assert_eq!(population.species.len(), 2);
     }
 
+    #[test]
+    fn partition_should_keep_the_total_when_giving_the_elite_a_spot() {
+        // The elite would get no spot, so one is taken from the other species
+        assert_eq!(
+            Population::<NeuralNetwork>::partition(10, &[1.0, 0.0], 1),
+            vec![9, 1]
+        );
+        for _ in 0..100 {
+            let partitions = Population::<NeuralNetwork>::partition(7, &[0.5, 0.49, 0.01], 2);
+            assert_eq!(partitions.iter().sum::<usize>(), 7);
+            assert!(partitions[2] > 0);
+        }
+    }
+
+    #[test]
+    fn partition_should_hand_out_the_residue_one_spot_at_a_time() {
+        // 10 * 0.35 is rounded down to 3, so one spot is left over
+        for _ in 0..100 {
+            let partitions = Population::<NeuralNetwork>::partition(10, &[0.35, 0.35, 0.3], 0);
+            assert_eq!(partitions.iter().sum::<usize>(), 10);
+            assert!(partitions.iter().all(|&n| n == 3 || n == 4));
+        }
+    }
+
     #[test]
     fn after_population_evolve_population_should_be_the_same() {
         struct X;
@@ -300,8 +669,170 @@ mod tests {
         let p = NeatParams::default(0, 0);
         let mut population = Population::create_population(150);
         for _ in 0..150 {
-            population.evolve(&mut X, &p,true);
+            population.evolve(&mut X, &p, true);
         }
         assert!(population.size() == 150);
     }
+
+    #[test]
+    fn nan_fitness_should_count_as_the_lowest() {
+        struct X;
+        impl Environment<NeuralNetwork> for X {
+            fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+                if organism.n_connections() < 3 {
+                    f64::NAN
+                } else {
+                    1.0
+                }
+            }
+        }
+
+        let p = NeatParams::default(1, 1);
+        let mut population = Population::create_population(30);
+        for i in 0..20 {
+            population.evolve(&mut X, &p, i % 2 == 0);
+        }
+        assert!(population.get_organisms().all(|o| !o.fitness.is_nan()));
+    }
+
+    #[test]
+    fn stagnation_without_elite_species_should_not_remove_every_species() {
+        struct X;
+        impl Environment<NeuralNetwork> for X {
+            fn test(&self, _organism: &mut NeuralNetwork) -> f64 {
+                1.0
+            }
+        }
+
+        let p = NeatParams {
+            species_elite: 0,
+            remove_after_n_generations: 2,
+            ..NeatParams::default(0, 0)
+        };
+        let mut population = Population::create_population(50);
+        for _ in 0..20 {
+            population.evolve(&mut X, &p, false);
+        }
+        assert_eq!(population.size(), 50);
+    }
+
+    #[test]
+    fn parsimony_should_select_smaller_genomes_without_changing_fitness() {
+        struct X;
+        impl Environment<NeuralNetwork> for X {
+            fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+                100.0 + organism.n_connections() as f64
+            }
+        }
+
+        let evolve = |parsimony_coef| {
+            let p = NeatParams {
+                parsimony_coef,
+                ..NeatParams::default(2, 1)
+            };
+            let mut population =
+                Population::create_population_from(NeuralNetwork::with_neurons(3), 50);
+            for _ in 0..20 {
+                population.evolve(&mut X, &p, false);
+            }
+            let champion = population.get_champion();
+            assert_eq!(
+                champion.fitness,
+                100.0 + champion.genome.n_connections() as f64
+            );
+            population.mean_complexity()
+        };
+        assert!(evolve(2.0) < evolve(0.0));
+    }
+
+    #[test]
+    fn phased_pruning_should_alternate_complexifying_and_simplifying() {
+        struct X;
+        impl Environment<NeuralNetwork> for X {
+            fn test(&self, _organism: &mut NeuralNetwork) -> f64 {
+                1.0
+            }
+        }
+
+        let p = NeatParams {
+            mutation_pr: 1.0,
+            mutate_add_conn_pr: 1.0,
+            mutate_del_conn_pr: 0.0,
+            mutate_add_neuron_pr: 0.2,
+            mutate_del_neuron_pr: 0.0,
+            pruning_threshold: 3.0,
+            pruning_generations: 3,
+            ..NeatParams::default(2, 1)
+        };
+        let mut population = Population::create_population_from(NeuralNetwork::with_neurons(3), 50);
+        for _ in 0..40 {
+            population.evolve(&mut X, &p, false);
+        }
+
+        let phases = population.phase_stats();
+        assert!(phases.len() >= 3);
+        for (i, stats) in phases.iter().enumerate() {
+            let expected = [Phase::Complexifying, Phase::Simplifying][i % 2];
+            assert_eq!(stats.phase, expected);
+            if i + 1 < phases.len() {
+                assert_eq!(stats.last_generation, phases[i + 1].first_generation);
+            }
+        }
+        assert!(phases[0].end_complexity > phases[0].start_complexity + 3.0);
+        assert!(phases[1].min_complexity < phases[1].start_complexity);
+    }
+
+    #[test]
+    fn phylogeny_should_trace_champion_back_to_initial_population() {
+        struct X;
+        impl Environment<NeuralNetwork> for X {
+            fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+                organism.n_connections() as f64
+            }
+        }
+
+        crate::seed(7);
+        let p = NeatParams::default(1, 1);
+        let mut population = Population::create_population(20);
+        population.enable_phylogeny();
+        let initial = population.get_organisms().map(|o| o.id).collect::<Vec<_>>();
+        population.evolve(&mut X, &p, false);
+        // Initial organisms carried over as elites are evaluated like the others
+        let survivors = population
+            .get_organisms()
+            .map(|o| o.id)
+            .filter(|id| initial.contains(id))
+            .collect::<Vec<_>>();
+        for _ in 1..10 {
+            population.evolve(&mut X, &p, false);
+        }
+
+        let ids = population.get_organisms().map(|o| o.id).collect::<Vec<_>>();
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(ids.len(), unique.len());
+
+        let phylogeny = population.phylogeny().unwrap();
+        let champion = population.get_champion();
+        let lineage = phylogeny.lineage(champion.id);
+        assert_eq!(lineage[0].id, champion.id);
+        assert_eq!(lineage[0].fitness, Some(champion.fitness));
+        assert_eq!(lineage.last().unwrap().generation, 0);
+        assert!(lineage[..lineage.len() - 1]
+            .iter()
+            .all(|record| record.fitness.is_some()));
+        for id in initial {
+            let evaluated = phylogeny.organisms[&id].fitness.is_some();
+            assert_eq!(evaluated, survivors.contains(&id));
+        }
+        for organism in population.get_organisms() {
+            assert_eq!(
+                phylogeny.organisms[&organism.id].fitness,
+                Some(organism.fitness)
+            );
+        }
+        assert!(phylogeny.to_newick().ends_with(';'));
+        assert!(phylogeny.species.contains_key(&0));
+    }
 }
@@ -0,0 +1,187 @@
+//! Environments simulated by another program, written in any language.
+//!
+//! A `ProcessEnvironment` launches an executable and talks to it with one
+//! JSON message per line over its stdin and stdout. Every episode starts with
+//! a reset, after which the network is activated with each observation and
+//! its outputs are sent back as the action:
+//!
+//! ```text
+//! > {"type": "reset", "episode": 0}
+//! < {"observation": [0.0, 1.0]}
+//! > {"type": "step", "action": [0.3]}
+//! < {"observation": [0.3, 1.0], "reward": 0.3, "done": false}
+//! > {"type": "step", "action": [0.9]}
+//! < {"observation": [1.2, 1.0], "reward": 0.8, "done": true}
+//! ```
+//!
+//! The fitness is the total reward, averaged over the episodes. Since
+//! `Population` requires non-negative fitnesses, rewards should be chosen
+//! accordingly. The network keeps its state during an episode (see
+//! `StatefulCtrnn`), and is reset between episodes.
+//!
+//! Processes are kept running between evaluations, one per evaluation running
+//! at the same time, and killed when the environment is dropped. Anything the
+//! program writes to stderr is passed through.
+
+use crate::{Environment, NeuralNetwork};
+use serde_derive::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+use std::{error, fmt};
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Request<'a> {
+    Reset { episode: usize },
+    Step { action: &'a [f64] },
+}
+
+#[derive(Deserialize)]
+struct Response {
+    observation: Vec<f64>,
+    #[serde(default)]
+    reward: f64,
+    #[serde(default)]
+    done: bool,
+}
+
+/// Errors when talking to the simulator
+#[derive(Debug)]
+pub enum ProcessError {
+    /// The program could not be started, or reading or writing failed
+    Io(io::Error),
+    /// The program sent something that is not a valid message
+    Json(serde_json::Error),
+    /// The program closed its stdout
+    Exited,
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProcessError::Io(e) => write!(f, "could not talk to environment process: {}", e),
+            ProcessError::Json(e) => write!(f, "invalid message from environment process: {}", e),
+            ProcessError::Exited => write!(f, "environment process exited"),
+        }
+    }
+}
+
+impl error::Error for ProcessError {}
+
+impl From<io::Error> for ProcessError {
+    fn from(e: io::Error) -> ProcessError {
+        ProcessError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ProcessError {
+    fn from(e: serde_json::Error) -> ProcessError {
+        ProcessError::Json(e)
+    }
+}
+
+/// A running simulator
+struct Process {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Process {
+    fn request(&mut self, request: &Request) -> Result<Response, ProcessError> {
+        serde_json::to_writer(&mut self.stdin, request)?;
+        self.stdin.write_all(b"\n")?;
+        self.stdin.flush()?;
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            return Err(ProcessError::Exited);
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// Evaluates networks in a simulator running as a separate process. See the
+/// module documentation for the protocol.
+pub struct ProcessEnvironment {
+    program: String,
+    args: Vec<String>,
+    /// Number of outputs of the network, sent as the action
+    pub n_outputs: usize,
+    /// Number of episodes per evaluation
+    pub episodes: usize,
+    /// Episodes are ended after this many steps, even if not done
+    pub max_steps: Option<usize>,
+    idle: Mutex<Vec<Process>>,
+}
+
+impl ProcessEnvironment {
+    /// Run `program` with `args` as the simulator. Networks produce actions
+    /// of `n_outputs` values. One episode per evaluation.
+    pub fn new(program: &str, args: &[&str], n_outputs: usize) -> ProcessEnvironment {
+        ProcessEnvironment {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            n_outputs,
+            episodes: 1,
+            max_steps: None,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn spawn(&self) -> Result<Process, ProcessError> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        Ok(Process {
+            stdin: BufWriter::new(child.stdin.take().unwrap()),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+        })
+    }
+
+    /// Run the episodes with `network`, and return the average total reward.
+    /// A process that fails is not reused.
+    pub fn evaluate(&self, network: &NeuralNetwork) -> Result<f64, ProcessError> {
+        let idle = self.idle.lock().unwrap().pop();
+        let mut process = match idle {
+            Some(process) => process,
+            None => self.spawn()?,
+        };
+        let mut runner = network.make_stateful();
+        let mut action = vec![0.0; self.n_outputs];
+        let mut total = 0.0;
+        for episode in 0..self.episodes {
+            runner.reset();
+            let mut response = process.request(&Request::Reset { episode })?;
+            let mut steps = 0;
+            loop {
+                runner.step(&response.observation, &mut action);
+                response = process.request(&Request::Step { action: &action })?;
+                total += response.reward;
+                steps += 1;
+                if response.done || self.max_steps.is_some_and(|max| steps >= max) {
+                    break;
+                }
+            }
+        }
+        self.idle.lock().unwrap().push(process);
+        Ok(total / self.episodes.max(1) as f64)
+    }
+}
+
+impl Environment for ProcessEnvironment {
+    /// Panics if the simulator fails, see `evaluate()` to handle errors.
+    fn test(&self, organism: &mut NeuralNetwork) -> f64 {
+        self.evaluate(organism).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
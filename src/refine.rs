@@ -0,0 +1,492 @@
+//! Local refinement of the weights and biases of networks, to complement the
+//! coarse search of evolution on tasks with a smooth loss, like supervised
+//! learning.
+//!
+//! After `Population::evolve()`, `Population::refine()` runs an `Optimizer`
+//! on the parameters (see `NeuralNetwork::parameters()`) of every organism, or
+//! of the best ones, to minimize a user-provided loss. The structure of the
+//! networks is not changed. With `Inheritance::Lamarckian`, the refined
+//! parameters replace those of the genome, so offspring inherit them. With
+//! `Inheritance::Baldwinian`, the genome is kept and only its fitness is the
+//! one of the refined network, which rewards genomes that are easy to refine.
+
+use crate::NeuralNetwork;
+#[cfg(feature = "evolution")]
+use crate::{Environment, Mutation, Population};
+use rand::distributions::{Distribution, Normal};
+#[cfg(feature = "evolution")]
+use rayon::prelude::*;
+
+/// Local search algorithm used by a `Refinement`
+#[derive(Debug, Clone, Copy)]
+pub enum Optimizer {
+    /// Perturb all parameters with gaussian noise of standard deviation
+    /// `step`, keep the result if it is better. One evaluation per iteration.
+    HillClimbing {
+        /// Standard deviation of the perturbations
+        step: f64,
+    },
+    /// Separable CMA-ES (diagonal covariance), starting with step size
+    /// `sigma`. One generation of `4 + 3 ln(n)` evaluations per iteration.
+    CmaEs {
+        /// Initial step size
+        sigma: f64,
+    },
+    /// Gradient descent with central finite-difference gradients. `2n`
+    /// evaluations per iteration.
+    FiniteDifference {
+        /// Step of the gradient descent
+        learning_rate: f64,
+        /// Perturbation used to estimate the gradient
+        epsilon: f64,
+    },
+}
+
+/// What happens with the refined parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inheritance {
+    /// Write the refined parameters back into the genome
+    Lamarckian,
+    /// Keep the genome, only use the fitness of the refined network
+    Baldwinian,
+}
+
+/// Settings of the refinement phase
+#[derive(Debug, Clone)]
+pub struct Refinement {
+    /// The local search algorithm
+    pub optimizer: Optimizer,
+    /// Number of iterations of the optimizer
+    pub iterations: usize,
+    /// Whether refined parameters are written back into the genome
+    pub inheritance: Inheritance,
+    /// Only refine the `top_k` fittest organisms of the population. All of
+    /// them if `None`.
+    pub top_k: Option<usize>,
+}
+
+impl Refinement {
+    /// Refine all organisms for 20 iterations, Lamarckian
+    pub fn new(optimizer: Optimizer) -> Refinement {
+        Refinement {
+            optimizer,
+            iterations: 20,
+            inheritance: Inheritance::Lamarckian,
+            top_k: None,
+        }
+    }
+
+    /// Refine the parameters of `network` to minimize `loss`. Returns the
+    /// refined network and its loss, or the network itself if nothing better
+    /// was found.
+    pub fn refine<F>(&self, network: &NeuralNetwork, loss: F) -> (NeuralNetwork, f64)
+    where
+        F: Fn(&NeuralNetwork) -> f64,
+    {
+        let mut candidate = network.clone();
+        let (parameters, value) = self.minimize(&network.parameters(), |parameters| {
+            candidate.set_parameters(parameters);
+            loss(&candidate)
+        });
+        candidate.set_parameters(&parameters);
+        (candidate, value)
+    }
+
+    /// Minimize `loss` starting from `parameters`. Returns the best parameters
+    /// found and their loss.
+    pub fn minimize<F>(&self, parameters: &[f64], mut loss: F) -> (Vec<f64>, f64)
+    where
+        F: FnMut(&[f64]) -> f64,
+    {
+        let mut best = parameters.to_vec();
+        let mut best_loss = loss(&best);
+        if parameters.is_empty() {
+            return (best, best_loss);
+        }
+        let mut rng = crate::rng::thread_rng();
+        match self.optimizer {
+            Optimizer::HillClimbing { step } => {
+                let noise = Normal::new(0.0, step);
+                for _ in 0..self.iterations {
+                    let candidate = best
+                        .iter()
+                        .map(|x| x + noise.sample(&mut rng))
+                        .collect::<Vec<_>>();
+                    let candidate_loss = loss(&candidate);
+                    if candidate_loss < best_loss {
+                        best = candidate;
+                        best_loss = candidate_loss;
+                    }
+                }
+            }
+            Optimizer::FiniteDifference {
+                learning_rate,
+                epsilon,
+            } => {
+                let mut x = best.clone();
+                for _ in 0..self.iterations {
+                    let mut gradient = vec![0.0; x.len()];
+                    for j in 0..x.len() {
+                        let original = x[j];
+                        x[j] = original + epsilon;
+                        let plus = loss(&x);
+                        x[j] = original - epsilon;
+                        let minus = loss(&x);
+                        x[j] = original;
+                        gradient[j] = (plus - minus) / (2.0 * epsilon);
+                    }
+                    for (x, g) in x.iter_mut().zip(&gradient) {
+                        *x -= learning_rate * g;
+                    }
+                    let x_loss = loss(&x);
+                    if x_loss < best_loss {
+                        best = x.clone();
+                        best_loss = x_loss;
+                    }
+                }
+            }
+            Optimizer::CmaEs { sigma } => {
+                let (x, x_loss) = sep_cma_es(&best, sigma, self.iterations, &mut loss, &mut rng);
+                if x_loss < best_loss {
+                    best = x;
+                    best_loss = x_loss;
+                }
+            }
+        }
+        (best, best_loss)
+    }
+}
+
+/// Separable CMA-ES (Ros & Hansen, 2008). Returns the best point evaluated.
+fn sep_cma_es<F, R>(
+    start: &[f64],
+    sigma: f64,
+    generations: usize,
+    loss: &mut F,
+    rng: &mut R,
+) -> (Vec<f64>, f64)
+where
+    F: FnMut(&[f64]) -> f64,
+    R: rand::Rng,
+{
+    let n = start.len();
+    let nf = n as f64;
+    let lambda = 4 + (3.0 * nf.ln()).floor() as usize;
+    let mu = lambda / 2;
+    let mut weights = (0..mu)
+        .map(|i| (mu as f64 + 0.5).ln() - ((i + 1) as f64).ln())
+        .collect::<Vec<_>>();
+    let sum = weights.iter().sum::<f64>();
+    for w in &mut weights {
+        *w /= sum;
+    }
+    let mueff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+
+    let cs = (mueff + 2.0) / (nf + mueff + 5.0);
+    let ds = 1.0 + 2.0 * f64::max(0.0, ((mueff - 1.0) / (nf + 1.0)).sqrt() - 1.0) + cs;
+    let cc = (4.0 + mueff / nf) / (nf + 4.0 + 2.0 * mueff / nf);
+    // Learning rates of the full CMA-ES, increased for the diagonal
+    let c1 = 2.0 / ((nf + 1.3).powi(2) + mueff) * (nf + 2.0) / 3.0;
+    let cmu = f64::min(
+        1.0 - c1,
+        2.0 * (mueff - 2.0 + 1.0 / mueff) / ((nf + 2.0).powi(2) + mueff) * (nf + 2.0) / 3.0,
+    );
+    let chi_n = nf.sqrt() * (1.0 - 1.0 / (4.0 * nf) + 1.0 / (21.0 * nf * nf));
+
+    let normal = Normal::new(0.0, 1.0);
+    let mut mean = start.to_vec();
+    let mut sigma = sigma;
+    let mut c = vec![1.0f64; n];
+    let mut ps = vec![0.0; n];
+    let mut pc = vec![0.0; n];
+    let mut best = (start.to_vec(), f64::INFINITY);
+
+    for generation in 0..generations {
+        let d = c.iter().map(|c| c.sqrt()).collect::<Vec<_>>();
+        let mut samples = (0..lambda)
+            .map(|_| {
+                let z = (0..n).map(|_| normal.sample(rng)).collect::<Vec<_>>();
+                let x = (0..n)
+                    .map(|j| mean[j] + sigma * d[j] * z[j])
+                    .collect::<Vec<_>>();
+                let value = loss(&x);
+                (z, x, value)
+            })
+            .collect::<Vec<_>>();
+        samples.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+        if samples[0].2 < best.1 {
+            best = (samples[0].1.clone(), samples[0].2);
+        }
+
+        let mut z_w = vec![0.0; n];
+        for (w, (z, _, _)) in weights.iter().zip(&samples) {
+            for j in 0..n {
+                z_w[j] += w * z[j];
+            }
+        }
+        let y_w = (0..n).map(|j| d[j] * z_w[j]).collect::<Vec<_>>();
+        for j in 0..n {
+            mean[j] += sigma * y_w[j];
+        }
+        let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+        for j in 0..n {
+            ps[j] = (1.0 - cs) * ps[j] + (cs * (2.0 - cs) * mueff).sqrt() * z_w[j];
+        }
+        let hsig = norm(&ps) / (1.0 - (1.0 - cs).powi(2 * (generation as i32 + 1))).sqrt() / chi_n
+            < 1.4 + 2.0 / (nf + 1.0);
+        let hsig = if hsig { 1.0 } else { 0.0 };
+        for j in 0..n {
+            pc[j] = (1.0 - cc) * pc[j] + hsig * (cc * (2.0 - cc) * mueff).sqrt() * y_w[j];
+        }
+        for j in 0..n {
+            let rank_mu = weights
+                .iter()
+                .zip(&samples)
+                .map(|(w, (z, _, _))| w * (d[j] * z[j]).powi(2))
+                .sum::<f64>();
+            c[j] = (1.0 - c1 - cmu) * c[j]
+                + c1 * (pc[j] * pc[j] + (1.0 - hsig) * cc * (2.0 - cc) * c[j])
+                + cmu * rank_mu;
+        }
+        sigma *= ((cs / ds) * (norm(&ps) / chi_n - 1.0)).exp();
+    }
+    best
+}
+
+#[cfg(feature = "evolution")]
+impl Population<NeuralNetwork> {
+    /// Refine the organisms (see the `refine` module) to minimize `loss`, and
+    /// update their fitness with `env`. Meant to be called after `evolve()`.
+    /// The phylogeny, if enabled, gets the new fitnesses, and with Lamarckian
+    /// inheritance the changes of weights and biases as mutations.
+    pub fn refine<F>(
+        &mut self,
+        env: &dyn Environment,
+        refinement: &Refinement,
+        loss: F,
+        in_parallel: bool,
+    ) where
+        F: Fn(&NeuralNetwork) -> f64 + Sync,
+    {
+        let mut organisms = self
+            .species
+            .iter_mut()
+            .flat_map(|species| species.organisms.iter_mut())
+            .collect::<Vec<_>>();
+        if let Some(k) = refinement.top_k {
+            organisms.sort_by(|a, b| {
+                b.fitness
+                    .partial_cmp(&a.fitness)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            organisms.truncate(k);
+        }
+        let refine = |organism: &mut &mut crate::Organism| {
+            let (mut refined, _) = refinement.refine(&organism.genome, &loss);
+            organism.fitness = crate::environment::fitness(env, &mut refined);
+            if refinement.inheritance == Inheritance::Lamarckian {
+                let weights = organism
+                    .genome
+                    .connections
+                    .values()
+                    .zip(refined.connections.values())
+                    .filter(|(a, b)| a.weight != b.weight)
+                    .count();
+                let biases = organism
+                    .genome
+                    .neurons
+                    .values()
+                    .zip(refined.neurons.values())
+                    .filter(|(a, b)| a.bias != b.bias)
+                    .count();
+                if weights > 0 {
+                    organism.mutations.push(Mutation::Weights { count: weights });
+                }
+                if biases > 0 {
+                    organism.mutations.push(Mutation::Biases { count: biases });
+                }
+                organism.genome = refined;
+            }
+        };
+        if in_parallel {
+            let streams = crate::rng::streams();
+            organisms
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(i, organism)| streams.run(i, || refine(organism)));
+        } else {
+            organisms.iter_mut().for_each(refine);
+        }
+        let refined = organisms.iter().map(|organism| organism.id).collect();
+        self.record_organisms(&refined);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Optimizer, Refinement};
+    use crate::NeuralNetwork;
+
+    /// Squared error of the output for input 1.0 against 0.8
+    fn loss(network: &NeuralNetwork) -> f64 {
+        let mut output = vec![0.0];
+        network.make_network().activate(vec![1.0], &mut output);
+        (output[0] - 0.8).powi(2)
+    }
+
+    fn network() -> NeuralNetwork {
+        let mut network = NeuralNetwork::with_neurons(3);
+        network.add_connection(0, 2, -1.0);
+        network.add_connection(2, 1, 0.1);
+        network
+    }
+
+    #[test]
+    fn parameters_should_round_trip() {
+        let mut network = network();
+        let mut parameters = network.parameters();
+        assert_eq!(parameters, vec![-1.0, 0.1, 0.0, 0.0, 0.0]);
+        parameters[1] = 0.5;
+        parameters[4] = 2.0;
+        network.set_parameters(&parameters);
+        assert_eq!(network.connections[&(2, 1)].weight, 0.5);
+        assert_eq!(network.neurons[&2].bias, 2.0);
+        assert_eq!(network.parameters(), parameters);
+    }
+
+    #[test]
+    fn optimizers_should_reduce_the_loss() {
+        let initial = loss(&network());
+        for &(optimizer, iterations) in &[
+            (Optimizer::HillClimbing { step: 0.3 }, 200),
+            (Optimizer::CmaEs { sigma: 0.5 }, 50),
+            (
+                Optimizer::FiniteDifference {
+                    learning_rate: 0.5,
+                    epsilon: 1e-5,
+                },
+                100,
+            ),
+        ] {
+            let refinement = Refinement {
+                iterations,
+                ..Refinement::new(optimizer)
+            };
+            let (refined, refined_loss) = refinement.refine(&network(), loss);
+            assert!((loss(&refined) - refined_loss).abs() < 1e-12);
+            assert!(
+                refined_loss < initial * 0.01,
+                "{:?}: {} -> {}",
+                optimizer,
+                initial,
+                refined_loss
+            );
+            // Same structure
+            assert_eq!(refined.n_connections(), 2);
+        }
+    }
+
+    #[cfg(feature = "evolution")]
+    #[test]
+    fn inheritance_should_decide_whether_genomes_change() {
+        use super::Inheritance;
+        use crate::{Environment, Population};
+        struct Fit;
+        impl Environment for Fit {
+            fn test(&self, network: &mut NeuralNetwork) -> f64 {
+                1.0 / (1.0 + loss(network))
+            }
+        }
+        for &inheritance in &[Inheritance::Lamarckian, Inheritance::Baldwinian] {
+            let mut population = Population::create_population_from(network(), 4);
+            let refinement = Refinement {
+                inheritance,
+                top_k: Some(2),
+                ..Refinement::new(Optimizer::CmaEs { sigma: 0.5 })
+            };
+            population.refine(&Fit, &refinement, loss, false);
+            let organisms = population.get_organisms().collect::<Vec<_>>();
+            let refined = organisms
+                .iter()
+                .filter(|o| o.fitness > Fit.test(&mut network()))
+                .count();
+            assert_eq!(refined, 2);
+            let changed = organisms
+                .iter()
+                .filter(|o| o.genome.parameters() != network().parameters())
+                .count();
+            match inheritance {
+                Inheritance::Lamarckian => assert_eq!(changed, 2),
+                Inheritance::Baldwinian => assert_eq!(changed, 0),
+            }
+        }
+    }
+
+    #[cfg(feature = "evolution")]
+    #[test]
+    fn parallel_refinement_should_repeat_with_a_seed() {
+        use crate::{Environment, Population};
+        struct Fit;
+        impl Environment for Fit {
+            fn test(&self, network: &mut NeuralNetwork) -> f64 {
+                1.0 / (1.0 + loss(network))
+            }
+        }
+        let refined = || {
+            crate::seed(11);
+            let mut population = Population::create_population_from(network(), 16);
+            for optimizer in &[
+                Optimizer::HillClimbing { step: 0.3 },
+                Optimizer::CmaEs { sigma: 0.5 },
+            ] {
+                let refinement = Refinement {
+                    iterations: 10,
+                    ..Refinement::new(*optimizer)
+                };
+                population.refine(&Fit, &refinement, loss, true);
+            }
+            population
+                .get_organisms()
+                .map(|o| o.genome.parameters())
+                .collect::<Vec<_>>()
+        };
+        // Several threads, so that the tasks run in a different order every
+        // time, even on one core
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let first = pool.install(refined);
+        assert!(first.iter().any(|p| *p != network().parameters()));
+        for _ in 0..5 {
+            assert_eq!(pool.install(refined), first);
+        }
+    }
+
+    #[cfg(feature = "evolution")]
+    #[test]
+    fn refinement_should_be_recorded_in_the_phylogeny() {
+        use crate::{Environment, Mutation, Population};
+        struct Fit;
+        impl Environment for Fit {
+            fn test(&self, network: &mut NeuralNetwork) -> f64 {
+                1.0 / (1.0 + loss(network))
+            }
+        }
+        let mut population = Population::create_population_from(network(), 4);
+        population.enable_phylogeny();
+        population.evolve(&mut Fit, &crate::NeatParams::default(1, 1), false);
+        let refinement = Refinement {
+            top_k: Some(1),
+            ..Refinement::new(Optimizer::CmaEs { sigma: 0.5 })
+        };
+        population.refine(&Fit, &refinement, loss, false);
+        let champion = population.get_champion();
+        let record = &population.phylogeny().unwrap().organisms[&champion.id];
+        assert_eq!(record.fitness, Some(champion.fitness));
+        assert!(record
+            .mutations
+            .iter()
+            .any(|mutation| matches!(mutation, Mutation::Weights { .. })));
+    }
+}
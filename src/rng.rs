@@ -0,0 +1,240 @@
+//! The random number generator used for evolution, which can be seeded to
+//! make runs reproducible.
+
+use rand::rngs::StdRng;
+use rand::{
+    distributions::Standard, prelude::Distribution, FromEntropy, Rng, RngCore, SeedableRng,
+};
+use std::cell::RefCell;
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+    /// Where the generators of the parallel tasks started by this thread come
+    /// from
+    static STREAMS: RefCell<Streams> = RefCell::new(Streams::default());
+}
+
+/// Seed the random number generator of the calling thread. Evolution
+/// (mutation, mating, speciation) then repeats exactly, as long as the
+/// fitnesses are deterministic too: it always runs on the calling thread,
+/// which gets a generator seeded with `seed` itself. Parallel tasks it starts,
+/// like the evaluation of fitnesses, get generators derived from `seed` and
+/// the position of their item, whatever the order in which they run. Other
+/// threads are not affected, so populations can be seeded and evolved on
+/// several threads at once.
+///
+/// Without a seed, every thread is seeded from the operating system.
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+    STREAMS.with(|streams| *streams.borrow_mut() = Streams::from_seed(seed));
+}
+
+/// Generators for parallel tasks, derived from the seed of the thread that
+/// starts them, if it has one. See `streams()`.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(not(feature = "evolution"), allow(dead_code))]
+pub(crate) struct Streams {
+    seed: Option<u64>,
+    /// Number of batches of tasks started from these streams so far
+    batches: u64,
+}
+
+/// The generators for a batch of parallel tasks of the current thread. Every
+/// task should run in `Streams::run()`, wherever rayon executes it. Every call
+/// gives other generators, in the same order from the same seed.
+#[cfg(feature = "evolution")]
+pub(crate) fn streams() -> Streams {
+    STREAMS.with(|streams| {
+        let mut streams = streams.borrow_mut();
+        streams.batches += 1;
+        Streams {
+            seed: streams.seed.map(|seed| derive(seed, streams.batches)),
+            batches: 0,
+        }
+    })
+}
+
+/// Run `f` with the current thread seeded with `seed`, and restore its
+/// generator afterwards, to repeat work done on one thread while other work
+/// runs on the same thread pool
+#[cfg(feature = "evolution")]
+pub(crate) fn with_seed<T>(seed: u64, f: impl FnOnce() -> T) -> T {
+    Streams::from_seed(seed).install(StdRng::seed_from_u64(seed), f)
+}
+
+/// Seed number `n` derived from `seed`
+#[cfg_attr(not(feature = "evolution"), allow(dead_code))]
+fn derive(seed: u64, n: u64) -> u64 {
+    // SplitMix64 finalizer, so that derived seeds of derived seeds differ
+    let mut z = seed ^ n.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl Streams {
+    fn from_seed(seed: u64) -> Streams {
+        Streams {
+            seed: Some(seed),
+            batches: 0,
+        }
+    }
+
+    /// Run `f`, the parallel task of item `index` of the batch, with the
+    /// generator derived from the seed and `index`, and restore the generator
+    /// of the thread afterwards. Without a seed, `f` uses the generator of the
+    /// thread it runs on.
+    #[cfg(feature = "evolution")]
+    pub(crate) fn run<T>(&self, index: usize, f: impl FnOnce() -> T) -> T {
+        match self.seed {
+            Some(seed) => {
+                let seed = derive(seed, index as u64);
+                Streams::from_seed(seed).install(StdRng::seed_from_u64(seed), f)
+            }
+            None => f(),
+        }
+    }
+
+    /// Run `f` with `rng` and these streams in place of those of the current
+    /// thread
+    #[cfg(feature = "evolution")]
+    fn install<T>(self, rng: StdRng, f: impl FnOnce() -> T) -> T {
+        /// Puts back the generator of the thread, even if `f` panics
+        struct Restore(Option<(StdRng, Streams)>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                if let Some((rng, streams)) = self.0.take() {
+                    RNG.with(|current| *current.borrow_mut() = rng);
+                    STREAMS.with(|current| *current.borrow_mut() = streams);
+                }
+            }
+        }
+        let _restore = Restore(Some((
+            RNG.with(|current| current.replace(rng)),
+            STREAMS.with(|current| current.replace(self)),
+        )));
+        f()
+    }
+}
+
+/// Handle to the generator of the current thread, used in place of
+/// `rand::thread_rng()`
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ThreadRng;
+
+/// The generator of the current thread
+pub(crate) fn thread_rng() -> ThreadRng {
+    ThreadRng
+}
+
+/// A random value from the generator of the current thread, used in place of
+/// `rand::random()`
+pub(crate) fn random<T>() -> T
+where
+    Standard: Distribution<T>,
+{
+    thread_rng().gen()
+}
+
+fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+impl RngCore for ThreadRng {
+    fn next_u32(&mut self) -> u32 {
+        with_rng(|rng| rng.next_u32())
+    }
+    fn next_u64(&mut self) -> u64 {
+        with_rng(|rng| rng.next_u64())
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        with_rng(|rng| rng.fill_bytes(dest))
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        with_rng(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeding_should_repeat_the_numbers() {
+        seed(7);
+        let first = (0..10).map(|_| random::<u64>()).collect::<Vec<_>>();
+        seed(7);
+        let second = (0..10).map(|_| random::<u64>()).collect::<Vec<_>>();
+        assert_eq!(first, second);
+        seed(8);
+        assert_ne!(first[0], random::<u64>());
+    }
+
+    #[test]
+    fn seeding_should_not_affect_other_threads() {
+        use std::sync::{Arc, Barrier};
+        use std::thread;
+
+        let draw = || (0..10).map(|_| random::<u64>()).collect::<Vec<_>>();
+        let expected = (1..=2)
+            .map(|s| {
+                seed(s);
+                draw()
+            })
+            .collect::<Vec<_>>();
+        let barrier = Arc::new(Barrier::new(2));
+        let threads = (1..=2)
+            .map(|s| {
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    seed(s);
+                    // Both threads are seeded before either draws
+                    barrier.wait();
+                    draw()
+                })
+            })
+            .collect::<Vec<_>>();
+        let drawn = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(drawn, expected);
+    }
+
+    #[cfg(feature = "evolution")]
+    #[test]
+    fn parallel_tasks_should_restore_the_generator_of_their_thread() {
+        seed(5);
+        let expected = random::<u64>();
+        seed(5);
+        let streams = Streams::from_seed(9);
+        let task = streams.run(0, random::<u64>);
+        assert_ne!(task, expected);
+        assert_eq!(random::<u64>(), expected);
+        assert_eq!(streams.run(0, random::<u64>), task);
+        assert_ne!(streams.run(1, random::<u64>), task);
+    }
+
+    #[cfg(feature = "evolution")]
+    #[test]
+    fn parallel_tasks_should_not_depend_on_the_order_they_run_in() {
+        use rayon::prelude::*;
+        let draw = |reverse: bool| {
+            seed(3);
+            let streams = streams();
+            let mut indices = (0..64).collect::<Vec<_>>();
+            if reverse {
+                indices.reverse();
+            }
+            let mut drawn = indices
+                .into_par_iter()
+                .map(|i| (i, streams.run(i, random::<u64>)))
+                .collect::<Vec<_>>();
+            drawn.sort();
+            (drawn, streams.run(0, || super::streams().run(0, random::<u64>)))
+        };
+        assert_eq!(draw(false), draw(true));
+        seed(3);
+        assert_ne!(streams().run(0, random::<u64>), streams().run(0, random::<u64>));
+    }
+}
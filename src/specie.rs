@@ -1,12 +1,13 @@
 use crate::{Genome, NeatParams, Organism};
 use conv::prelude::*;
+use serde_derive::{Deserialize, Serialize};
 use rand::{
     self,
     distributions::{Distribution, Uniform},
 };
 
 /// A species (several organisms) and associated fitnesses
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Specie<G: Genome> {
     ///
     pub id: usize,
@@ -20,6 +21,13 @@ pub struct Specie<G: Genome> {
 
     /// All orgnamisms in this species
     pub organisms: Vec<Organism<G>>,
+
+    /// The species that the founding organism was bred in
+    pub parent_id: Option<usize>,
+    /// The generation in which this species appeared
+    pub birth_generation: usize,
+    /// The generation in which this species went extinct, if it has
+    pub extinction_generation: Option<usize>,
 }
 
 impl<G: Genome> Specie<G> {
@@ -33,6 +41,9 @@ impl<G: Genome> Specie<G> {
             champion: None,
             age: 0,
             age_last_improvement: 0,
+            parent_id: None,
+            birth_generation: 0,
+            extinction_generation: None,
         }
     }
     /// Check if another organism is of the same species as this one.
@@ -101,11 +112,15 @@ impl<G: Genome> Specie<G> {
     /// within this species. `champion_fitness`: the fitness of the
     /// population-wide champion. The reason for this parameter is that the
     /// species should see if it is the best-performing one.
+    /// New organisms are given ids counting from `organism_id` and are marked
+    /// as born in `generation`.
     pub fn generate_offspring(
         &mut self,
         n_offspring: usize,
         population_offspring: &[Organism<G>],
         innovation_id: &mut usize,
+        organism_id: &mut usize,
+        generation: usize,
         p: &NeatParams,
     ) {
         self.age += 1;
@@ -113,7 +128,7 @@ impl<G: Genome> Specie<G> {
             self.organisms = Vec::new();
             return;
         }
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::rng::thread_rng();
 
         self.organisms
             .sort_by(|a, b| a.fitness.partial_cmp(&b.fitness).unwrap());
@@ -141,7 +156,12 @@ impl<G: Genome> Specie<G> {
         let offspring: Vec<Organism<G>> = Iterator::chain(
             // mate n_random random organisms
             range.sample_iter(&mut rng).take(n_random).map(|i| {
-                self.create_child(&self.organisms[i], population_offspring, innovation_id, p)
+                let mut child =
+                    self.create_child(&self.organisms[i], population_offspring, innovation_id, p);
+                child.id = *organism_id;
+                child.generation = generation;
+                *organism_id += 1;
+                child
             }),
             // copy elite organisms
             (first_elite..self.organisms.len()).map(|i| self.organisms[i].clone()),
@@ -170,7 +190,7 @@ impl<G: Genome> Specie<G> {
     ) -> Organism<G> {
         let mut child = self.create_child_by_mate(organism, population_organisms, p);
 
-        if rand::random::<f64>() < p.mutation_pr {
+        if crate::rng::random::<f64>() < p.mutation_pr {
             child.mutate(innovation_id, p);
         }
         child
@@ -182,8 +202,8 @@ impl<G: Genome> Specie<G> {
         population_organisms: &[Organism<G>],
         p: &NeatParams,
     ) -> Organism<G> {
-        let mut rng = rand::thread_rng();
-        if rand::random::<f64>() > p.interspecie_mate_pr {
+        let mut rng = crate::rng::thread_rng();
+        if crate::rng::random::<f64>() > p.interspecie_mate_pr {
             let selected_mate = Uniform::from(0..self.organisms.len()).sample(&mut rng);
             organism.mate(&self.organisms[selected_mate], p)
         } else {
@@ -0,0 +1,962 @@
+//! Supervised training of acyclic networks with backpropagation.
+//!
+//! Evolution finds the structure of a network, but is slow at fine-tuning its
+//! weights when a dataset of examples is available. `Backprop` computes the
+//! exact gradient of the mean squared error of a feed-forward network (see
+//! `FeedForward`) with respect to its weights and biases, and trains them
+//! with SGD or Adam. The trained parameters can be written back into the
+//! `ConnectionGene::weight` and `NeuronGene::bias` of the genome.
+//!
+//! Recurrent networks are handled by `Bptt`, which backpropagates through the
+//! Euler steps of `Ctrnn`, over single activations or whole episodes, and can
+//! also train the time constants.
+//!
+//! ```
+//! use rustneat::train::{Dataset, Optimizer, Training};
+//! use rustneat::NeuralNetwork;
+//!
+//! let mut network = NeuralNetwork::with_neurons(2);
+//! network.add_connection(0, 1, 0.1);
+//! // y = 2x
+//! let dataset = Dataset::new(vec![vec![1.0], vec![2.0]], vec![vec![2.0], vec![4.0]]);
+//! let training = Training::new(Optimizer::adam(0.1), 200);
+//! let losses = network.train(1, 1, &dataset, &training).unwrap();
+//! assert!(losses.last().unwrap() < &losses[0]);
+//! ```
+
+use crate::nn::{Activation, Ctrnn, FeedForward, DELTA_T, STEPS, TAU};
+use crate::NeuralNetwork;
+use rand::seq::SliceRandom;
+
+/// Examples for supervised training: the network should produce `targets[i]`
+/// for `inputs[i]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dataset {
+    /// Inputs of the examples
+    pub inputs: Vec<Vec<f64>>,
+    /// Expected outputs of the examples
+    pub targets: Vec<Vec<f64>>,
+}
+
+impl Dataset {
+    /// Create a dataset. Panics if there are not as many targets as inputs.
+    pub fn new(inputs: Vec<Vec<f64>>, targets: Vec<Vec<f64>>) -> Dataset {
+        assert_eq!(inputs.len(), targets.len());
+        Dataset { inputs, targets }
+    }
+
+    /// Number of examples
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Whether there are no examples
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+}
+
+/// Gradient-based update rule used by `Backprop::train()`
+#[derive(Debug, Clone, Copy)]
+pub enum Optimizer {
+    /// Stochastic gradient descent with momentum
+    Sgd {
+        /// Step size
+        learning_rate: f64,
+        /// Fraction of the previous update added to the next one
+        momentum: f64,
+    },
+    /// Adam (Kingma and Ba, 2014)
+    Adam {
+        /// Step size
+        learning_rate: f64,
+        /// Decay of the first moment estimate
+        beta1: f64,
+        /// Decay of the second moment estimate
+        beta2: f64,
+        /// Added to the denominator for numerical stability
+        epsilon: f64,
+    },
+}
+
+impl Optimizer {
+    /// Plain SGD, without momentum
+    pub fn sgd(learning_rate: f64) -> Optimizer {
+        Optimizer::Sgd {
+            learning_rate,
+            momentum: 0.0,
+        }
+    }
+
+    /// Adam with the usual defaults
+    pub fn adam(learning_rate: f64) -> Optimizer {
+        Optimizer::Adam {
+            learning_rate,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+        }
+    }
+}
+
+/// Settings of a training run
+#[derive(Debug, Clone)]
+pub struct Training {
+    /// The update rule
+    pub optimizer: Optimizer,
+    /// Number of passes over the dataset
+    pub epochs: usize,
+    /// Number of examples per update, or `None` to use the whole dataset
+    /// (full-batch training)
+    pub batch_size: Option<usize>,
+    /// Whether to visit the examples in a random order in every epoch
+    pub shuffle: bool,
+}
+
+impl Training {
+    /// Full-batch training without shuffling
+    pub fn new(optimizer: Optimizer, epochs: usize) -> Training {
+        Training {
+            optimizer,
+            epochs,
+            batch_size: None,
+            shuffle: false,
+        }
+    }
+}
+
+/// Moments kept by the optimizer between updates
+struct OptimizerState {
+    first: Vec<f64>,
+    second: Vec<f64>,
+    t: i32,
+}
+
+impl OptimizerState {
+    fn new(n: usize) -> OptimizerState {
+        OptimizerState {
+            first: vec![0.0; n],
+            second: vec![0.0; n],
+            t: 0,
+        }
+    }
+
+    fn update(&mut self, optimizer: &Optimizer, parameters: &mut [f64], gradient: &[f64]) {
+        self.t += 1;
+        match *optimizer {
+            Optimizer::Sgd {
+                learning_rate,
+                momentum,
+            } => {
+                for ((p, v), g) in parameters.iter_mut().zip(&mut self.first).zip(gradient) {
+                    *v = momentum * *v - learning_rate * g;
+                    *p += *v;
+                }
+            }
+            Optimizer::Adam {
+                learning_rate,
+                beta1,
+                beta2,
+                epsilon,
+            } => {
+                let correction1 = 1.0 - beta1.powi(self.t);
+                let correction2 = 1.0 - beta2.powi(self.t);
+                for (((p, m), v), g) in parameters
+                    .iter_mut()
+                    .zip(&mut self.first)
+                    .zip(&mut self.second)
+                    .zip(gradient)
+                {
+                    *m = beta1 * *m + (1.0 - beta1) * g;
+                    *v = beta2 * *v + (1.0 - beta2) * g * g;
+                    *p -=
+                        learning_rate * (*m / correction1) / ((*v / correction2).sqrt() + epsilon);
+                }
+            }
+        }
+    }
+}
+
+/// Differentiable view of an acyclic `NeuralNetwork`. Its parameters are laid
+/// out like `NeuralNetwork::parameters()`: the weights in the order of
+/// `connections`, followed by the biases in the order of `neurons`.
+#[derive(Debug, Clone)]
+pub struct Backprop {
+    n_inputs: usize,
+    n_outputs: usize,
+    activations: Vec<Activation>,
+    /// Topological order of the neurons
+    order: Vec<usize>,
+    /// Incoming connections `(source, parameter index of the weight)`
+    incoming: Vec<Vec<(usize, usize)>>,
+    /// Parameter index of the bias of every neuron
+    bias: Vec<usize>,
+    parameters: Vec<f64>,
+}
+
+impl Backprop {
+    /// Prepare `network` for training, with the given number of inputs and
+    /// outputs. Returns `None` if the network has cycles.
+    pub fn new(network: &NeuralNetwork, n_inputs: usize, n_outputs: usize) -> Option<Backprop> {
+        let ids = network.sorted_neuron_ids();
+        let index = |id| ids.binary_search(&id).unwrap();
+        let connections = network
+            .connections
+            .values()
+            .map(|gene| {
+                (
+                    index(gene.in_neuron_id()),
+                    index(gene.out_neuron_id()),
+                    gene.weight,
+                )
+            })
+            .collect::<Vec<_>>();
+        let order =
+            FeedForward::new(network.get_bias(), network.get_activations(), &connections)?.order;
+
+        let mut incoming = vec![Vec::new(); ids.len()];
+        for (i, &(source, target, _)) in connections.iter().enumerate() {
+            incoming[target].push((source, i));
+        }
+        let bias = ids
+            .iter()
+            .map(|id| connections.len() + network.neurons.get_full(id).unwrap().0)
+            .collect();
+        Some(Backprop {
+            n_inputs,
+            n_outputs,
+            activations: network.get_activations(),
+            order,
+            incoming,
+            bias,
+            parameters: network.parameters(),
+        })
+    }
+
+    /// The current weights and biases
+    pub fn parameters(&self) -> &[f64] {
+        &self.parameters
+    }
+
+    /// Replace the weights and biases. Panics if `parameters` has the wrong
+    /// length.
+    pub fn set_parameters(&mut self, parameters: &[f64]) {
+        assert_eq!(parameters.len(), self.parameters.len());
+        self.parameters.copy_from_slice(parameters);
+    }
+
+    /// Write the current weights and biases into the genome it was created
+    /// from.
+    pub fn write_back(&self, network: &mut NeuralNetwork) {
+        network.set_parameters(&self.parameters);
+    }
+
+    /// The state of every neuron for `input`, like `FeedForward::evaluate()`
+    fn forward(&self, input: &[f64]) -> Vec<f64> {
+        let mut state = vec![0.0; self.bias.len()];
+        for &neuron in &self.order {
+            let mut y = input.get(neuron).cloned().unwrap_or(0.0);
+            for &(source, weight) in &self.incoming[neuron] {
+                y += self.parameters[weight] * self.activate(source, &state);
+            }
+            state[neuron] = y;
+        }
+        state
+    }
+
+    fn activate(&self, neuron: usize, state: &[f64]) -> f64 {
+        self.activations[neuron].apply(state[neuron] + self.parameters[self.bias[neuron]])
+    }
+
+    /// Outputs of the network for `input`
+    pub fn predict(&self, input: &[f64]) -> Vec<f64> {
+        let state = self.forward(input);
+        (0..self.n_outputs)
+            .map(|i| state.get(self.n_inputs + i).cloned().unwrap_or(0.0))
+            .collect()
+    }
+
+    /// Mean squared error over all examples and outputs
+    pub fn loss(&self, dataset: &Dataset) -> f64 {
+        self.gradient_of(dataset, &(0..dataset.len()).collect::<Vec<_>>(), None)
+    }
+
+    /// Mean squared error over all examples and outputs, and its gradient with
+    /// respect to the parameters
+    pub fn gradient(&self, dataset: &Dataset) -> (f64, Vec<f64>) {
+        let mut gradient = vec![0.0; self.parameters.len()];
+        let examples = (0..dataset.len()).collect::<Vec<_>>();
+        let loss = self.gradient_of(dataset, &examples, Some(&mut gradient));
+        (loss, gradient)
+    }
+
+    /// Loss over the given examples; accumulates its gradient if asked to
+    fn gradient_of(
+        &self,
+        dataset: &Dataset,
+        examples: &[usize],
+        mut gradient: Option<&mut Vec<f64>>,
+    ) -> f64 {
+        let n_neurons = self.bias.len();
+        let scale = 1.0 / (examples.len() * self.n_outputs).max(1) as f64;
+        let mut loss = 0.0;
+        for &example in examples {
+            let state = self.forward(&dataset.inputs[example]);
+            // Derivative of the loss with respect to the state of each neuron
+            let mut delta = vec![0.0; n_neurons];
+            for (i, target) in dataset.targets[example].iter().enumerate() {
+                if let Some(y) = state.get(self.n_inputs + i) {
+                    let error = y - target;
+                    loss += error * error * scale;
+                    delta[self.n_inputs + i] = 2.0 * error * scale;
+                }
+            }
+            let gradient = match gradient {
+                Some(ref mut gradient) => gradient,
+                None => continue,
+            };
+            // Every neuron is done once all neurons it feeds are
+            for &neuron in self.order.iter().rev() {
+                if delta[neuron] == 0.0 {
+                    continue;
+                }
+                for &(source, weight) in &self.incoming[neuron] {
+                    let x = state[source] + self.parameters[self.bias[source]];
+                    gradient[weight] += delta[neuron] * self.activations[source].apply(x);
+                    let d = delta[neuron]
+                        * self.parameters[weight]
+                        * self.activations[source].derivative(x);
+                    gradient[self.bias[source]] += d;
+                    delta[source] += d;
+                }
+            }
+        }
+        loss
+    }
+
+    /// Train the parameters on `dataset`. Returns the loss on the whole
+    /// dataset before training and after every epoch.
+    pub fn train(&mut self, dataset: &Dataset, training: &Training) -> Vec<f64> {
+        let mut rng = crate::rng::thread_rng();
+        let mut state = OptimizerState::new(self.parameters.len());
+        let mut examples = (0..dataset.len()).collect::<Vec<_>>();
+        let batch_size = training.batch_size.unwrap_or(examples.len()).max(1);
+        let mut losses = vec![self.loss(dataset)];
+        for _ in 0..training.epochs {
+            if training.shuffle {
+                examples.shuffle(&mut rng);
+            }
+            for batch in examples.chunks(batch_size) {
+                let mut gradient = vec![0.0; self.parameters.len()];
+                self.gradient_of(dataset, batch, Some(&mut gradient));
+                state.update(&training.optimizer, &mut self.parameters, &gradient);
+            }
+            losses.push(self.loss(dataset));
+        }
+        losses
+    }
+}
+
+/// Inputs and targets of consecutive activations
+type Episode<'a> = (&'a [Vec<f64>], &'a [Vec<f64>]);
+
+/// Gradient of the loss of a `Bptt` network
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    /// The loss itself
+    pub loss: f64,
+    /// Derivatives with respect to the weights and biases, laid out like
+    /// `NeuralNetwork::parameters()`
+    pub parameters: Vec<f64>,
+    /// Derivatives with respect to the time constant of every neuron, in the
+    /// order of the neuron ids
+    pub tau: Vec<f64>,
+}
+
+/// Differentiable view of a recurrent `NeuralNetwork`, for backpropagation
+/// through time. Gradients are computed through the unrolled Euler steps of
+/// `Ctrnn::activate()`, either for independent examples or for episodes in
+/// which the state carries over between activations, like with
+/// `StatefulCtrnn::step()`. Like `make_network()`, plastic connections and
+/// modulatory neurons are treated as ordinary ones.
+///
+/// The weights and biases are laid out like `NeuralNetwork::parameters()`.
+/// The genome has no time constants, so they start at `TAU`; they are only
+/// trained if `learn_tau` is set (never below the time step, to keep the
+/// integration stable), and cannot be written back.
+#[derive(Debug, Clone)]
+pub struct Bptt {
+    n_inputs: usize,
+    n_outputs: usize,
+    activations: Vec<Activation>,
+    /// Connections `(source, target)`, indexed like their weights
+    connections: Vec<(usize, usize)>,
+    /// Parameter index of the bias of every neuron
+    bias: Vec<usize>,
+    parameters: Vec<f64>,
+    tau: Vec<f64>,
+    delta_t: f64,
+    steps: usize,
+    /// Whether `train()` also changes the time constants
+    pub learn_tau: bool,
+}
+
+impl Bptt {
+    /// Prepare `network` for training, with the given number of inputs and
+    /// outputs and the integration settings of `make_network()`.
+    pub fn new(network: &NeuralNetwork, n_inputs: usize, n_outputs: usize) -> Bptt {
+        let ids = network.sorted_neuron_ids();
+        let index = |id| ids.binary_search(&id).unwrap();
+        Bptt {
+            n_inputs,
+            n_outputs,
+            activations: network.get_activations(),
+            connections: network
+                .connections
+                .values()
+                .map(|gene| (index(gene.in_neuron_id()), index(gene.out_neuron_id())))
+                .collect(),
+            bias: ids
+                .iter()
+                .map(|id| network.n_connections() + network.neurons.get_full(id).unwrap().0)
+                .collect(),
+            parameters: network.parameters(),
+            tau: vec![TAU; ids.len()],
+            delta_t: DELTA_T,
+            steps: STEPS,
+            learn_tau: false,
+        }
+    }
+
+    /// Use other integration settings
+    pub fn with_integration(mut self, delta_t: f64, steps: usize) -> Bptt {
+        self.delta_t = delta_t;
+        self.steps = steps;
+        self
+    }
+
+    /// The current weights and biases
+    pub fn parameters(&self) -> &[f64] {
+        &self.parameters
+    }
+
+    /// Replace the weights and biases. Panics if `parameters` has the wrong
+    /// length.
+    pub fn set_parameters(&mut self, parameters: &[f64]) {
+        assert_eq!(parameters.len(), self.parameters.len());
+        self.parameters.copy_from_slice(parameters);
+    }
+
+    /// The current time constants, in the order of the neuron ids
+    pub fn tau(&self) -> &[f64] {
+        &self.tau
+    }
+
+    /// Replace the time constants. Panics if `tau` has the wrong length.
+    pub fn set_tau(&mut self, tau: &[f64]) {
+        assert_eq!(tau.len(), self.tau.len());
+        self.tau.copy_from_slice(tau);
+    }
+
+    /// Write the current weights and biases into the genome it was created
+    /// from.
+    pub fn write_back(&self, network: &mut NeuralNetwork) {
+        network.set_parameters(&self.parameters);
+    }
+
+    /// The equivalent `Ctrnn`
+    pub fn make_network(&self) -> Ctrnn {
+        let n = self.tau.len();
+        let mut wij = vec![0.0; n * n];
+        for (weight, &(source, target)) in self.parameters.iter().zip(&self.connections) {
+            wij[target * n + source] = *weight;
+        }
+        let theta = self.bias.iter().map(|i| self.parameters[*i]).collect();
+        Ctrnn::new(theta, self.tau.clone(), wij, self.delta_t, self.steps)
+            .with_activations(self.activations.clone())
+    }
+
+    fn pad(&self, input: &[f64]) -> Vec<f64> {
+        (0..self.tau.len())
+            .map(|i| input.get(i).cloned().unwrap_or(0.0))
+            .collect()
+    }
+
+    /// One Euler step from `y`. Returns the new state and the derivative
+    /// `W * activation(y + bias) - y + input`.
+    fn step(&self, y: &[f64], input: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let activation = (0..y.len())
+            .map(|i| self.activations[i].apply(y[i] + self.parameters[self.bias[i]]))
+            .collect::<Vec<_>>();
+        let mut dy = (0..y.len()).map(|i| input[i] - y[i]).collect::<Vec<_>>();
+        for (weight, &(source, target)) in self.parameters.iter().zip(&self.connections) {
+            dy[target] += weight * activation[source];
+        }
+        let y = (0..y.len())
+            .map(|i| y[i] + self.delta_t / self.tau[i] * dy[i])
+            .collect();
+        (y, dy)
+    }
+
+    /// Outputs of the network for `input`, like `Ctrnn::activate()`
+    pub fn predict(&self, input: &[f64]) -> Vec<f64> {
+        let mut outputs = self.predict_episode(&[input.to_vec()]);
+        outputs.pop().unwrap()
+    }
+
+    /// Outputs of the network after each input of an episode
+    pub fn predict_episode(&self, inputs: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        let mut y = match inputs.first() {
+            Some(input) => self.pad(input),
+            None => return Vec::new(),
+        };
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let input = self.pad(input);
+            for _ in 0..self.steps {
+                y = self.step(&y, &input).0;
+            }
+            outputs.push(
+                (0..self.n_outputs)
+                    .map(|i| y.get(self.n_inputs + i).cloned().unwrap_or(0.0))
+                    .collect(),
+            );
+        }
+        outputs
+    }
+
+    /// Mean squared error over all examples and outputs, with every example
+    /// activated from scratch
+    pub fn loss(&self, dataset: &Dataset) -> f64 {
+        self.gradient(dataset).loss
+    }
+
+    /// Mean squared error over all examples and outputs and its gradient, with
+    /// every example activated from scratch
+    pub fn gradient(&self, dataset: &Dataset) -> Gradient {
+        let episodes = (0..dataset.len())
+            .map(|i| (&dataset.inputs[i..=i], &dataset.targets[i..=i]))
+            .collect::<Vec<_>>();
+        self.gradient_of(&episodes)
+    }
+
+    /// Mean squared error and its gradient over an episode, in which the
+    /// examples are consecutive activations and the state carries over
+    pub fn episode_gradient(&self, episode: &Dataset) -> Gradient {
+        self.gradient_of(&[(&episode.inputs[..], &episode.targets[..])])
+    }
+
+    /// Loss and gradient over the given episodes `(inputs, targets)`
+    fn gradient_of(&self, episodes: &[Episode]) -> Gradient {
+        let n = self.tau.len();
+        let n_targets = episodes
+            .iter()
+            .flat_map(|(_, targets)| targets.iter())
+            .map(|target| target.len().min(self.n_outputs))
+            .sum::<usize>();
+        let scale = 1.0 / n_targets.max(1) as f64;
+        let mut gradient = Gradient {
+            loss: 0.0,
+            parameters: vec![0.0; self.parameters.len()],
+            tau: vec![0.0; n],
+        };
+        for &(inputs, targets) in episodes {
+            if inputs.is_empty() {
+                continue;
+            }
+            let inputs = inputs.iter().map(|x| self.pad(x)).collect::<Vec<_>>();
+            // Forward pass, keeping the state before every step
+            let mut y = inputs[0].clone();
+            let mut history = Vec::with_capacity(inputs.len() * self.steps);
+            let mut errors = Vec::with_capacity(inputs.len());
+            for (input, target) in inputs.iter().zip(targets) {
+                for _ in 0..self.steps {
+                    let next = self.step(&y, input).0;
+                    history.push(y);
+                    y = next;
+                }
+                let mut error = vec![0.0; n];
+                for (i, target) in target.iter().take(self.n_outputs).enumerate() {
+                    if let Some(y) = y.get(self.n_inputs + i) {
+                        let e = y - target;
+                        gradient.loss += e * e * scale;
+                        error[self.n_inputs + i] = 2.0 * e * scale;
+                    }
+                }
+                errors.push(error);
+            }
+
+            // Backward pass; `delta` is the derivative of the loss with
+            // respect to the state after the current step
+            let mut delta = vec![0.0; n];
+            for (k, error) in errors.iter().enumerate().rev() {
+                for (d, e) in delta.iter_mut().zip(error) {
+                    *d += e;
+                }
+                for s in (0..self.steps).rev() {
+                    let y = &history[k * self.steps + s];
+                    let (_, dy) = self.step(y, &inputs[k]);
+                    let scaled = (0..n)
+                        .map(|i| delta[i] * self.delta_t / self.tau[i])
+                        .collect::<Vec<_>>();
+                    for i in 0..n {
+                        gradient.tau[i] -= scaled[i] * dy[i] / self.tau[i];
+                        delta[i] -= scaled[i];
+                    }
+                    // Derivative with respect to the activations
+                    let mut d_activation = vec![0.0; n];
+                    for (w, &(source, target)) in self.connections.iter().enumerate() {
+                        let x = y[source] + self.parameters[self.bias[source]];
+                        gradient.parameters[w] +=
+                            scaled[target] * self.activations[source].apply(x);
+                        d_activation[source] += scaled[target] * self.parameters[w];
+                    }
+                    for i in 0..n {
+                        let x = y[i] + self.parameters[self.bias[i]];
+                        let d = d_activation[i] * self.activations[i].derivative(x);
+                        gradient.parameters[self.bias[i]] += d;
+                        delta[i] += d;
+                    }
+                }
+            }
+        }
+        gradient
+    }
+
+    /// Train on independent examples. Returns the loss on the whole dataset
+    /// before training and after every epoch.
+    pub fn train(&mut self, dataset: &Dataset, training: &Training) -> Vec<f64> {
+        let episodes = (0..dataset.len())
+            .map(|i| (&dataset.inputs[i..=i], &dataset.targets[i..=i]))
+            .collect::<Vec<_>>();
+        self.fit(&episodes, training)
+    }
+
+    /// Train on episodes, in which the examples are consecutive activations.
+    /// Batches are made of whole episodes. Returns the loss on all episodes
+    /// before training and after every epoch.
+    pub fn train_episodes(&mut self, episodes: &[Dataset], training: &Training) -> Vec<f64> {
+        let episodes = episodes
+            .iter()
+            .map(|episode| (&episode.inputs[..], &episode.targets[..]))
+            .collect::<Vec<_>>();
+        self.fit(&episodes, training)
+    }
+
+    fn fit(&mut self, episodes: &[Episode], training: &Training) -> Vec<f64> {
+        let mut rng = crate::rng::thread_rng();
+        let n_parameters = self.parameters.len();
+        let mut state = OptimizerState::new(n_parameters + self.tau.len());
+        let mut order = (0..episodes.len()).collect::<Vec<_>>();
+        let batch_size = training.batch_size.unwrap_or(order.len()).max(1);
+        let mut losses = vec![self.gradient_of(episodes).loss];
+        for _ in 0..training.epochs {
+            if training.shuffle {
+                order.shuffle(&mut rng);
+            }
+            for batch in order.chunks(batch_size) {
+                let batch = batch.iter().map(|i| episodes[*i]).collect::<Vec<_>>();
+                let gradient = self.gradient_of(&batch);
+                let mut parameters = [&self.parameters[..], &self.tau[..]].concat();
+                let mut derivatives = gradient.parameters;
+                if self.learn_tau {
+                    derivatives.extend(gradient.tau);
+                } else {
+                    derivatives.resize(parameters.len(), 0.0);
+                }
+                state.update(&training.optimizer, &mut parameters, &derivatives);
+                self.parameters.copy_from_slice(&parameters[..n_parameters]);
+                if self.learn_tau {
+                    for (tau, new) in self.tau.iter_mut().zip(&parameters[n_parameters..]) {
+                        // Keep the integration stable
+                        *tau = new.max(self.delta_t);
+                    }
+                }
+            }
+            losses.push(self.gradient_of(episodes).loss);
+        }
+        losses
+    }
+}
+
+impl NeuralNetwork {
+    /// Train the weights and biases of an acyclic network on `dataset` with
+    /// backpropagation, and write them back into the genome. Returns the loss
+    /// before training and after every epoch, or `None` (leaving the genome
+    /// untouched) if the network has cycles. See `Backprop`.
+    pub fn train(
+        &mut self,
+        n_inputs: usize,
+        n_outputs: usize,
+        dataset: &Dataset,
+        training: &Training,
+    ) -> Option<Vec<f64>> {
+        let mut backprop = Backprop::new(self, n_inputs, n_outputs)?;
+        let losses = backprop.train(dataset, training);
+        backprop.write_back(self);
+        Some(losses)
+    }
+
+    /// Mean squared error of `make_network()` on `dataset`, and its gradient
+    /// with respect to `parameters()`, computed with backpropagation through
+    /// time. See `Bptt`.
+    pub fn loss_gradient(
+        &self,
+        n_inputs: usize,
+        n_outputs: usize,
+        dataset: &Dataset,
+    ) -> (f64, Vec<f64>) {
+        let gradient = Bptt::new(self, n_inputs, n_outputs).gradient(dataset);
+        (gradient.loss, gradient.parameters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Acyclic network with every activation function on the hidden neurons
+    fn network() -> NeuralNetwork {
+        let activations = [
+            Activation::Sigmoid,
+            Activation::Tanh,
+            Activation::Relu,
+            Activation::Identity,
+            Activation::Gaussian,
+            Activation::Sine,
+            Activation::Abs,
+        ];
+        let mut network = NeuralNetwork::with_neurons(4 + activations.len());
+        for (i, activation) in activations.iter().enumerate() {
+            let id = 4 + i;
+            let gene = network.neurons.get_mut(&id).unwrap();
+            gene.activation = *activation;
+            gene.bias = 0.1 * i as f64 - 0.3;
+            network.add_connection(0, id, 0.5 + 0.1 * i as f64);
+            network.add_connection(1, id, -0.4 + 0.05 * i as f64);
+            network.add_connection(id, 2 + i % 2, 0.7 - 0.2 * i as f64);
+        }
+        network.add_connection(4, 5, 0.9);
+        network.add_connection(5, 6, -1.1);
+        network.add_connection(0, 2, 0.3);
+        network.neurons.get_mut(&0).unwrap().activation = Activation::Tanh;
+        network.neurons.get_mut(&0).unwrap().bias = 0.2;
+        network
+    }
+
+    fn dataset() -> Dataset {
+        Dataset::new(
+            vec![
+                vec![0.0, 0.0],
+                vec![0.0, 1.0],
+                vec![1.0, 0.0],
+                vec![1.0, 1.0],
+                vec![0.3, -0.7],
+            ],
+            vec![
+                vec![0.0, 1.0],
+                vec![1.0, 0.0],
+                vec![1.0, 0.5],
+                vec![0.0, -0.5],
+                vec![0.2, 0.2],
+            ],
+        )
+    }
+
+    #[test]
+    fn gradient_should_match_finite_differences() {
+        let mut backprop = Backprop::new(&network(), 2, 2).unwrap();
+        let dataset = dataset();
+        let (loss, gradient) = backprop.gradient(&dataset);
+        assert!((loss - backprop.loss(&dataset)).abs() < 1e-12);
+        let parameters = backprop.parameters().to_vec();
+        let epsilon = 1e-6;
+        for i in 0..parameters.len() {
+            let mut p = parameters.clone();
+            p[i] += epsilon;
+            backprop.set_parameters(&p);
+            let plus = backprop.loss(&dataset);
+            p[i] -= 2.0 * epsilon;
+            backprop.set_parameters(&p);
+            let minus = backprop.loss(&dataset);
+            let expected = (plus - minus) / (2.0 * epsilon);
+            assert!(
+                (gradient[i] - expected).abs() < 1e-6,
+                "parameter {}: {} != {}",
+                i,
+                gradient[i],
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn predictions_should_match_feed_forward() {
+        let network = network();
+        let backprop = Backprop::new(&network, 2, 2).unwrap();
+        let feed_forward = network.make_feed_forward().unwrap();
+        for input in &dataset().inputs {
+            let mut expected = vec![0.0; 2];
+            feed_forward.activate(input.clone(), &mut expected);
+            assert_eq!(backprop.predict(input), expected);
+        }
+    }
+
+    #[test]
+    fn training_should_reduce_loss_and_write_back() {
+        let dataset = dataset();
+        for optimizer in &[
+            Optimizer::Sgd {
+                learning_rate: 0.05,
+                momentum: 0.5,
+            },
+            Optimizer::adam(0.01),
+        ] {
+            let mut network = network();
+            let mut training = Training::new(*optimizer, 100);
+            training.batch_size = Some(2);
+            training.shuffle = true;
+            let losses = network.train(2, 2, &dataset, &training).unwrap();
+            assert_eq!(losses.len(), 101);
+            assert!(losses[100] < 0.5 * losses[0], "{:?}", losses);
+            let trained = Backprop::new(&network, 2, 2).unwrap();
+            assert!((trained.loss(&dataset) - losses[100]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn recurrent_networks_should_be_rejected() {
+        let mut network = network();
+        network.add_connection(2, 4, 0.1);
+        assert!(Backprop::new(&network, 2, 2).is_none());
+        let training = Training::new(Optimizer::sgd(0.1), 1);
+        assert!(network.train(2, 2, &dataset(), &training).is_none());
+    }
+
+    fn recurrent_network() -> NeuralNetwork {
+        let mut network = NeuralNetwork::with_neurons(6);
+        network.neurons.get_mut(&4).unwrap().bias = 0.3;
+        network.neurons.get_mut(&4).unwrap().activation = Activation::Tanh;
+        network.neurons.get_mut(&5).unwrap().bias = -0.25;
+        network.neurons.get_mut(&5).unwrap().activation = Activation::Relu;
+        network.neurons.get_mut(&3).unwrap().activation = Activation::Identity;
+        network.add_connection(0, 4, 1.5);
+        network.add_connection(1, 4, -0.7);
+        network.add_connection(4, 2, 2.0);
+        network.add_connection(4, 3, -1.0);
+        network.add_connection(2, 4, 0.5);
+        network.add_connection(3, 3, 0.4);
+        network.add_connection(1, 5, 0.8);
+        network.add_connection(5, 2, 0.6);
+        network
+    }
+
+    fn bptt() -> Bptt {
+        let mut bptt = Bptt::new(&recurrent_network(), 2, 2).with_integration(0.5, 6);
+        bptt.set_tau(&[1.0, 1.5, 2.0, 1.2, 0.8, 1.0]);
+        bptt
+    }
+
+    fn assert_gradient<F: FnMut(&mut Bptt) -> f64>(gradient: &Gradient, mut loss: F) {
+        let epsilon = 1e-6;
+        let mut bptt = bptt();
+        let parameters = bptt.parameters().to_vec();
+        for i in 0..parameters.len() {
+            let mut p = parameters.clone();
+            p[i] += epsilon;
+            bptt.set_parameters(&p);
+            let plus = loss(&mut bptt);
+            p[i] -= 2.0 * epsilon;
+            bptt.set_parameters(&p);
+            let minus = loss(&mut bptt);
+            let expected = (plus - minus) / (2.0 * epsilon);
+            assert!(
+                (gradient.parameters[i] - expected).abs() < 1e-6,
+                "parameter {}: {} != {}",
+                i,
+                gradient.parameters[i],
+                expected
+            );
+        }
+        bptt.set_parameters(&parameters);
+        let tau = bptt.tau().to_vec();
+        for i in 0..tau.len() {
+            let mut t = tau.clone();
+            t[i] += epsilon;
+            bptt.set_tau(&t);
+            let plus = loss(&mut bptt);
+            t[i] -= 2.0 * epsilon;
+            bptt.set_tau(&t);
+            let minus = loss(&mut bptt);
+            let expected = (plus - minus) / (2.0 * epsilon);
+            assert!(
+                (gradient.tau[i] - expected).abs() < 1e-6,
+                "tau {}: {} != {}",
+                i,
+                gradient.tau[i],
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn bptt_gradient_should_match_finite_differences() {
+        let dataset = dataset();
+        assert_gradient(&bptt().gradient(&dataset), |bptt| bptt.loss(&dataset));
+        let episode = dataset;
+        assert_gradient(&bptt().episode_gradient(&episode), |bptt| {
+            bptt.episode_gradient(&episode).loss
+        });
+    }
+
+    #[test]
+    fn bptt_predictions_should_match_ctrnn_and_stateful_runner() {
+        let network = recurrent_network();
+        let bptt = Bptt::new(&network, 2, 2);
+        let ctrnn = network.make_network();
+        let inputs = dataset().inputs;
+        for input in &inputs {
+            let mut expected = vec![0.0; 2];
+            ctrnn.activate(input.clone(), &mut expected);
+            let output = bptt.predict(input);
+            for (e, o) in expected.iter().zip(&output) {
+                assert!((e - o).abs() < 1e-12, "{} != {}", e, o);
+            }
+        }
+        let mut stateful = network.make_stateful();
+        for (input, output) in inputs.iter().zip(bptt.predict_episode(&inputs)) {
+            let mut expected = vec![0.0; 2];
+            stateful.step(input, &mut expected);
+            for (e, o) in expected.iter().zip(&output) {
+                assert!((e - o).abs() < 1e-12, "{} != {}", e, o);
+            }
+        }
+    }
+
+    #[test]
+    fn bptt_training_should_reduce_loss() {
+        let mut network = recurrent_network();
+        let episodes = vec![dataset(), dataset()];
+        let mut bptt = Bptt::new(&network, 2, 2);
+        bptt.learn_tau = true;
+        let losses = bptt.train_episodes(&episodes, &Training::new(Optimizer::adam(0.02), 100));
+        assert!(losses[100] < 0.5 * losses[0], "{:?}", losses);
+        assert!(bptt.tau().iter().any(|tau| *tau != TAU));
+
+        bptt.write_back(&mut network);
+        let (loss, gradient) = network.loss_gradient(2, 2, &dataset());
+        assert_eq!(gradient.len(), network.parameters().len());
+        let trained = Bptt::new(&network, 2, 2);
+        assert!((loss - trained.loss(&dataset())).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bptt_training_should_keep_tau_unless_learned() {
+        let mut bptt = bptt();
+        // Below the time step of 0.5
+        let tau = [0.3, 1.5, 2.0, 1.2, 0.8, 1.0];
+        bptt.set_tau(&tau);
+        bptt.train_episodes(&[dataset()], &Training::new(Optimizer::adam(0.02), 5));
+        assert_eq!(bptt.tau(), &tau);
+    }
+}
@@ -0,0 +1,788 @@
+//! Search for `NeatParams` that evolve good solutions in an `Environment`.
+//!
+//! A `SearchSpace` gives a range to every parameter to tune, the rest keep
+//! the values of a base `NeatParams`. A `Tuner` then runs trials: each trial
+//! picks values for the parameters with its `Strategy`, evolves a population
+//! for a number of generations with every seed, and scores the values with
+//! the mean fitness of the champions. The seeds run in parallel, and are the
+//! same for every trial, so that trials are compared on equal terms.
+//!
+//! ```
+//! use rustneat::dataset::DatasetEnvironment;
+//! use rustneat::tune::{SearchSpace, Strategy, Tuner};
+//! use rustneat::{NeatParams, NeuralNetwork};
+//!
+//! let space = SearchSpace::from_toml(
+//!     r#"
+//!     mutation_pr = { min = 0.2, max = 1.0 }
+//!     weight_mutate_var = { min = 0.05, max = 2.0, log = true }
+//!     species_elite = { min = 1, max = 4, integer = true }
+//!     "#,
+//! )
+//! .unwrap();
+//! let tuner = Tuner::new(space, NeatParams::default(2, 1), NeuralNetwork::with_neurons(3))
+//!     .with_strategy(Strategy::tpe())
+//!     .with_generations(5)
+//!     .with_population_size(20)
+//!     .with_seeds(vec![1, 2]);
+//! let trials = tuner.run(&DatasetEnvironment::xor(), 4, |_| {}).unwrap();
+//! let best = rustneat::tune::best(&trials).unwrap();
+//! let params = tuner.space().apply(&NeatParams::default(2, 1), &best.values).unwrap();
+//! assert!(params.mutation_pr >= 0.2 && params.mutation_pr <= 1.0);
+//! ```
+//!
+//! With a log file, every finished trial is appended to it as a line of JSON.
+//! A tuner started with an existing log continues where it stopped: the logged
+//! trials count towards the number of trials, and guide the search.
+
+use crate::params::ParamsError;
+use crate::{Environment, NeatParams, NeuralNetwork, Population};
+use indexmap::IndexMap;
+use rand::distributions::{Distribution, Normal};
+use rand::Rng;
+use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::{error, fmt};
+
+/// The values a parameter is searched in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Range {
+    /// Smallest value
+    pub min: f64,
+    /// Largest value
+    pub max: f64,
+    /// Search on a logarithmic scale, for parameters whose order of magnitude
+    /// matters more than their value, like variances
+    #[serde(default)]
+    pub log: bool,
+    /// Only whole numbers, for counts like `species_elite`
+    #[serde(default)]
+    pub integer: bool,
+}
+
+impl Range {
+    /// The value at `u` between 0 (`min`) and 1 (`max`)
+    fn value(&self, u: f64) -> f64 {
+        let u = u.clamp(0.0, 1.0);
+        if self.integer {
+            // Every whole number gets the same share of the unit interval
+            let (min, max) = (self.min.round(), self.max.round());
+            let x = if self.log {
+                ((min - 0.5).max(0.5).ln() * (1.0 - u) + (max + 0.5).ln() * u).exp()
+            } else {
+                (min - 0.5) + u * (max - min + 1.0)
+            };
+            x.round().clamp(min, max)
+        } else if self.log {
+            (self.min.ln() * (1.0 - u) + self.max.ln() * u).exp()
+        } else {
+            self.min + u * (self.max - self.min)
+        }
+    }
+
+    /// Where `value` lies between `min` and `max`, from 0 to 1
+    fn unit(&self, value: f64) -> f64 {
+        let (min, max, value) = if self.integer {
+            let (min, max) = (self.min.round(), self.max.round());
+            if self.log {
+                ((min - 0.5).max(0.5).ln(), (max + 0.5).ln(), value.ln())
+            } else {
+                (min - 0.5, max + 0.5, value)
+            }
+        } else if self.log {
+            (self.min.ln(), self.max.ln(), value.ln())
+        } else {
+            (self.min, self.max, value)
+        };
+        if max > min {
+            ((value - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.5
+        }
+    }
+}
+
+/// The parameters to tune, with their ranges
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SearchSpace {
+    /// Range of every parameter, by the name of its field in `NeatParams`
+    pub ranges: IndexMap<String, Range>,
+}
+
+impl SearchSpace {
+    /// Read the ranges from a table of TOML, with one entry per parameter,
+    /// like `mutation_pr = { min = 0.2, max = 1.0 }`
+    pub fn from_toml(text: &str) -> Result<SearchSpace, ParamsError> {
+        toml::from_str(text).map_err(|e| ParamsError::Parse(e.to_string()))
+    }
+
+    /// Read the ranges from a JSON object, see `from_toml()`
+    pub fn from_json(text: &str) -> Result<SearchSpace, ParamsError> {
+        serde_json::from_str(text).map_err(|e| ParamsError::Parse(e.to_string()))
+    }
+
+    /// Read the ranges from a TOML file, or a JSON file if the name ends in
+    /// `.json`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<SearchSpace, ParamsError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        if path.extension().is_some_and(|e| e == "json") {
+            SearchSpace::from_json(&text)
+        } else {
+            SearchSpace::from_toml(&text)
+        }
+    }
+
+    /// Add a parameter to tune
+    pub fn with(mut self, name: &str, range: Range) -> SearchSpace {
+        self.ranges.insert(name.to_string(), range);
+        self
+    }
+
+    /// `base` with the parameters of the search space set to `values`,
+    /// validated
+    pub fn apply(
+        &self,
+        base: &NeatParams,
+        values: &IndexMap<String, f64>,
+    ) -> Result<NeatParams, ParamsError> {
+        let mut overrides = Map::new();
+        for (name, value) in values {
+            let integer = self.ranges.get(name).is_some_and(|range| range.integer);
+            let value = if integer {
+                Value::from(value.round() as u64)
+            } else {
+                Value::from(*value)
+            };
+            overrides.insert(name.clone(), value);
+        }
+        base.with_overrides(&overrides)
+    }
+
+    /// Check the ranges, and that they belong to parameters of `base` and
+    /// stay within their valid values
+    fn check(&self, base: &NeatParams) -> Result<(), ParamsError> {
+        let mut problems = Vec::new();
+        for (name, range) in &self.ranges {
+            if range.min.is_nan() || range.max.is_nan() || range.min > range.max {
+                problems.push(format!("the range of {} is empty", name));
+            } else if range.log && range.min <= 0.0 {
+                problems.push(format!(
+                    "the range of {} is logarithmic but not positive",
+                    name
+                ));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(ParamsError::Invalid(problems));
+        }
+        self.apply(base, &self.point(&vec![0.0; self.ranges.len()]))?;
+        self.apply(base, &self.point(&vec![1.0; self.ranges.len()]))?;
+        Ok(())
+    }
+
+    /// The values at `units`, one number from 0 to 1 per parameter
+    fn point(&self, units: &[f64]) -> IndexMap<String, f64> {
+        self.ranges
+            .iter()
+            .zip(units)
+            .map(|((name, range), u)| (name.clone(), range.value(*u)))
+            .collect()
+    }
+
+    /// Where the values of `trial` lie in their ranges, from 0 to 1
+    fn units(&self, trial: &Trial) -> Vec<f64> {
+        self.ranges
+            .iter()
+            .map(|(name, range)| trial.values.get(name).map_or(0.5, |v| range.unit(*v)))
+            .collect()
+    }
+}
+
+/// How the values of the next trials are picked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Strategy {
+    /// Uniformly at random in the ranges (or their logarithms)
+    Random,
+    /// Every combination of `steps` evenly spaced values per parameter, in
+    /// order. Integer ranges with fewer values have fewer steps.
+    Grid {
+        /// Values per parameter
+        steps: usize,
+    },
+    /// Tree-structured Parzen estimator (Bergstra et al., 2011): after
+    /// `startup` random trials, the trials are split into the best `gamma`
+    /// fraction and the rest, and of `candidates` values drawn near the best
+    /// trials, the one most likely to be among the best is picked. `batch`
+    /// trials are picked at once, to run them in parallel.
+    Tpe {
+        /// Number of random trials before the estimator is used
+        startup: usize,
+        /// Fraction of the trials considered to be the best
+        gamma: f64,
+        /// Number of values to choose from per trial
+        candidates: usize,
+        /// Trials picked at once
+        batch: usize,
+    },
+}
+
+impl Strategy {
+    /// Grid search with 5 values per parameter
+    pub fn grid() -> Strategy {
+        Strategy::Grid { steps: 5 }
+    }
+
+    /// Tree-structured Parzen estimator with the usual settings: 10 random
+    /// trials first, the best quarter, and 24 candidates per trial
+    pub fn tpe() -> Strategy {
+        Strategy::Tpe {
+            startup: 10,
+            gamma: 0.25,
+            candidates: 24,
+            batch: 4,
+        }
+    }
+
+    /// Values of the next trials, as numbers from 0 to 1 per parameter,
+    /// given the trials so far. At most `limit`, and none when the strategy
+    /// is exhausted.
+    fn suggest(&self, space: &SearchSpace, history: &[Trial], limit: usize) -> Vec<Vec<f64>> {
+        let mut rng = crate::rng::thread_rng();
+        let dimensions = space.ranges.len();
+        let random = |rng: &mut crate::rng::ThreadRng| {
+            (0..dimensions)
+                .map(|_| rng.gen::<f64>())
+                .collect::<Vec<_>>()
+        };
+        match *self {
+            Strategy::Random => (0..limit).map(|_| random(&mut rng)).collect(),
+            Strategy::Grid { steps } => {
+                let axes = space
+                    .ranges
+                    .values()
+                    .map(|range| grid_axis(range, steps))
+                    .collect::<Vec<_>>();
+                let size = axes.iter().map(Vec::len).product::<usize>();
+                (history.len()..size.min(history.len() + limit))
+                    .map(|mut index| {
+                        axes.iter()
+                            .map(|axis| {
+                                let u = axis[index % axis.len()];
+                                index /= axis.len();
+                                u
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+            Strategy::Tpe {
+                startup,
+                gamma,
+                candidates,
+                batch,
+            } => (0..limit.min(batch.max(1)))
+                .map(|_| {
+                    if history.len() < startup.max(2) {
+                        random(&mut rng)
+                    } else {
+                        tpe_suggest(space, history, gamma, candidates.max(1), &mut rng)
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The values of a range in grid search, from 0 to 1
+fn grid_axis(range: &Range, steps: usize) -> Vec<f64> {
+    let steps = steps.max(1);
+    let mut axis = (0..steps)
+        .map(|i| {
+            if steps == 1 {
+                0.5
+            } else {
+                i as f64 / (steps - 1) as f64
+            }
+        })
+        .collect::<Vec<_>>();
+    if range.integer {
+        // Snap to the whole numbers, and drop duplicates
+        axis = axis
+            .into_iter()
+            .map(|u| range.unit(range.value(u)))
+            .collect();
+        axis.dedup_by(|a, b| (*a - *b).abs() < 1e-12);
+    }
+    axis
+}
+
+/// Density of values of one parameter, estimated from the values of some
+/// trials: a mixture of a normal distribution around each value, as wide as
+/// the larger gap to its neighbours, and of a uniform prior over the unit
+/// interval, weighed like one more value
+struct Parzen {
+    means: Vec<f64>,
+    sigmas: Vec<f64>,
+}
+
+impl Parzen {
+    fn new(mut means: Vec<f64>) -> Parzen {
+        means.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = means.len();
+        let min_sigma = 1.0 / (n + 1).min(100) as f64;
+        let sigmas = (0..n)
+            .map(|i| {
+                let left = means[i] - if i == 0 { 0.0 } else { means[i - 1] };
+                let right = if i + 1 == n { 1.0 } else { means[i + 1] } - means[i];
+                left.max(right).clamp(min_sigma, 1.0)
+            })
+            .collect();
+        Parzen { means, sigmas }
+    }
+
+    fn density(&self, x: f64) -> f64 {
+        let components = self
+            .means
+            .iter()
+            .zip(&self.sigmas)
+            .map(|(mean, sigma)| {
+                let z = (x - mean) / sigma;
+                (-0.5 * z * z).exp() / (sigma * (2.0 * std::f64::consts::PI).sqrt())
+            })
+            .sum::<f64>();
+        (components + 1.0) / (self.means.len() + 1) as f64
+    }
+
+    fn sample(&self, rng: &mut crate::rng::ThreadRng) -> f64 {
+        let i = rng.gen_range(0, self.means.len() + 1);
+        if i == self.means.len() {
+            rng.gen::<f64>()
+        } else {
+            // Truncated to the unit interval, since clamping would pile the
+            // samples up at the ends
+            let normal = Normal::new(self.means[i], self.sigmas[i]);
+            (0..100)
+                .map(|_| normal.sample(rng))
+                .find(|x| (0.0..=1.0).contains(x))
+                .unwrap_or(self.means[i])
+        }
+    }
+}
+
+fn tpe_suggest(
+    space: &SearchSpace,
+    history: &[Trial],
+    gamma: f64,
+    candidates: usize,
+    rng: &mut crate::rng::ThreadRng,
+) -> Vec<f64> {
+    let mut sorted = history.iter().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let n_good = ((gamma * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len() - 1);
+    let units = sorted.iter().map(|t| space.units(t)).collect::<Vec<_>>();
+    let estimate =
+        |trials: &[Vec<f64>], d: usize| Parzen::new(trials.iter().map(|u| u[d]).collect());
+    let good = (0..space.ranges.len())
+        .map(|d| estimate(&units[..n_good], d))
+        .collect::<Vec<_>>();
+    let bad = (0..space.ranges.len())
+        .map(|d| estimate(&units[n_good..], d))
+        .collect::<Vec<_>>();
+
+    // Of the candidates drawn from the good trials, pick the one where they
+    // are most likely compared to the others
+    let mut best = (f64::NEG_INFINITY, Vec::new());
+    for _ in 0..candidates {
+        let candidate = good.iter().map(|good| good.sample(rng)).collect::<Vec<_>>();
+        let score = candidate
+            .iter()
+            .zip(good.iter().zip(&bad))
+            .map(|(x, (good, bad))| good.density(*x).ln() - bad.density(*x).ln())
+            .sum::<f64>();
+        if score > best.0 {
+            best = (score, candidate);
+        }
+    }
+    best.1
+}
+
+/// The result of evolving with some values of the parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trial {
+    /// Number of the trial, starting from 0
+    pub id: usize,
+    /// Value of every parameter of the search space
+    pub values: IndexMap<String, f64>,
+    /// Fitness of the champion after evolving with each seed
+    pub scores: Vec<f64>,
+    /// Mean of `scores`
+    pub score: f64,
+}
+
+/// The trial with the highest score
+pub fn best(trials: &[Trial]) -> Option<&Trial> {
+    trials.iter().max_by(|a, b| {
+        a.score
+            .partial_cmp(&b.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })
+}
+
+/// Errors while tuning
+#[derive(Debug)]
+pub enum TuneError {
+    /// The search space does not fit the parameters
+    Params(ParamsError),
+    /// The trial log could not be read or written
+    Io(io::Error),
+    /// A line of the trial log is not a trial
+    Log {
+        /// Line number, starting from 1
+        line: usize,
+        /// What is wrong with it
+        message: String,
+    },
+    /// There are no seeds to evolve with, so no trial can be scored
+    NoSeeds,
+}
+
+impl fmt::Display for TuneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TuneError::Params(e) => write!(f, "{}", e),
+            TuneError::Io(e) => write!(f, "could not access trial log: {}", e),
+            TuneError::Log { line, message } => {
+                write!(f, "invalid trial log, line {}: {}", line, message)
+            }
+            TuneError::NoSeeds => write!(f, "no seeds to evolve with"),
+        }
+    }
+}
+
+impl error::Error for TuneError {}
+
+impl From<ParamsError> for TuneError {
+    fn from(e: ParamsError) -> TuneError {
+        TuneError::Params(e)
+    }
+}
+
+impl From<io::Error> for TuneError {
+    fn from(e: io::Error) -> TuneError {
+        TuneError::Io(e)
+    }
+}
+
+/// Runs trials to tune parameters. See the module documentation.
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    space: SearchSpace,
+    base: NeatParams,
+    initial_genome: NeuralNetwork,
+    strategy: Strategy,
+    seeds: Vec<u64>,
+    generations: usize,
+    population_size: usize,
+    log: Option<PathBuf>,
+}
+
+impl Tuner {
+    /// Tune the parameters of `space`, with the others from `base`, evolving
+    /// populations started from `initial_genome`. By default, the search is
+    /// random, and every trial evolves 150 organisms for 100 generations with
+    /// seeds 0, 1 and 2.
+    pub fn new(space: SearchSpace, base: NeatParams, initial_genome: NeuralNetwork) -> Tuner {
+        Tuner {
+            space,
+            base,
+            initial_genome,
+            strategy: Strategy::Random,
+            seeds: vec![0, 1, 2],
+            generations: 100,
+            population_size: 150,
+            log: None,
+        }
+    }
+
+    /// Pick the values of the trials with `strategy`
+    pub fn with_strategy(mut self, strategy: Strategy) -> Tuner {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Evolve once with each of `seeds` per trial
+    pub fn with_seeds(mut self, seeds: Vec<u64>) -> Tuner {
+        self.seeds = seeds;
+        self
+    }
+
+    /// Evolve for `generations` per seed
+    pub fn with_generations(mut self, generations: usize) -> Tuner {
+        self.generations = generations;
+        self
+    }
+
+    /// Evolve populations of `population_size` organisms
+    pub fn with_population_size(mut self, population_size: usize) -> Tuner {
+        self.population_size = population_size;
+        self
+    }
+
+    /// Append finished trials to the file at `path`, and continue from the
+    /// trials in it
+    pub fn with_log<P: AsRef<Path>>(mut self, path: P) -> Tuner {
+        self.log = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// The parameters being tuned
+    pub fn space(&self) -> &SearchSpace {
+        &self.space
+    }
+
+    /// Run trials in `environment` until there are `n_trials`, counting the
+    /// ones of the log, or the strategy is exhausted. `on_trial` is called
+    /// with every new trial. Returns all trials, in order.
+    pub fn run<F: FnMut(&Trial)>(
+        &self,
+        environment: &dyn Environment,
+        n_trials: usize,
+        mut on_trial: F,
+    ) -> Result<Vec<Trial>, TuneError> {
+        if self.seeds.is_empty() {
+            return Err(TuneError::NoSeeds);
+        }
+        self.space.check(&self.base)?;
+        let mut trials = match &self.log {
+            Some(path) if path.exists() => read_log(path)?,
+            _ => Vec::new(),
+        };
+        let mut log = match &self.log {
+            Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+            None => None,
+        };
+
+        while trials.len() < n_trials {
+            let suggestions = self
+                .strategy
+                .suggest(&self.space, &trials, n_trials - trials.len());
+            if suggestions.is_empty() {
+                break;
+            }
+            let candidates = suggestions
+                .iter()
+                .map(|units| {
+                    let values = self.space.point(units);
+                    let params = self.space.apply(&self.base, &values)?;
+                    Ok((values, params))
+                })
+                .collect::<Result<Vec<_>, ParamsError>>()?;
+            let runs = candidates
+                .iter()
+                .flat_map(|candidate| self.seeds.iter().map(move |seed| (candidate, *seed)))
+                .collect::<Vec<_>>();
+            let scores = runs
+                .into_par_iter()
+                .map(|((_, params), seed)| self.evolve(environment, params, seed))
+                .collect::<Vec<_>>();
+
+            for ((values, _), scores) in candidates
+                .into_iter()
+                .zip(scores.chunks(self.seeds.len()))
+            {
+                let scores = scores.to_vec();
+                let trial = Trial {
+                    id: trials.len(),
+                    values,
+                    score: scores.iter().sum::<f64>() / scores.len() as f64,
+                    scores,
+                };
+                if let Some(log) = log.as_mut() {
+                    writeln!(log, "{}", serde_json::to_string(&trial).unwrap())?;
+                }
+                on_trial(&trial);
+                trials.push(trial);
+            }
+        }
+        Ok(trials)
+    }
+
+    /// Fitness of the champion after evolving with `params` from `seed`
+    fn evolve(&self, environment: &dyn Environment, params: &NeatParams, seed: u64) -> f64 {
+        // Everything runs on this thread, which makes the run depend on the
+        // seed only, whatever other trials run at the same time
+        crate::rng::with_seed(seed, || {
+            let mut population = Population::create_population_from(
+                self.initial_genome.clone(),
+                self.population_size,
+            );
+            for _ in 0..self.generations {
+                population.reproduce(params);
+                population.evaluate(environment, false);
+            }
+            population.get_champion().fitness
+        })
+    }
+}
+
+/// Read the trials of a log
+fn read_log(path: &Path) -> Result<Vec<Trial>, TuneError> {
+    let mut trials = Vec::new();
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let trial = serde_json::from_str(&line).map_err(|e| TuneError::Log {
+            line: i + 1,
+            message: e.to_string(),
+        })?;
+        trials.push(trial);
+    }
+    Ok(trials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn space() -> SearchSpace {
+        SearchSpace::default()
+            .with(
+                "weight_mutate_var",
+                Range {
+                    min: 0.01,
+                    max: 1.0,
+                    log: true,
+                    integer: false,
+                },
+            )
+            .with(
+                "species_elite",
+                Range {
+                    min: 1.0,
+                    max: 3.0,
+                    log: false,
+                    integer: true,
+                },
+            )
+    }
+
+    #[test]
+    fn ranges_should_map_to_the_unit_interval_and_back() {
+        let range = &space().ranges["weight_mutate_var"];
+        assert!((range.value(0.0) - 0.01).abs() < 1e-12);
+        assert!((range.value(0.5) - 0.1).abs() < 1e-12);
+        assert!((range.unit(0.1) - 0.5).abs() < 1e-12);
+        let range = &space().ranges["species_elite"];
+        assert_eq!(
+            (0..=10)
+                .map(|i| range.value(i as f64 / 10.0))
+                .collect::<Vec<_>>(),
+            vec![1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 3.0, 3.0, 3.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn grid_should_cover_every_combination_once() {
+        let space = space();
+        let points = Strategy::grid().suggest(&space, &[], 100);
+        assert_eq!(points.len(), 5 * 3);
+        let mut values = points
+            .iter()
+            .map(|units| {
+                let point = space.point(units);
+                (
+                    point["weight_mutate_var"].to_string(),
+                    point["species_elite"] as usize,
+                )
+            })
+            .collect::<Vec<_>>();
+        values.sort();
+        values.dedup();
+        assert_eq!(values.len(), 15);
+    }
+
+    #[test]
+    fn tpe_should_suggest_values_near_the_best_trials() {
+        let space = SearchSpace::default().with(
+            "mutation_pr",
+            Range {
+                min: 0.0,
+                max: 1.0,
+                log: false,
+                integer: false,
+            },
+        );
+        // The score peaks at 0.8
+        let history = (0..40)
+            .map(|id| {
+                let x = id as f64 / 39.0;
+                let score = 1.0 - (x - 0.8).abs();
+                let values = vec![("mutation_pr".to_string(), x)].into_iter().collect();
+                Trial {
+                    id,
+                    values,
+                    scores: vec![score],
+                    score,
+                }
+            })
+            .collect::<Vec<_>>();
+        crate::seed(3);
+        let suggestions = (0..20)
+            .map(|_| tpe_suggest(&space, &history, 0.25, 24, &mut crate::rng::thread_rng())[0])
+            .collect::<Vec<_>>();
+        let mean = suggestions.iter().sum::<f64>() / suggestions.len() as f64;
+        assert!((mean - 0.8).abs() < 0.1, "mean suggestion {}", mean);
+    }
+
+    #[test]
+    fn unknown_parameters_and_empty_ranges_should_be_rejected() {
+        let base = NeatParams::default(2, 1);
+        let unknown = SearchSpace::from_toml("mutation_p = { min = 0.0, max = 1.0 }").unwrap();
+        match unknown.check(&base) {
+            Err(ParamsError::UnknownParameter(name)) => assert_eq!(name, "mutation_p"),
+            other => panic!("{:?}", other),
+        }
+        let empty = SearchSpace::from_toml("mutation_pr = { min = 1.0, max = 0.0 }").unwrap();
+        assert!(empty.check(&base).is_err());
+        let out_of_range =
+            SearchSpace::from_toml("mutation_pr = { min = 0.5, max = 2.0 }").unwrap();
+        match out_of_range.check(&base) {
+            Err(ParamsError::Invalid(problems)) => assert!(problems[0].starts_with("mutation_pr")),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn run_without_seeds_should_fail() {
+        struct X;
+        impl Environment for X {
+            fn test(&self, _: &mut NeuralNetwork) -> f64 {
+                1.0
+            }
+        }
+        let tuner = Tuner::new(
+            space(),
+            NeatParams::default(2, 1),
+            NeuralNetwork::with_neurons(3),
+        )
+        .with_seeds(vec![]);
+        match tuner.run(&X, 1, |_| ()) {
+            Err(TuneError::NoSeeds) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+#![cfg(all(feature = "benchmarks", feature = "evolution"))]
+extern crate rustneat;
+
+use rustneat::benchmarks::{
+    Benchmark, CartPole, CopyTask, DelayedRecall, DoublePole, MountainCar, Parity, TMaze,
+};
+use rustneat::{Environment, NeuralNetwork, Population};
+
+/// Evolve with the benchmark's parameters until the champion solves it.
+/// Returns the generation it was solved in, or the best fitness reached. The
+/// seed is fixed, so that a regression fails the same way every time.
+fn evolve<B: Benchmark>(mut benchmark: B, max_generations: usize) -> Result<usize, f64> {
+    rustneat::seed(42);
+    let params = benchmark.params();
+    let mut population = Population::create_population_from(benchmark.initial_genome(), 150);
+    for generation in 0..max_generations {
+        population.evolve(&mut benchmark, &params, true);
+        if benchmark.solves(&population.get_champion().genome) {
+            return Ok(generation + 1);
+        }
+    }
+    Err(population.get_champion().fitness)
+}
+
+#[test]
+fn cart_pole_should_be_solved() {
+    assert!(evolve(CartPole::new(), 50).is_ok());
+}
+
+#[test]
+fn mountain_car_should_be_solved() {
+    assert!(evolve(MountainCar::new(), 50).is_ok());
+}
+
+#[test]
+#[ignore] // slow, run with `cargo test --release --features benchmarks -- --ignored`
+fn double_pole_with_velocities_should_be_solved() {
+    assert!(evolve(DoublePole::with_velocities(), 500).is_ok());
+}
+
+#[test]
+fn initial_genome_should_fail_every_benchmark() {
+    let benchmarks: Vec<Box<dyn Benchmark>> = vec![
+        Box::new(CartPole::new()),
+        Box::new(MountainCar::new()),
+        Box::new(DoublePole::with_velocities()),
+        Box::new(DoublePole::without_velocities()),
+    ];
+    for benchmark in benchmarks {
+        let mut genome = benchmark.initial_genome();
+        assert!(!benchmark.solves(&genome));
+        assert!(benchmark.test(&mut genome) < 0.5);
+    }
+}
+
+#[test]
+fn delayed_recall_should_be_solved() {
+    assert!(evolve(DelayedRecall::new(3), 100).is_ok());
+}
+
+#[test]
+fn t_maze_should_be_solved() {
+    assert!(evolve(TMaze::new(3), 100).is_ok());
+}
+
+#[test]
+fn memoryless_network_should_score_half_on_sequence_tasks() {
+    let benchmarks: Vec<Box<dyn Benchmark>> = vec![
+        Box::new(Parity::new(3)),
+        Box::new(DelayedRecall::new(4)),
+        Box::new(CopyTask::new(3)),
+        Box::new(TMaze::new(5)),
+    ];
+    for benchmark in benchmarks {
+        let mut genome = benchmark.initial_genome();
+        assert!(!benchmark.solves(&genome));
+        assert_eq!(benchmark.test(&mut genome), 0.5);
+    }
+}
+
+#[test]
+fn latch_should_solve_delayed_recall() {
+    // Neuron 3 is bistable thanks to its self-connection, and is switched on
+    // by a positive bit on input 0
+    let mut latch = NeuralNetwork::with_neurons(4);
+    latch.neurons.get_mut(&0).unwrap().bias = -0.5;
+    latch.neurons.get_mut(&3).unwrap().bias = -1.0;
+    latch.add_connection(0, 3, 1.0);
+    latch.add_connection(3, 3, 2.0);
+    latch.add_connection(3, 2, 1.0);
+    let recall = DelayedRecall::new(20);
+    assert!(recall.solves(&latch));
+    assert!(recall.test(&mut latch.clone()) > 0.95);
+    // Without its self-connection, it forgets the bit
+    latch.connections.swap_remove(&(3, 3));
+    assert!(!recall.solves(&latch));
+}
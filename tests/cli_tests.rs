@@ -0,0 +1,139 @@
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn rustneat(args: &[&str]) -> String {
+    let output = run(args);
+    assert!(
+        output.status.success(),
+        "rustneat {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn run(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_rustneat"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// A fresh directory for the results of a test, with a config for a short
+/// cart-pole run
+fn setup(name: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("rustneat_cli_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    fs::write(
+        &config,
+        format!(
+            "population = 50\nseed = 7\nparallel = false\noutput = {:?}\n\n\
+             [environment]\ntype = \"cart_pole\"\n\n\
+             [params]\ncull_fraction = 0.9\n\n\
+             [stop]\ngenerations = 5\n",
+            dir.join("run")
+        ),
+    )
+    .unwrap();
+    (dir, config)
+}
+
+fn path(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+#[test]
+fn run_should_be_reproducible_with_a_seed() {
+    let (dir, config) = setup("seed");
+    let first = rustneat(&["run", path(&config)]);
+    let second = rustneat(&["run", path(&config)]);
+    assert!(first.contains("generation 5:"));
+    assert_eq!(first, second);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn run_should_reject_solved_outside_benchmarks() {
+    let (dir, config) = setup("solved");
+    let text = fs::read_to_string(&config)
+        .unwrap()
+        .replace("cart_pole", "xor")
+        .replace("generations = 5", "solved = true");
+    fs::write(&config, text).unwrap();
+    let output = run(&["run", path(&config)]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`solved`"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn results_of_run_should_be_usable_by_the_other_commands() {
+    let (dir, config) = setup("commands");
+    rustneat(&["run", path(&config)]);
+    let checkpoint = dir.join("run/checkpoint.json");
+    let champion = dir.join("run/champion.json");
+
+    let resumed = rustneat(&["resume", path(&checkpoint), "--generations", "2"]);
+    assert!(resumed.contains("stopped after generation 7"));
+
+    let stats = rustneat(&["inspect", path(&checkpoint)]);
+    assert!(stats.contains("(4 inputs, 1 outputs"));
+
+    let dot = rustneat(&["render", path(&champion), "--inputs", "4", "--outputs", "1"]);
+    assert!(dot.starts_with("digraph"));
+    let svg = dir.join("champion.svg");
+    rustneat(&[
+        "render",
+        path(&checkpoint),
+        "--format",
+        "svg",
+        "-o",
+        path(&svg),
+    ]);
+    assert!(fs::read_to_string(&svg).unwrap().starts_with("<svg"));
+
+    assert!(rustneat(&["eval", path(&config), path(&champion)]).contains("solved:"));
+
+    let simplified = dir.join("simplified.json");
+    rustneat(&["simplify", path(&checkpoint), "-o", path(&simplified)]);
+    assert_eq!(
+        rustneat(&["eval", path(&config), path(&simplified)]),
+        rustneat(&["eval", path(&config), path(&champion)])
+    );
+    let diff = rustneat(&["diff", path(&checkpoint), path(&simplified)]);
+    assert!(diff.contains("same species"));
+    let json = rustneat(&[
+        "diff",
+        path(&champion),
+        path(&simplified),
+        "--format",
+        "json",
+    ]);
+    assert!(json.contains("\"matching\""));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn tune_should_continue_from_its_trial_log() {
+    let (dir, config) = setup("tune");
+    let ranges = "\n[tune]\ntrials = 3\nseeds = 2\n\n\
+                  [tune.ranges]\nweight_mutate_var = { min = 0.05, max = 1.0, log = true }\n";
+    let mut text = fs::read_to_string(&config).unwrap() + ranges;
+    fs::write(&config, &text).unwrap();
+    assert!(rustneat(&["tune", path(&config)]).contains("trial 2:"));
+
+    text = text.replace("trials = 3", "trials = 5");
+    fs::write(&config, &text).unwrap();
+    let output = rustneat(&["tune", path(&config)]);
+    assert!(!output.contains("trial 2:") && output.contains("trial 4:"));
+    let log = fs::read_to_string(dir.join("run/trials.jsonl")).unwrap();
+    assert_eq!(log.lines().count(), 5);
+    assert!(fs::read_to_string(dir.join("run/params.json"))
+        .unwrap()
+        .contains("weight_mutate_var"));
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,266 @@
+extern crate rustneat;
+
+use rustneat::codegen::{Language, Update};
+use rustneat::model::Model;
+use rustneat::nn::Activation;
+use rustneat::NeuralNetwork;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const INPUTS: [[f64; 2]; 5] = [
+    [0.0, 0.0],
+    [0.0, 1.0],
+    [1.0, 0.5],
+    [-2.0, 3.0],
+    [40.0, -35.0],
+];
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rustneat_codegen_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Acyclic model using every activation function
+fn acyclic_model() -> Model {
+    let mut network = NeuralNetwork::with_neurons(10);
+    network.neurons.get_mut(&4).unwrap().bias = 0.4;
+    network.neurons.get_mut(&5).unwrap().bias = -0.2;
+    network.add_connection(0, 4, 1.5);
+    network.add_connection(1, 4, -0.7);
+    network.add_connection(4, 5, 2.0);
+    network.add_connection(5, 2, -1.0);
+    network.add_connection(0, 3, 0.5);
+    network.add_connection(5, 3, 1.2);
+    for i in 6..10 {
+        network.add_connection(1, i, 0.3 * i as f64);
+        network.add_connection(i, 3, 0.25);
+    }
+    let mut model = network.to_model(2, 2);
+    let activations = [
+        Activation::Tanh,
+        Activation::Relu,
+        Activation::Identity,
+        Activation::Gaussian,
+        Activation::Sine,
+        Activation::Abs,
+    ];
+    for (neuron, activation) in model.neurons.iter_mut().skip(4).zip(&activations) {
+        neuron.activation = *activation;
+    }
+    model
+}
+
+/// Recurrent model with different time constants
+fn recurrent_model() -> Model {
+    let mut network = NeuralNetwork::with_neurons(5);
+    network.neurons.get_mut(&4).unwrap().bias = 0.3;
+    network.add_connection(0, 4, 1.5);
+    network.add_connection(1, 4, -0.7);
+    network.add_connection(4, 2, 2.0);
+    network.add_connection(4, 3, -1.0);
+    network.add_connection(2, 4, 0.5);
+    network.add_connection(3, 3, 0.8);
+    let mut model = network.to_model(2, 2);
+    model.neurons[3].tau = 2.5;
+    model.integration.delta_t = 0.5;
+    model.integration.steps = 20;
+    model
+}
+
+fn expected(model: &Model, update: Update) -> Vec<Vec<f64>> {
+    INPUTS
+        .iter()
+        .map(|input| {
+            let mut output = vec![0.0; 2];
+            match update {
+                Update::FeedForward => model
+                    .to_feed_forward()
+                    .unwrap()
+                    .activate(input.to_vec(), &mut output),
+                Update::Ctrnn => model.to_ctrnn().activate(input.to_vec(), &mut output),
+            }
+            output
+        })
+        .collect()
+}
+
+fn parse_outputs(stdout: &[u8]) -> Vec<Vec<f64>> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|line| {
+            line.split_whitespace()
+                .map(|x| x.parse::<f64>().unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+fn assert_close(expected: &[Vec<f64>], actual: &[Vec<f64>]) {
+    assert_eq!(expected.len(), actual.len());
+    for (e, a) in expected.iter().zip(actual) {
+        assert_eq!(e.len(), a.len());
+        for (e, a) in e.iter().zip(a) {
+            assert!((e - a).abs() < 1e-9, "{} != {}", e, a);
+        }
+    }
+}
+
+fn cases() -> Vec<(&'static str, Model, Update)> {
+    vec![
+        ("acyclic_ff", acyclic_model(), Update::FeedForward),
+        ("acyclic_ctrnn", acyclic_model(), Update::Ctrnn),
+        ("recurrent_ctrnn", recurrent_model(), Update::Ctrnn),
+    ]
+}
+
+#[test]
+fn generated_rust_should_match_interpreted_network() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let dir = scratch_dir("rust");
+    let mut source = String::new();
+    let mut main = String::from("fn main() {\n");
+    for (name, model, update) in cases() {
+        source += &model.generate(name, Language::Rust, update).unwrap();
+        main += &format!(
+            "    for input in INPUTS.iter() {{ let mut output = [0.0; 2]; {}(input, &mut output); \
+             println!(\"{{:?}} {{:?}}\", output[0], output[1]); }}\n",
+            name
+        );
+    }
+    main += "}\n";
+    let inputs = format!("const INPUTS: [[f64; 2]; 5] = {:?};\n", INPUTS);
+
+    // The generated code must not need std
+    fs::write(dir.join("lib.rs"), format!("#![no_std]\n{}", source)).unwrap();
+    let status = Command::new(&rustc)
+        .args([
+            "--crate-type",
+            "rlib",
+            "--edition",
+            "2018",
+            "-D",
+            "warnings",
+            "--out-dir",
+        ])
+        .arg(&dir)
+        .arg(dir.join("lib.rs"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "{}", source);
+
+    fs::write(dir.join("main.rs"), format!("{}{}{}", source, inputs, main)).unwrap();
+    let status = Command::new(&rustc)
+        .args(["--edition", "2018", "-o"])
+        .arg(dir.join("main"))
+        .arg(dir.join("main.rs"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let output = Command::new(dir.join("main")).output().unwrap();
+    let outputs = parse_outputs(&output.stdout);
+    for (i, (_, model, update)) in cases().into_iter().enumerate() {
+        let n = INPUTS.len();
+        assert_close(&expected(&model, update), &outputs[i * n..(i + 1) * n]);
+    }
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn generated_c_should_match_interpreted_network() {
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if Command::new(&cc).arg("--version").output().is_err() {
+        eprintln!("No C compiler found, skipping");
+        return;
+    }
+    let dir = scratch_dir("c");
+    let mut source = String::new();
+    let mut main = String::from("#include <stdio.h>\nint main(void) {\n    int i;\n");
+    for (name, model, update) in cases() {
+        source += &model.generate(name, Language::C, update).unwrap();
+        main += &format!(
+            "    for (i = 0; i < 5; i++) {{ double output[2]; {}(INPUTS[i], output); \
+             printf(\"%.17g %.17g\\n\", output[0], output[1]); }}\n",
+            name
+        );
+    }
+    main += "    return 0;\n}\n";
+    let inputs = format!(
+        "static const double INPUTS[5][2] = {{{}}};\n",
+        INPUTS
+            .iter()
+            .map(|x| format!("{{{:?}, {:?}}}", x[0], x[1]))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    fs::write(
+        dir.join("network.c"),
+        format!("{}{}{}", source, inputs, main),
+    )
+    .unwrap();
+    let status = Command::new(&cc)
+        .args(["-std=c99", "-Wall", "-Werror", "-o"])
+        .arg(dir.join("main"))
+        .arg(dir.join("network.c"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "{}", source);
+    let output = Command::new(dir.join("main")).output().unwrap();
+    let outputs = parse_outputs(&output.stdout);
+    for (i, (_, model, update)) in cases().into_iter().enumerate() {
+        let n = INPUTS.len();
+        assert_close(&expected(&model, update), &outputs[i * n..(i + 1) * n]);
+    }
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn feed_forward_generation_should_reject_recurrent_networks() {
+    assert!(recurrent_model()
+        .generate("network", Language::Rust, Update::FeedForward)
+        .is_none());
+}
+
+#[test]
+fn generation_should_reject_invalid_names_and_parameters() {
+    let model = acyclic_model();
+    for name in &["", "2fast", "my-network", "_"] {
+        assert!(model
+            .generate(name, Language::Rust, Update::FeedForward)
+            .is_none());
+        assert!(model
+            .generate(name, Language::C, Update::FeedForward)
+            .is_none());
+    }
+    // Keywords of one language only
+    assert!(model
+        .generate("fn", Language::Rust, Update::FeedForward)
+        .is_none());
+    assert!(model
+        .generate("fn", Language::C, Update::FeedForward)
+        .is_some());
+    assert!(model
+        .generate("double", Language::C, Update::FeedForward)
+        .is_none());
+    assert!(model
+        .generate("_network2", Language::C, Update::FeedForward)
+        .is_some());
+
+    let mut model = acyclic_model();
+    model.connections[0].weight = f64::INFINITY;
+    assert!(model
+        .generate("network", Language::Rust, Update::FeedForward)
+        .is_none());
+    let mut model = recurrent_model();
+    model.neurons[0].tau = 0.0;
+    assert!(model
+        .generate("network", Language::C, Update::Ctrnn)
+        .is_none());
+    model.neurons[0].tau = 1.0;
+    model.neurons[2].bias = f64::NAN;
+    assert!(model
+        .generate("network", Language::C, Update::Ctrnn)
+        .is_none());
+}
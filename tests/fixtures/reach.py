@@ -0,0 +1,25 @@
+#!/usr/bin/env python3
+"""Stand-in simulator for the ProcessEnvironment tests.
+
+The agent moves along a line by its action, and is rewarded for being close to
+a target, which depends on the episode. Episodes last five steps.
+"""
+import json
+import sys
+
+x = target = 0.0
+steps = 0
+for line in sys.stdin:
+    message = json.loads(line)
+    if message["type"] == "reset":
+        x, target, steps = 0.0, 1.0 + message["episode"], 0
+        reply = {"observation": [x, target]}
+    else:
+        x += message["action"][0]
+        steps += 1
+        reply = {
+            "observation": [x, target],
+            "reward": max(0.0, 1.0 - abs(target - x)),
+            "done": steps >= 5,
+        }
+    print(json.dumps(reply), flush=True)
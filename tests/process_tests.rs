@@ -0,0 +1,122 @@
+extern crate rustneat;
+
+use rustneat::process::{ProcessEnvironment, ProcessError};
+use rustneat::NeuralNetwork;
+use std::process::Command;
+
+fn python() -> Option<&'static str> {
+    if Command::new("python3").arg("--version").output().is_ok() {
+        Some("python3")
+    } else {
+        eprintln!("No python3 found, skipping");
+        None
+    }
+}
+
+fn fixture() -> String {
+    format!("{}/tests/fixtures/reach.py", env!("CARGO_MANIFEST_DIR"))
+}
+
+fn network() -> NeuralNetwork {
+    let mut network = NeuralNetwork::with_neurons(4);
+    network.neurons.get_mut(&3).unwrap().bias = -0.5;
+    network.add_connection(0, 3, -1.0);
+    network.add_connection(1, 3, 0.8);
+    network.add_connection(3, 2, 0.6);
+    network.add_connection(2, 2, 0.2);
+    network
+}
+
+/// The fixture's simulation, run in Rust
+fn expected_fitness(network: &NeuralNetwork, episodes: usize, max_steps: usize) -> f64 {
+    let mut runner = network.make_stateful();
+    let mut total = 0.0;
+    for episode in 0..episodes {
+        runner.reset();
+        let (mut x, target) = (0.0, 1.0 + episode as f64);
+        for _ in 0..max_steps.min(5) {
+            let mut action = [0.0];
+            runner.step(&[x, target], &mut action);
+            x += action[0];
+            total += f64::max(0.0, 1.0 - (target - x).abs());
+        }
+    }
+    total / episodes as f64
+}
+
+#[test]
+fn fitness_should_match_simulation() {
+    let python = match python() {
+        Some(python) => python,
+        None => return,
+    };
+    let network = network();
+    let mut environment = ProcessEnvironment::new(python, &[&fixture()], 1);
+    environment.episodes = 3;
+    let expected = expected_fitness(&network, 3, 5);
+    // The second evaluation reuses the process
+    for _ in 0..2 {
+        let fitness = environment.evaluate(&network).unwrap();
+        assert!(
+            (fitness - expected).abs() < 1e-9,
+            "{} != {}",
+            fitness,
+            expected
+        );
+    }
+
+    environment.max_steps = Some(2);
+    let fitness = environment.evaluate(&network).unwrap();
+    let expected = expected_fitness(&network, 3, 2);
+    assert!(
+        (fitness - expected).abs() < 1e-9,
+        "{} != {}",
+        fitness,
+        expected
+    );
+}
+
+#[test]
+fn misbehaving_processes_should_give_errors() {
+    let python = match python() {
+        Some(python) => python,
+        None => return,
+    };
+    let network = network();
+    let garbage = ProcessEnvironment::new(python, &["-c", "print('hello')"], 1);
+    match garbage.evaluate(&network) {
+        Err(ProcessError::Json(_)) => {}
+        other => panic!("{:?}", other),
+    }
+    let silent = ProcessEnvironment::new(python, &["-c", "pass"], 1);
+    match silent.evaluate(&network) {
+        Err(ProcessError::Exited) | Err(ProcessError::Io(_)) => {}
+        other => panic!("{:?}", other),
+    }
+    let missing = ProcessEnvironment::new("/nonexistent/simulator", &[], 1);
+    match missing.evaluate(&network) {
+        Err(ProcessError::Io(_)) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[cfg(feature = "evolution")]
+#[test]
+fn population_should_evolve_in_process_environment() {
+    use rustneat::{Environment, NeatParams, Population};
+
+    let python = match python() {
+        Some(python) => python,
+        None => return,
+    };
+    let mut environment = ProcessEnvironment::new(python, &[&fixture()], 1);
+    let mut start_genome = NeuralNetwork::with_neurons(3);
+    start_genome.add_connection(1, 2, 0.1);
+    let mut population = Population::create_population_from(start_genome, 20);
+    let p = NeatParams::default(2, 1);
+    for _ in 0..3 {
+        population.evolve(&mut environment, &p, true);
+    }
+    let mut champion = population.get_champion();
+    assert!((champion.fitness - environment.test(&mut champion.genome)).abs() < 1e-9);
+}
@@ -1,5 +1,6 @@
-#[cfg(test)]
+#[cfg(all(test, feature = "evolution"))]
 mod test {
+    use rustneat::dataset::DatasetEnvironment;
     use rustneat::{Environment, NeatParams, NeuralNetwork, Organism, Population};
 
     struct X;
@@ -10,28 +11,6 @@ mod test {
         }
     }
 
-    struct XORClassification;
-
-    impl Environment for XORClassification {
-        fn test(&self, organism: &mut NeuralNetwork) -> f64 {
-            let nn = organism.make_network();
-            let target_inputs = vec![
-                vec![0.0, 0.0],
-                vec![0.0, 1.0],
-                vec![1.0, 0.0],
-                vec![1.0, 1.0],
-            ];
-            let target_outputs = vec![0.0, 1.0, 1.0, 0.0];
-            let mut output = vec![0.0];
-            let mut distance: f64 = 0.0;
-            for (i, o) in target_inputs.iter().zip(target_outputs) {
-                nn.activate(i.clone(), &mut output);
-                distance += (o - output[0]).powi(2);
-            }
-            16.0 / (1.0 + distance)
-        }
-    }
-
     #[test]
     fn can_generate_a_population() {
         let population = Population::<NeuralNetwork>::create_population(150);
@@ -64,7 +43,7 @@ mod test {
         let p = NeatParams::optimized_for_xor3(2, 1);
         let start_genome = NeuralNetwork::with_neurons(3);
         let mut population = Population::create_population_from(start_genome, 150);
-        let mut environment = XORClassification;
+        let mut environment = DatasetEnvironment::xor();
         let mut champion: Option<Organism> = None;
         let mut i = 0;
         while champion.is_none() && i < MAX_GENERATIONS {
@@ -95,7 +74,7 @@ mod test {
         const MAX_GENERATIONS: usize = 200;
         let p = NeatParams::default(2, 1);
         let mut population = Population::create_population(150);
-        let mut environment = XORClassification;
+        let mut environment = DatasetEnvironment::xor();
         let mut best_fitness = std::f64::MIN;
         for _ in 0..MAX_GENERATIONS {
             population.evolve(&mut environment, &p,true);